@@ -32,6 +32,15 @@ pub enum Schedule {
     },
 }
 
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule::Interval {
+            // Default to once every half second
+            period_millis: 500,
+        }
+    }
+}
+
 fn concurrency_default() -> u8 {
     1
 }
@@ -50,8 +59,8 @@ pub struct ComponentMetadata {
 impl Display for ComponentMetadata {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "[{:?}:{}:{}]",
-            self.component_type, self.type_name, self.id
+            "[{:?}:{}:{}:{}]",
+            self.component_type, self.type_name, self.display_name, self.id
         ))
     }
 }
@@ -61,7 +70,8 @@ impl ComponentMetadata {
         let type_name: String = def.type_name.clone();
 
         ComponentMetadata {
-            id: nanoid!(),
+            // Share the definition id so logs and bulletins can be traced back to it
+            id: def.id.clone(),
             type_name,
             display_name: def.display_name.clone(),
             component_type: def.component_type.clone(),
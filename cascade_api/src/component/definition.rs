@@ -2,6 +2,7 @@ use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use crate::component::component::Schedule;
+use crate::connection::definition::present;
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,18 +20,122 @@ pub struct ComponentDefinition {
 
     pub type_name: String,
     pub component_type: ComponentType,
-    #[serde(default = "schedule_default")]
-    pub schedule: Schedule,
+    // Left out to use the default schedule of the registry creating the component
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<Schedule>,
 
     pub config: Value,
+    // Where #{name} references in the config are resolved from
+    #[serde(default)]
+    pub parameter_context: Option<String>,
 }
 
 fn id_default() -> String {
     nanoid!()
 }
-fn schedule_default() -> Schedule {
-    Schedule::Interval{
-        // Default to once every half second
-        period_millis: 500,
+
+/// Changes to an existing component, anything not given is left as it is
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct ComponentUpdate {
+    pub display_name: Option<String>,
+    pub schedule: Option<Schedule>,
+    pub config: Option<Value>,
+    // Given as null to stop using a parameter context
+    #[serde(default, deserialize_with = "present")]
+    pub parameter_context: Option<Option<String>>,
+}
+
+/// A single value changed by an update, config keys are given as config.key
+#[derive(Clone, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+// Record where two values differ, descending into objects so only changed keys are listed
+// Keys of the top level object are used as they are if no field is given
+pub(crate) fn diff_values(
+    field: String,
+    before: &Value,
+    after: &Value,
+    changes: &mut Vec<FieldChange>,
+) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let field: String = match field.is_empty() {
+                    true => key.clone(),
+                    false => format!("{}.{}", field, key),
+                };
+
+                diff_values(
+                    field,
+                    before.get(key).unwrap_or(&Value::Null),
+                    after.get(key).unwrap_or(&Value::Null),
+                    changes,
+                );
+            }
+        }
+        _ if before != after => changes.push(FieldChange {
+            field,
+            before: before.clone(),
+            after: after.clone(),
+        }),
+        _ => {}
     }
-}
\ No newline at end of file
+}
+
+impl ComponentDefinition {
+    /// Apply an update and return what actually changed
+    pub fn apply_update(&mut self, update: ComponentUpdate) -> Vec<FieldChange> {
+        let mut changes: Vec<FieldChange> = vec![];
+
+        if let Some(display_name) = update.display_name {
+            diff_values(
+                "display_name".to_string(),
+                &Value::from(self.display_name.as_str()),
+                &Value::from(display_name.as_str()),
+                &mut changes,
+            );
+            self.display_name = display_name;
+        }
+
+        if let Some(schedule) = update.schedule {
+            // Schedules always serialise
+            let before: Value = serde_json::to_value(&self.schedule).unwrap();
+            let after: Value = serde_json::to_value(&schedule).unwrap();
+
+            // Schedules are replaced whole, so aren't diffed key by key
+            if before != after {
+                changes.push(FieldChange {
+                    field: "schedule".to_string(),
+                    before,
+                    after,
+                });
+            }
+            self.schedule = Some(schedule);
+        }
+
+        if let Some(config) = update.config {
+            diff_values("config".to_string(), &self.config, &config, &mut changes);
+            self.config = config;
+        }
+
+        if let Some(parameter_context) = update.parameter_context {
+            diff_values(
+                "parameter_context".to_string(),
+                &Value::from(self.parameter_context.as_deref()),
+                &Value::from(parameter_context.as_deref()),
+                &mut changes,
+            );
+            self.parameter_context = parameter_context;
+        }
+
+        changes
+    }
+}
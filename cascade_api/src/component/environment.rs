@@ -1,60 +1,164 @@
 use std::collections::HashMap;
 
-use async_channel::{Receiver, Sender};
-use futures::stream::{select_all, SelectAll};
+use std::sync::Arc;
+
+use async_channel::Receiver;
+use futures::stream::{select_all, BoxStream, SelectAll};
 use futures::StreamExt;
 
 use crate::component::component::ComponentMetadata;
 use crate::component::error::ComponentError;
+use crate::component::logger::ComponentLogger;
+use crate::component::state::ComponentState;
 use crate::connection::ComponentChannels;
 use crate::connection::definition::DEFAULT_CONNECTION;
+use crate::connection::distribution::NamedOutput;
+use crate::connection::queue::MessageQueue;
+use crate::message::trace::{now_nanos, Span, SpanStatus, TraceContext};
 use crate::message::{InternalMessage, Message};
 
-/// Wraps async-channel receivers to create a fused stream
+/// Wraps connection queues and the signal channel to create a fused stream
 /// Multiple input streams can then be read from the same stream
-pub struct FusedStream<Message> {
-    select_all: SelectAll<Receiver<Message>>,
+pub struct FusedStream {
+    select_all: SelectAll<BoxStream<'static, InternalMessage>>,
 }
 
-impl<Message> FusedStream<Message> {
-    pub fn new(receivers: Vec<Receiver<Message>>) -> FusedStream<Message> {
+impl FusedStream {
+    pub fn new(
+        queues: Vec<Arc<MessageQueue>>,
+        rx_signal: Receiver<InternalMessage>,
+    ) -> FusedStream {
+        let mut streams: Vec<BoxStream<'static, InternalMessage>> = queues
+            .iter()
+            .map(|queue| queue.stream().map(InternalMessage::Item).boxed())
+            .collect();
+
+        streams.push(rx_signal.boxed());
+
         FusedStream {
-            select_all: select_all(receivers),
+            select_all: select_all(streams),
         }
     }
 
-    pub(crate) async fn recv(&mut self) -> Option<Message> {
+    pub(crate) async fn recv(&mut self) -> Option<InternalMessage> {
         self.select_all.next().await
     }
 }
 
 pub struct ExecutionEnvironment {
     pub metadata: ComponentMetadata,
+    // Tags records with the component they came from
+    pub logger: ComponentLogger,
+    // Kept across restarts of the component
+    pub state: ComponentState,
 
     // Connections which can be ignored if they don't exist
     ignore_connections: Vec<String>,
 
     in_progress: Option<Message>,
 
-    rx: FusedStream<InternalMessage>,
-    tx_named: HashMap<String, Sender<InternalMessage>>,
+    rx: FusedStream,
+    tx_named: HashMap<String, NamedOutput>,
+
+    // Spans opened during the current invocation of process
+    invocation_start_nanos: u128,
+    open_spans: Vec<Span>,
 }
 
 impl ExecutionEnvironment {
-    pub fn new(metadata: ComponentMetadata, channels: ComponentChannels) -> ExecutionEnvironment {
+    pub fn new(
+        metadata: ComponentMetadata,
+        channels: ComponentChannels,
+        logger: ComponentLogger,
+    ) -> ExecutionEnvironment {
         ExecutionEnvironment {
             metadata,
+            logger,
+            state: Default::default(),
             ignore_connections: vec![DEFAULT_CONNECTION.to_string()],
             in_progress: None,
-            rx: FusedStream::new(channels.rx),
+            rx: FusedStream::new(channels.rx, channels.rx_signal),
             tx_named: channels.tx_named,
+            invocation_start_nanos: now_nanos(),
+            open_spans: vec![],
         }
     }
 
+    // Messages sent to the output are dropped if it isn't connected
+    pub fn ignore_output(&mut self, name: &str) {
+        self.ignore_connections.push(name.to_string());
+    }
+
+    // Called by the runtime before each invocation of process
+    pub fn begin_invocation(&mut self) {
+        self.invocation_start_nanos = now_nanos();
+    }
+
+    /// Called by the runtime after each invocation of process
+    /// Closes and returns the spans for every message handled in the invocation
+    pub fn end_invocation(&mut self, result: &Result<(), ComponentError>) -> Vec<Span> {
+        let end_nanos: u128 = now_nanos();
+        let status: SpanStatus = match result {
+            Ok(()) => SpanStatus::Ok,
+            Err(err) => SpanStatus::Error(format!("{:?}", err)),
+        };
+
+        self.open_spans
+            .drain(..)
+            .map(|mut span| {
+                span.end_nanos = end_nanos;
+                span.status = status.clone();
+
+                span
+            })
+            .collect()
+    }
+
+    fn open_span(&mut self, trace: &TraceContext, start_nanos: u128) -> &mut Span {
+        let span: Span = Span::start(
+            trace,
+            self.metadata.display_name.clone(),
+            self.metadata.id.clone(),
+            self.metadata.type_name.clone(),
+            start_nanos,
+        );
+
+        self.open_spans.push(span);
+        self.open_spans.last_mut().unwrap()
+    }
+
+    // Make the span handling this message the parent of its next hop
+    fn trace_outgoing(&mut self, item: &mut Message) {
+        let existing: Option<&Span> = self
+            .open_spans
+            .iter()
+            .rev()
+            .find(|span| span.trace_id == item.trace.trace_id);
+
+        let span_id: String = match existing {
+            Some(span) => span.span_id.clone(),
+            // Messages created by this component are traced from the start of the invocation
+            None => {
+                let start_nanos: u128 = self.invocation_start_nanos;
+
+                self.open_span(&item.trace.clone(), start_nanos)
+                    .span_id
+                    .clone()
+            }
+        };
+
+        item.trace.parent_span_id = Some(span_id);
+    }
+
     // Get a single item from the session
     pub async fn recv(&mut self) -> Result<&Message, ComponentError> {
         match self.rx.recv().await.ok_or(ComponentError::InputClosed)? {
             InternalMessage::Item(item) => {
+                self.logger.set_message_id(Some(item.id.clone()));
+
+                let span: &mut Span = self.open_span(&item.trace, now_nanos());
+                span.message_id = Some(item.id.clone());
+
                 // Store the item in-progress
                 Ok(self.in_progress.insert(item))
             }
@@ -62,16 +166,19 @@ impl ExecutionEnvironment {
         }
     }
 
-    pub async fn send(&mut self, name: &str, item: Message) -> Result<(), ComponentError> {
+    pub async fn send(&mut self, name: &str, mut item: Message) -> Result<(), ComponentError> {
+        if self.tx_named.contains_key(name) {
+            self.trace_outgoing(&mut item);
+        }
+
         match self.tx_named.get(name) {
-            Some(connection) => {
-                connection
-                    .send(InternalMessage::Item(item))
-                    .await
-                    .or(Err(ComponentError::OutputClosed))?;
+            Some(output) => {
+                // Waits while the connections are full
+                output.send(item).await;
 
                 // Remove the item from in-progress
                 self.in_progress.take();
+                self.logger.set_message_id(None);
 
                 Ok(())
             }
@@ -1,5 +1,8 @@
+use std::fmt::{Display, Formatter};
 use std::io::Error;
 
+use crate::expression::ExpressionError;
+
 #[derive(Debug)]
 pub enum ComponentError {
     ComponentShutdown,
@@ -10,6 +13,27 @@ pub enum ComponentError {
     // Errors from underlying processor
     IOError(Error),
     RuntimeError(String),
+    // Config doesn't match what the component type expects
+    InvalidConfig(String),
+}
+
+impl Display for ComponentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComponentError::ComponentShutdown => f.write_str("Component was shut down"),
+            ComponentError::InputClosed => f.write_str("Input connection closed"),
+            ComponentError::OutputClosed => f.write_str("Output connection closed"),
+            ComponentError::MissingInput => f.write_str("Component has no input connection"),
+            ComponentError::MissingOutput(name) => {
+                f.write_fmt(format_args!("Component has no output named {}", name))
+            }
+            ComponentError::IOError(err) => err.fmt(f),
+            ComponentError::RuntimeError(err) => f.write_str(err),
+            ComponentError::InvalidConfig(err) => {
+                f.write_fmt(format_args!("Invalid config: {}", err))
+            }
+        }
+    }
 }
 
 impl From<Error> for ComponentError {
@@ -17,3 +41,15 @@ impl From<Error> for ComponentError {
         ComponentError::IOError(value)
     }
 }
+
+impl From<serde_json::Error> for ComponentError {
+    fn from(value: serde_json::Error) -> Self {
+        ComponentError::InvalidConfig(value.to_string())
+    }
+}
+
+impl From<ExpressionError> for ComponentError {
+    fn from(value: ExpressionError) -> Self {
+        ComponentError::RuntimeError(value.to_string())
+    }
+}
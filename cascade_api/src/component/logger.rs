@@ -0,0 +1,141 @@
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use log::{Level, LevelFilter, Record};
+
+use crate::component::component::ComponentMetadata;
+
+// Records from components are logged under this target followed by the component type
+pub const COMPONENT_TARGET: &str = "component";
+
+/// Notified of every record a component logs above its level
+/// Used by the runtime to raise bulletins from warnings and errors
+pub trait LogListener: Send + Sync {
+    fn on_log(&self, metadata: &ComponentMetadata, level: Level, message: &str);
+}
+
+/// Log level which can be changed while a component is running
+pub struct ComponentLogLevel(AtomicUsize);
+
+impl Default for ComponentLogLevel {
+    fn default() -> Self {
+        ComponentLogLevel::new(LevelFilter::Info)
+    }
+}
+
+impl ComponentLogLevel {
+    pub fn new(level: LevelFilter) -> ComponentLogLevel {
+        ComponentLogLevel(AtomicUsize::new(level as usize))
+    }
+
+    pub fn get(&self) -> LevelFilter {
+        match self.0.load(Ordering::Relaxed) {
+            0 => LevelFilter::Off,
+            1 => LevelFilter::Error,
+            2 => LevelFilter::Warn,
+            3 => LevelFilter::Info,
+            4 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    }
+
+    pub fn set(&self, level: LevelFilter) {
+        self.0.store(level as usize, Ordering::Relaxed)
+    }
+}
+
+/// Logger handed to components which tags each record with the component it came from
+#[derive(Clone)]
+pub struct ComponentLogger {
+    metadata: ComponentMetadata,
+    // Used as the record target so components can be filtered by type
+    target: String,
+
+    level: Arc<ComponentLogLevel>,
+    listener: Option<Arc<dyn LogListener>>,
+
+    // Message currently being processed, included as a field on records
+    message_id: Option<String>,
+}
+
+impl ComponentLogger {
+    pub fn new(
+        metadata: ComponentMetadata,
+        level: Arc<ComponentLogLevel>,
+        listener: Option<Arc<dyn LogListener>>,
+    ) -> ComponentLogger {
+        ComponentLogger {
+            target: component_target(&metadata.type_name),
+            metadata,
+            level,
+            listener,
+            message_id: None,
+        }
+    }
+
+    pub(crate) fn set_message_id(&mut self, message_id: Option<String>) {
+        self.message_id = message_id;
+    }
+
+    pub fn enabled(&self, level: Level) -> bool {
+        level <= self.level.get()
+    }
+
+    pub fn log<M: Display>(&self, level: Level, message: M) {
+        if !self.enabled(level) {
+            return;
+        }
+
+        let message: String = message.to_string();
+
+        let mut fields: Vec<(&str, &str)> = vec![
+            ("component_id", &self.metadata.id),
+            ("component_type", &self.metadata.type_name),
+            ("display_name", &self.metadata.display_name),
+        ];
+        if let Some(message_id) = &self.message_id {
+            fields.push(("message_id", message_id));
+        }
+
+        // The level of the component is what filters its records, the logger doesn't filter them again
+        log::logger().log(
+            &Record::builder()
+                .args(format_args!("{} {}", self.metadata, message))
+                .level(level)
+                .target(&self.target)
+                .module_path(Some(&self.target))
+                .key_values(&fields.as_slice())
+                .build(),
+        );
+
+        if let Some(listener) = &self.listener {
+            listener.on_log(&self.metadata, level, &message);
+        }
+    }
+
+    pub fn error<M: Display>(&self, message: M) {
+        self.log(Level::Error, message)
+    }
+
+    pub fn warn<M: Display>(&self, message: M) {
+        self.log(Level::Warn, message)
+    }
+
+    pub fn info<M: Display>(&self, message: M) {
+        self.log(Level::Info, message)
+    }
+
+    pub fn debug<M: Display>(&self, message: M) {
+        self.log(Level::Debug, message)
+    }
+
+    pub fn trace<M: Display>(&self, message: M) {
+        self.log(Level::Trace, message)
+    }
+}
+
+/// Target records from components of the type are logged under
+pub fn component_target(type_name: &str) -> String {
+    format!("{}::{}", COMPONENT_TARGET, type_name)
+}
@@ -5,24 +5,46 @@ use serde_json::Value;
 use crate::component::environment::ExecutionEnvironment;
 use crate::component::error::ComponentError;
 
+#[allow(clippy::module_inception)]
 pub mod component;
 pub mod definition;
 pub mod environment;
 pub mod error;
+pub mod logger;
+pub mod state;
 
 /// Implemented by all components to statically define type name
 pub trait NamedComponent {
     fn type_name() -> &'static str
     where
         Self: Sized;
+
+    /// Config properties which hold secrets, so are encrypted when stored and masked when returned
+    fn sensitive_properties() -> &'static [&'static str]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
 }
 
 /// Implemented by a either a Producer or Processor component
 #[async_trait]
 pub trait Process: NamedComponent + Send + Sync {
-    fn create_from_json(config: Value) -> Arc<dyn Process>
+    /// This will fail if the config doesn't match what the component expects
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError>
     where
         Self: Sized;
 
     async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError>;
+
+    /// Outputs which must be connected for the component to start, so messages aren't lost
+    fn required_outputs(&self) -> Vec<&str> {
+        vec![]
+    }
+
+    /// Outputs which messages are dropped from if they aren't connected
+    fn optional_outputs(&self) -> Vec<&str> {
+        vec![]
+    }
 }
@@ -0,0 +1,68 @@
+use std::fs;
+use std::fs::File;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::component::error::ComponentError;
+
+/// State a component keeps across restarts, stored as JSON in the state directory by component id
+/// Nothing is stored if no state directory is configured, so state only lasts while running
+#[derive(Clone, Default)]
+pub struct ComponentState {
+    path: Option<PathBuf>,
+}
+
+impl ComponentState {
+    pub fn new(directory: Option<&Path>, component_id: &str) -> ComponentState {
+        ComponentState {
+            path: directory.map(|directory| directory.join(format!("{}.json", component_id))),
+        }
+    }
+
+    /// Read the stored state, None if nothing has been stored yet
+    /// This will fail if the state can't be read or doesn't match what the component expects
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Option<T>, ComponentError> {
+        let Some(path) = &self.path else {
+            return Ok(None);
+        };
+
+        let contents: String = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Some(serde_json::from_str(&contents).map_err(|err| {
+            ComponentError::RuntimeError(format!("State in {} is invalid: {}", path.display(), err))
+        })?))
+    }
+
+    /// Replace the stored state, written to a temporary file first so it's never left half written
+    pub fn save<T: Serialize>(&self, state: &T) -> Result<(), ComponentError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let contents: String = serde_json::to_string(state)
+            .map_err(|err| ComponentError::RuntimeError(err.to_string()))?;
+
+        // Synced before the rename, so a crash can't leave an empty or partial file in its place
+        let temporary: PathBuf = path.with_extension("json.tmp");
+        let mut file: File = File::create(&temporary)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+
+        fs::rename(&temporary, path)?;
+
+        // The rename only survives a crash once the directory holding it is synced
+        #[cfg(unix)]
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            File::open(directory)?.sync_all()?;
+        }
+
+        Ok(())
+    }
+}
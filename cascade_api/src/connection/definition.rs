@@ -1,5 +1,11 @@
 use nanoid::nanoid;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::component::definition::{diff_values, FieldChange};
+use crate::connection::distribution::Distribution;
+use crate::connection::error::ConnectionError;
+use crate::connection::prioritizer::Prioritizer;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionDefinition {
@@ -11,6 +17,15 @@ pub struct ConnectionDefinition {
     pub target: usize,
 
     pub max_items: usize,
+    // Messages older than this are dropped instead of being received
+    #[serde(default)]
+    pub expiration_millis: Option<u64>,
+    // Order in which queued messages are received, FIFO if empty
+    #[serde(default)]
+    pub prioritizers: Vec<Prioritizer>,
+    // Must match any other connection from the source with the same name
+    #[serde(default)]
+    pub distribution: Distribution,
 }
 
 fn id_default() -> String {
@@ -28,6 +43,75 @@ impl ConnectionDefinition {
             source: from,
             target: to,
             max_items: DEFAULT_MAX_ITEMS,
+            expiration_millis: None,
+            prioritizers: vec![],
+            distribution: Default::default(),
+        }
+    }
+}
+
+/// Changes to an existing connection, anything not given is left as it is
+#[derive(Clone, Default, Deserialize)]
+pub struct ConnectionUpdate {
+    pub name: Option<String>,
+    // Index of the component to redirect the connection to
+    pub target: Option<usize>,
+    pub max_items: Option<usize>,
+    // Given as null to remove the expiration
+    #[serde(default, deserialize_with = "present")]
+    pub expiration_millis: Option<Option<u64>>,
+    pub prioritizers: Option<Vec<Prioritizer>>,
+    pub distribution: Option<Distribution>,
+}
+
+// Distinguish a field given as null from one which is missing
+pub(crate) fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+impl ConnectionDefinition {
+    /// Apply an update and return what actually changed
+    pub fn apply_update(&mut self, update: ConnectionUpdate) -> Vec<FieldChange> {
+        // Definitions always serialise
+        let before: Value = serde_json::to_value(&*self).unwrap();
+
+        if let Some(name) = update.name {
+            self.name = name;
         }
+        if let Some(target) = update.target {
+            self.target = target;
+        }
+        if let Some(max_items) = update.max_items {
+            self.max_items = max_items;
+        }
+        if let Some(expiration_millis) = update.expiration_millis {
+            self.expiration_millis = expiration_millis;
+        }
+        if let Some(prioritizers) = update.prioritizers {
+            self.prioritizers = prioritizers;
+        }
+        if let Some(distribution) = update.distribution {
+            self.distribution = distribution;
+        }
+
+        let after: Value = serde_json::to_value(&*self).unwrap();
+
+        let mut changes: Vec<FieldChange> = vec![];
+        diff_values(String::new(), &before, &after, &mut changes);
+
+        changes
+    }
+    /// Check the connection could be used to pass messages between components
+    /// This will fail if the connection can't hold any items, as sending to it would never finish
+    pub fn validate(&self) -> Result<(), ConnectionError> {
+        if self.max_items == 0 {
+            return Err(ConnectionError::ZeroCapacity(self.id.clone()));
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,84 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::connection::queue::MessageQueue;
+use crate::message::Message;
+
+/// How messages sent to a named output are spread across its connections
+/// Only matters when more than one connection shares the same name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Distribution {
+    // Every connection receives a copy of the message
+    #[default]
+    Clone,
+    // Connections take turns receiving messages
+    RoundRobin,
+    // The connection with the fewest queued messages receives it
+    LeastQueued,
+    // Messages with the same property value always go to the same connection
+    Partition { property: String },
+}
+
+/// All connections from a component sharing the same name
+#[derive(Clone)]
+pub struct NamedOutput {
+    pub distribution: Distribution,
+    pub queues: Vec<Arc<MessageQueue>>,
+
+    // Shared between concurrent tasks for round robin
+    next: Arc<AtomicUsize>,
+}
+
+impl NamedOutput {
+    pub fn new(distribution: Distribution) -> NamedOutput {
+        NamedOutput {
+            distribution,
+            queues: vec![],
+            next: Default::default(),
+        }
+    }
+
+    // Send according to the distribution, waiting while target connections are full
+    pub async fn send(&self, message: Message) {
+        match &self.distribution {
+            Distribution::Clone => {
+                if let Some((last, rest)) = self.queues.split_last() {
+                    for queue in rest {
+                        queue.push(message.clone()).await;
+                    }
+
+                    last.push(message).await;
+                }
+            }
+            Distribution::RoundRobin => {
+                let index: usize = self.next.fetch_add(1, Ordering::Relaxed) % self.queues.len();
+
+                self.queues[index].push(message).await;
+            }
+            Distribution::LeastQueued => {
+                // Ties go to the first connection
+                let queue: &Arc<MessageQueue> = self
+                    .queues
+                    .iter()
+                    .min_by_key(|queue| queue.len())
+                    .unwrap();
+
+                queue.push(message).await;
+            }
+            Distribution::Partition { property } => {
+                let mut hasher: DefaultHasher = DefaultHasher::new();
+                // Messages missing the property all land in the same partition
+                message.properties.get(property).hash(&mut hasher);
+
+                let index: usize = (hasher.finish() % self.queues.len() as u64) as usize;
+
+                self.queues[index].push(message).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum ConnectionError {
+    // Nothing could ever be sent to a connection which holds no items
+    ZeroCapacity(String),
+}
+
+impl Display for ConnectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectionError::ZeroCapacity(id) => f.write_fmt(format_args!(
+                "Connection {} must allow at least one queued item",
+                id
+            )),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use crate::message::Message;
+
+/// Selects messages on a connection by their properties or age
+/// An empty filter matches every message
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MessageFilter {
+    // Properties which must all be present with an equal value
+    #[serde(default)]
+    pub properties: HashMap<String, String>,
+    // Only match messages created longer ago than this
+    #[serde(default)]
+    pub older_than_millis: Option<u64>,
+}
+
+impl MessageFilter {
+    pub fn matches(&self, message: &Message) -> bool {
+        let properties_match: bool = self
+            .properties
+            .iter()
+            .all(|(key, value)| message.properties.get(key) == Some(value));
+
+        let age_matches: bool = match self.older_than_millis {
+            None => true,
+            Some(older_than_millis) => {
+                let now: u128 = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos();
+
+                now.saturating_sub(message.created_nanos) > older_than_millis as u128 * 1_000_000
+            }
+        };
+
+        properties_match && age_matches
+    }
+}
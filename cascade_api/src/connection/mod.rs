@@ -1,42 +1,90 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-use async_channel::{bounded, Receiver, Sender};
+use async_channel::{Receiver, Sender};
+use serde::Serialize;
 
 use definition::ConnectionDefinition;
+use distribution::NamedOutput;
+use filter::MessageFilter;
+use queue::MessageQueue;
 
-use crate::message::InternalMessage;
+use crate::message::{InternalMessage, Message};
 
 pub mod definition;
+pub mod distribution;
+pub mod error;
+pub mod filter;
+pub mod prioritizer;
+pub mod queue;
 
 #[derive(Clone)]
 pub struct Connection {
     pub name: String,
-    pub max_items: usize,
 
-    pub rx: Receiver<InternalMessage>,
-    pub tx: Sender<InternalMessage>,
+    pub queue: Arc<MessageQueue>,
 }
 
 impl Connection {
     pub fn new(def: &ConnectionDefinition) -> Connection {
-        let (tx, rx): (Sender<InternalMessage>, Receiver<InternalMessage>) = bounded(def.max_items);
-
         Connection {
             name: def.name.clone(),
-            max_items: def.max_items,
-            rx,
-            tx
+            queue: Arc::new(MessageQueue::new(
+                def.max_items,
+                def.expiration_millis.map(Duration::from_millis),
+                def.prioritizers.clone(),
+            )),
         }
     }
+
+    /// Apply changed settings to the connection, keeping anything already queued
+    pub fn reconfigure(&mut self, def: &ConnectionDefinition) {
+        self.name = def.name.clone();
+        self.queue.reconfigure(
+            def.max_items,
+            def.expiration_millis.map(Duration::from_millis),
+            def.prioritizers.clone(),
+        );
+    }
+
+    /// Remove all queued messages matching the filter and return them
+    pub fn purge(&self, filter: &MessageFilter) -> Vec<Message> {
+        self.queue.purge(filter)
+    }
+
+    // Next messages to be received, without removing them
+    pub fn peek(&self, limit: usize) -> Vec<Message> {
+        self.queue.peek(limit)
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        ConnectionStats {
+            name: self.name.clone(),
+            count: self.queue.len(),
+            max_items: self.queue.capacity(),
+            expired: self.queue.expired_count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionStats {
+    pub name: String,
+    pub count: usize,
+    pub max_items: usize,
+    // Messages dropped for exceeding the connection expiration
+    pub expired: usize,
 }
 
 #[derive(Clone)]
 pub struct ComponentChannels {
     // Incoming connections
-    pub rx: Vec<Receiver<InternalMessage>>,
-    // Sender to dispatch signals to the component
+    pub rx: Vec<Arc<MessageQueue>>,
+    // Channel to dispatch signals to the component
+    pub rx_signal: Receiver<InternalMessage>,
     pub tx_signal: Sender<InternalMessage>,
 
     // Named output connections
-    pub tx_named: HashMap<String, Sender<InternalMessage>>,
+    pub tx_named: HashMap<String, NamedOutput>,
 }
@@ -0,0 +1,176 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::message::Message;
+
+/// Strategy used to order messages on a connection
+/// Multiple prioritizers are applied in turn until one separates two messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Prioritizer {
+    // First queued is first out
+    Fifo,
+    // Last queued is first out
+    NewestFirst,
+    // Messages created earliest are first out
+    OldestCreatedFirst,
+    // Lowest value of the property is first out
+    // Numbers are compared numerically and messages missing the property go last
+    Property { name: String },
+}
+
+/// Message held on a queue with its arrival order
+pub(crate) struct QueuedMessage {
+    pub(crate) sequence: u64,
+    pub(crate) message: Message,
+}
+
+impl Prioritizer {
+    // Less means a is dequeued before b
+    fn compare(&self, a: &QueuedMessage, b: &QueuedMessage) -> Ordering {
+        match self {
+            Prioritizer::Fifo => a.sequence.cmp(&b.sequence),
+            Prioritizer::NewestFirst => b.sequence.cmp(&a.sequence),
+            Prioritizer::OldestCreatedFirst => {
+                a.message.created_nanos.cmp(&b.message.created_nanos)
+            }
+            Prioritizer::Property { name } => compare_property(
+                a.message.properties.get(name),
+                b.message.properties.get(name),
+            ),
+        }
+    }
+}
+
+fn compare_property(a: Option<&String>, b: Option<&String>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => a.total_cmp(&b),
+            _ => a.cmp(b),
+        },
+        // Messages with the property come first
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// Compare using each prioritizer in turn, falling back to arrival order
+pub(crate) fn compare_chain(
+    prioritizers: &[Prioritizer],
+    a: &QueuedMessage,
+    b: &QueuedMessage,
+) -> Ordering {
+    prioritizers
+        .iter()
+        .map(|prioritizer| prioritizer.compare(a, b))
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.sequence.cmp(&b.sequence))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::collections::HashMap;
+
+    use crate::connection::prioritizer::{compare_chain, Prioritizer, QueuedMessage};
+    use crate::message::Message;
+
+    fn queued(sequence: u64, properties: &[(&str, &str)]) -> QueuedMessage {
+        let properties: HashMap<String, String> = properties
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        QueuedMessage {
+            sequence,
+            message: Message::new(properties),
+        }
+    }
+
+    fn by_priority(name: &str) -> Vec<Prioritizer> {
+        vec![Prioritizer::Property {
+            name: name.to_string(),
+        }]
+    }
+
+    #[test]
+    fn no_prioritizers_keeps_arrival_order() {
+        assert_eq!(compare_chain(&[], &queued(0, &[]), &queued(1, &[])), Ordering::Less);
+    }
+
+    #[test]
+    fn newest_first_reverses_arrival_order() {
+        let prioritizers: Vec<Prioritizer> = vec![Prioritizer::NewestFirst];
+
+        assert_eq!(
+            compare_chain(&prioritizers, &queued(0, &[]), &queued(1, &[])),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn oldest_created_first_ignores_arrival_order() {
+        let prioritizers: Vec<Prioritizer> = vec![Prioritizer::OldestCreatedFirst];
+
+        let mut older: QueuedMessage = queued(1, &[]);
+        older.message.created_nanos = 0;
+
+        assert_eq!(compare_chain(&prioritizers, &older, &queued(0, &[])), Ordering::Less);
+    }
+
+    #[test]
+    fn numeric_properties_compare_as_numbers() {
+        let prioritizers: Vec<Prioritizer> = by_priority("priority");
+
+        assert_eq!(
+            compare_chain(
+                &prioritizers,
+                &queued(1, &[("priority", "9")]),
+                &queued(0, &[("priority", "10")])
+            ),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn other_properties_compare_as_text() {
+        let prioritizers: Vec<Prioritizer> = by_priority("priority");
+
+        assert_eq!(
+            compare_chain(
+                &prioritizers,
+                &queued(1, &[("priority", "high")]),
+                &queued(0, &[("priority", "low")])
+            ),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn messages_missing_the_property_go_last() {
+        let prioritizers: Vec<Prioritizer> = by_priority("priority");
+
+        assert_eq!(
+            compare_chain(&prioritizers, &queued(0, &[]), &queued(1, &[("priority", "5")])),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn ties_fall_through_to_the_next_prioritizer() {
+        let mut prioritizers: Vec<Prioritizer> = by_priority("priority");
+        prioritizers.push(Prioritizer::NewestFirst);
+
+        let first: QueuedMessage = queued(0, &[("priority", "1")]);
+        let second: QueuedMessage = queued(1, &[("priority", "1")]);
+
+        assert_eq!(compare_chain(&prioritizers, &first, &second), Ordering::Greater);
+        // Without another prioritizer arrival order breaks the tie
+        assert_eq!(
+            compare_chain(&by_priority("priority"), &first, &second),
+            Ordering::Less
+        );
+    }
+}
@@ -0,0 +1,539 @@
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use event_listener::{Event, EventListener};
+use futures::{FutureExt, Stream};
+
+use crate::connection::filter::MessageFilter;
+use crate::connection::prioritizer::{compare_chain, Prioritizer, QueuedMessage};
+use crate::message::Message;
+use crate::message::trace::now_nanos;
+
+// How often the whole queue is checked for expired messages, the front is checked on every pop
+const EXPIRY_SWEEP_PERIOD: Duration = Duration::from_secs(1);
+
+/// Told about each message dropped from a queue because it expired
+pub type ExpiredHandler = Arc<dyn Fn(&Message) + Send + Sync>;
+
+/// Bounded queue of messages ordered by a chain of prioritizers
+/// Pushing waits while the queue is full and popping waits while it is empty
+pub struct MessageQueue {
+    state: Mutex<QueueState>,
+
+    // Notified when a message is pushed
+    not_empty: Event,
+    // Notified when space is freed
+    not_full: Event,
+
+    // Count of messages dropped due to expiration
+    expired: AtomicUsize,
+    on_expired: Mutex<Option<ExpiredHandler>>,
+}
+
+struct QueueState {
+    // Kept sorted so the front is always the next message out
+    items: VecDeque<QueuedMessage>,
+    capacity: usize,
+    expiration: Option<Duration>,
+    prioritizers: Vec<Prioritizer>,
+
+    next_sequence: u64,
+    // When expired messages were last swept from behind the front
+    last_sweep_nanos: u128,
+}
+
+impl MessageQueue {
+    pub fn new(
+        capacity: usize,
+        expiration: Option<Duration>,
+        prioritizers: Vec<Prioritizer>,
+    ) -> MessageQueue {
+        MessageQueue {
+            state: Mutex::new(QueueState {
+                items: Default::default(),
+                capacity,
+                expiration,
+                prioritizers,
+                next_sequence: 0,
+                last_sweep_nanos: 0,
+            }),
+            not_empty: Event::new(),
+            not_full: Event::new(),
+            expired: Default::default(),
+            on_expired: Default::default(),
+        }
+    }
+
+    // Replaces any handler set before
+    pub fn set_expired_handler(&self, handler: ExpiredHandler) {
+        *self.on_expired.lock().unwrap() = Some(handler);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lock().items.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.lock().capacity
+    }
+
+    pub fn expired_count(&self) -> usize {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    /// Change the settings of the queue while keeping the messages on it
+    /// Messages over a reduced capacity stay queued, pushes wait until they're received
+    pub fn reconfigure(
+        &self,
+        capacity: usize,
+        expiration: Option<Duration>,
+        prioritizers: Vec<Prioritizer>,
+    ) {
+        let mut state: MutexGuard<QueueState> = self.lock();
+
+        // Only space which wasn't already taken is freed
+        let freed: usize = capacity.saturating_sub(state.capacity.max(state.items.len()));
+
+        state.capacity = capacity;
+        state.expiration = expiration;
+        state.prioritizers = prioritizers;
+
+        // Sequences are kept so arrival order still breaks ties
+        let QueueState {
+            items,
+            prioritizers,
+            ..
+        } = &mut *state;
+        items
+            .make_contiguous()
+            .sort_by(|a, b| compare_chain(prioritizers, a, b));
+
+        drop(state);
+        self.not_full.notify_additional(freed);
+    }
+
+    // Push without waiting, handing the message back if the queue is full
+    pub fn try_push(&self, message: Message) -> Option<Message> {
+        let mut state: MutexGuard<QueueState> = self.lock();
+
+        if state.items.len() >= state.capacity {
+            return Some(message);
+        }
+
+        let queued: QueuedMessage = QueuedMessage {
+            sequence: state.next_sequence,
+            message,
+        };
+        state.next_sequence += 1;
+
+        // Insert after every message which should be dequeued first
+        let prioritizers: &[Prioritizer] = &state.prioritizers;
+        let index: usize = state
+            .items
+            .partition_point(|existing| compare_chain(prioritizers, existing, &queued).is_le());
+        state.items.insert(index, queued);
+
+        drop(state);
+        self.not_empty.notify_additional(1);
+
+        None
+    }
+
+    // Push a message, waiting for space if the queue is full
+    pub async fn push(&self, mut message: Message) {
+        loop {
+            message = match self.try_push(message) {
+                None => return,
+                Some(message) => message,
+            };
+
+            // Listen before retrying so a pop in between isn't missed
+            let listener: EventListener = self.not_full.listen();
+
+            message = match self.try_push(message) {
+                None => return,
+                Some(message) => message,
+            };
+
+            listener.await;
+        }
+    }
+
+    // Pop the highest priority message without waiting
+    // Expired messages at the front are dropped first, while those behind it are swept out
+    // periodically, as with a prioritizer the oldest messages may never reach the front
+    pub fn try_pop(&self) -> Option<Message> {
+        let mut state: MutexGuard<QueueState> = self.lock();
+        let mut expired: Vec<Message> = vec![];
+
+        if let Some(expiration) = state.expiration {
+            let now: u128 = now_nanos();
+
+            if now.saturating_sub(state.last_sweep_nanos) >= EXPIRY_SWEEP_PERIOD.as_nanos() {
+                state.last_sweep_nanos = now;
+
+                let (swept, retained): (VecDeque<QueuedMessage>, VecDeque<QueuedMessage>) = state
+                    .items
+                    .drain(..)
+                    .partition(|queued| has_expired(&queued.message, expiration, now));
+                state.items = retained;
+
+                expired.extend(swept.into_iter().map(|queued| queued.message));
+            }
+
+            while state
+                .items
+                .front()
+                .is_some_and(|queued| has_expired(&queued.message, expiration, now))
+            {
+                expired.extend(state.items.pop_front().map(|queued| queued.message));
+            }
+        }
+
+        let message: Option<Message> = state.items.pop_front().map(|queued| queued.message);
+
+        drop(state);
+        self.not_full
+            .notify_additional(expired.len() + usize::from(message.is_some()));
+
+        if !expired.is_empty() {
+            self.expired.fetch_add(expired.len(), Ordering::Relaxed);
+
+            if let Some(handler) = self.on_expired.lock().unwrap().clone() {
+                expired.iter().for_each(|message| handler(message));
+            }
+        }
+
+        message
+    }
+
+    // Pop a message, waiting for one if the queue is empty
+    pub async fn pop(&self) -> Message {
+        loop {
+            if let Some(message) = self.try_pop() {
+                return message;
+            }
+
+            let listener: EventListener = self.not_empty.listen();
+
+            if let Some(message) = self.try_pop() {
+                return message;
+            }
+
+            listener.await;
+        }
+    }
+
+    // Copy of the next messages out without removing them
+    pub fn peek(&self, limit: usize) -> Vec<Message> {
+        self.lock()
+            .items
+            .iter()
+            .take(limit)
+            .map(|queued| queued.message.clone())
+            .collect()
+    }
+
+    /// Remove all queued messages matching the filter and return them
+    /// Messages which don't match keep their position in the queue
+    pub fn purge(&self, filter: &MessageFilter) -> Vec<Message> {
+        let mut state: MutexGuard<QueueState> = self.lock();
+
+        let (dropped, retained): (VecDeque<QueuedMessage>, VecDeque<QueuedMessage>) = state
+            .items
+            .drain(..)
+            .partition(|queued| filter.matches(&queued.message));
+        state.items = retained;
+
+        drop(state);
+        self.not_full.notify_additional(dropped.len());
+
+        dropped.into_iter().map(|queued| queued.message).collect()
+    }
+
+    // Stream which pops messages as they become available
+    pub fn stream(self: &Arc<Self>) -> QueueStream {
+        QueueStream {
+            queue: self.clone(),
+            listener: None,
+        }
+    }
+}
+
+fn has_expired(message: &Message, expiration: Duration, now: u128) -> bool {
+    now.saturating_sub(message.created_nanos) > expiration.as_nanos()
+}
+
+pub struct QueueStream {
+    queue: Arc<MessageQueue>,
+    listener: Option<EventListener>,
+}
+
+impl Stream for QueueStream {
+    type Item = Message;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Message>> {
+        loop {
+            if let Some(message) = self.queue.try_pop() {
+                self.listener = None;
+
+                return Poll::Ready(Some(message));
+            }
+
+            match self.listener.as_mut() {
+                // Listen then check again so a push in between isn't missed
+                None => self.listener = Some(self.queue.not_empty.listen()),
+                Some(listener) => {
+                    if listener.poll_unpin(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+
+                    self.listener = None;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::future::Future;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::time::Duration;
+
+    use futures::task::{waker, ArcWake};
+
+    use crate::connection::filter::MessageFilter;
+    use crate::connection::prioritizer::Prioritizer;
+    use crate::connection::queue::MessageQueue;
+    use crate::message::Message;
+    use crate::message::trace::now_nanos;
+
+    // Counts how often a waiting push or pop is woken
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn message(n: usize) -> Message {
+        Message::new(HashMap::from([("n".to_string(), n.to_string())]))
+    }
+
+    fn numbers(messages: impl IntoIterator<Item = Message>) -> Vec<String> {
+        messages
+            .into_iter()
+            .map(|message| message.properties["n"].clone())
+            .collect()
+    }
+
+    fn drain(queue: &MessageQueue) -> Vec<String> {
+        numbers(std::iter::from_fn(|| queue.try_pop()))
+    }
+
+    fn queue_of(capacity: usize, prioritizers: Vec<Prioritizer>, count: usize) -> MessageQueue {
+        let queue: MessageQueue = MessageQueue::new(capacity, None, prioritizers);
+
+        for n in 0..count {
+            assert!(queue.try_push(message(n)).is_none());
+        }
+
+        queue
+    }
+
+    #[test]
+    fn messages_leave_in_arrival_order_by_default() {
+        let queue: MessageQueue = queue_of(10, vec![], 3);
+
+        assert_eq!(drain(&queue), ["0", "1", "2"]);
+    }
+
+    #[test]
+    fn newest_first_leaves_in_reverse_order() {
+        let queue: MessageQueue = queue_of(10, vec![Prioritizer::NewestFirst], 3);
+
+        assert_eq!(drain(&queue), ["2", "1", "0"]);
+    }
+
+    #[test]
+    fn full_queue_hands_the_message_back() {
+        let queue: MessageQueue = queue_of(2, vec![], 2);
+
+        let rejected: Option<Message> = queue.try_push(message(2));
+
+        assert_eq!(numbers(rejected), ["2"]);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn waiting_push_is_woken_when_space_is_freed() {
+        let queue: MessageQueue = queue_of(1, vec![], 1);
+        let counter: Arc<CountingWaker> = Default::default();
+        let waker: Waker = waker(counter.clone());
+        let mut context: Context = Context::from_waker(&waker);
+
+        let mut push = Box::pin(queue.push(message(1)));
+        assert!(push.as_mut().poll(&mut context).is_pending());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        assert_eq!(numbers(queue.try_pop()), ["0"]);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        assert!(push.as_mut().poll(&mut context).is_ready());
+        assert_eq!(drain(&queue), ["1"]);
+    }
+
+    #[test]
+    fn waiting_pop_is_woken_by_a_push() {
+        let queue: MessageQueue = MessageQueue::new(1, None, vec![]);
+        let counter: Arc<CountingWaker> = Default::default();
+        let waker: Waker = waker(counter.clone());
+        let mut context: Context = Context::from_waker(&waker);
+
+        let mut pop = Box::pin(queue.pop());
+        assert!(pop.as_mut().poll(&mut context).is_pending());
+
+        assert!(queue.try_push(message(0)).is_none());
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        match pop.as_mut().poll(&mut context) {
+            Poll::Ready(popped) => assert_eq!(numbers([popped]), ["0"]),
+            Poll::Pending => panic!("Pop should have received the pushed message"),
+        }
+    }
+
+    #[test]
+    fn reconfigure_reorders_queued_messages() {
+        let queue: MessageQueue = queue_of(10, vec![], 3);
+
+        queue.reconfigure(10, None, vec![Prioritizer::NewestFirst]);
+
+        assert_eq!(drain(&queue), ["2", "1", "0"]);
+    }
+
+    #[test]
+    fn reduced_capacity_keeps_messages_until_received() {
+        let queue: MessageQueue = queue_of(3, vec![], 3);
+
+        queue.reconfigure(1, None, vec![]);
+
+        assert_eq!(queue.len(), 3);
+        assert!(queue.try_push(message(3)).is_some());
+
+        // Space only frees once the queue is back under the new capacity
+        queue.try_pop();
+        queue.try_pop();
+        assert!(queue.try_push(message(3)).is_some());
+
+        queue.try_pop();
+        assert!(queue.try_push(message(3)).is_none());
+    }
+
+    #[test]
+    fn increased_capacity_wakes_waiting_push() {
+        let queue: MessageQueue = queue_of(1, vec![], 1);
+        let counter: Arc<CountingWaker> = Default::default();
+        let waker: Waker = waker(counter.clone());
+        let mut context: Context = Context::from_waker(&waker);
+
+        let mut push = Box::pin(queue.push(message(1)));
+        assert!(push.as_mut().poll(&mut context).is_pending());
+
+        queue.reconfigure(2, None, vec![]);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+
+        assert!(push.as_mut().poll(&mut context).is_ready());
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn purge_keeps_the_order_of_the_rest() {
+        let queue: MessageQueue = queue_of(10, vec![Prioritizer::NewestFirst], 5);
+
+        let filter: MessageFilter = MessageFilter {
+            properties: HashMap::from([("n".to_string(), "2".to_string())]),
+            ..Default::default()
+        };
+
+        assert_eq!(numbers(queue.purge(&filter)), ["2"]);
+        assert_eq!(drain(&queue), ["4", "3", "1", "0"]);
+    }
+
+    #[test]
+    fn expired_messages_are_dropped_from_behind_the_front() {
+        let queue: MessageQueue =
+            MessageQueue::new(10, Some(Duration::from_secs(60)), vec![Prioritizer::NewestFirst]);
+
+        let dropped: Arc<Mutex<Vec<Message>>> = Default::default();
+        let handler_dropped: Arc<Mutex<Vec<Message>>> = dropped.clone();
+        queue.set_expired_handler(Arc::new(move |message| {
+            handler_dropped.lock().unwrap().push(message.clone());
+        }));
+
+        let mut expired: Message = message(0);
+        expired.created_nanos = 0;
+        assert!(queue.try_push(expired).is_none());
+        assert!(queue.try_push(message(1)).is_none());
+
+        // The expired message is at the back, behind a fresh one
+        assert_eq!(numbers(queue.try_pop()), ["1"]);
+        assert!(queue.is_empty());
+        assert_eq!(queue.expired_count(), 1);
+        assert_eq!(numbers(dropped.lock().unwrap().clone()), ["0"]);
+    }
+    // Queue holding an expired message and a fresh one, ordered by the prioritizers
+    fn queue_with_expired(prioritizers: Vec<Prioritizer>) -> MessageQueue {
+        let queue: MessageQueue =
+            MessageQueue::new(10, Some(Duration::from_secs(60)), prioritizers);
+
+        let mut expired: Message = message(0);
+        expired.created_nanos = 0;
+        assert!(queue.try_push(expired).is_none());
+        assert!(queue.try_push(message(1)).is_none());
+
+        queue
+    }
+
+    #[test]
+    fn expired_front_is_dropped_between_sweeps() {
+        let queue: MessageQueue = queue_with_expired(vec![]);
+        queue.lock().last_sweep_nanos = now_nanos();
+
+        assert_eq!(numbers(queue.try_pop()), ["1"]);
+        assert_eq!(queue.expired_count(), 1);
+    }
+
+    #[test]
+    fn expired_messages_behind_the_front_wait_for_the_next_sweep() {
+        let queue: MessageQueue = queue_with_expired(vec![Prioritizer::NewestFirst]);
+        queue.lock().last_sweep_nanos = now_nanos();
+
+        assert_eq!(numbers(queue.try_pop()), ["1"]);
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.expired_count(), 0);
+
+        // Once the sweep period has passed
+        queue.lock().last_sweep_nanos = 0;
+
+        assert!(queue.try_pop().is_none());
+        assert_eq!(queue.expired_count(), 1);
+    }
+}
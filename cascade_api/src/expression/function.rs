@@ -0,0 +1,274 @@
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::expression::{ExpressionError, ExpressionValue};
+use crate::message::Message;
+
+/// Functions which can be called in an expression, by name or on a value as its first argument
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Function {
+    // Strings
+    ToUpper,
+    ToLower,
+    Trim,
+    Length,
+    Substring,
+    Replace,
+    ReplaceRegex,
+    Matches,
+    Find,
+    Contains,
+    StartsWith,
+    EndsWith,
+    IsEmpty,
+    IfEmpty,
+    // Math
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    Min,
+    Max,
+    ToNumber,
+    // Dates, as nanoseconds since the epoch
+    FormatDate,
+    Now,
+    // Message
+    CreatedNanos,
+    MessageId,
+    Property,
+    Uuid,
+}
+
+impl Function {
+    pub fn from_name(name: &str) -> Option<Function> {
+        let function: Function = match name {
+            "toUpper" => Function::ToUpper,
+            "toLower" => Function::ToLower,
+            "trim" => Function::Trim,
+            "length" => Function::Length,
+            "substring" => Function::Substring,
+            "replace" => Function::Replace,
+            "replaceRegex" => Function::ReplaceRegex,
+            "matches" => Function::Matches,
+            "find" => Function::Find,
+            "contains" => Function::Contains,
+            "startsWith" => Function::StartsWith,
+            "endsWith" => Function::EndsWith,
+            "isEmpty" => Function::IsEmpty,
+            "ifEmpty" => Function::IfEmpty,
+            "abs" => Function::Abs,
+            "round" => Function::Round,
+            "floor" => Function::Floor,
+            "ceil" => Function::Ceil,
+            "min" => Function::Min,
+            "max" => Function::Max,
+            "toNumber" => Function::ToNumber,
+            "formatDate" => Function::FormatDate,
+            "now" => Function::Now,
+            "createdNanos" => Function::CreatedNanos,
+            "messageId" => Function::MessageId,
+            "property" => Function::Property,
+            "uuid" => Function::Uuid,
+            _ => return None,
+        };
+
+        Some(function)
+    }
+
+    // Smallest and largest number of arguments the function takes
+    fn arity(&self) -> (usize, usize) {
+        match self {
+            Function::Now | Function::CreatedNanos | Function::MessageId | Function::Uuid => (0, 0),
+            Function::ToUpper
+            | Function::ToLower
+            | Function::Trim
+            | Function::Length
+            | Function::IsEmpty
+            | Function::Abs
+            | Function::Round
+            | Function::Floor
+            | Function::Ceil
+            | Function::ToNumber
+            | Function::Property => (1, 1),
+            Function::Matches
+            | Function::Find
+            | Function::Contains
+            | Function::StartsWith
+            | Function::EndsWith
+            | Function::IfEmpty
+            | Function::Min
+            | Function::Max
+            | Function::FormatDate => (2, 2),
+            Function::Substring => (2, 3),
+            Function::Replace | Function::ReplaceRegex => (3, 3),
+        }
+    }
+
+    pub fn check_args(&self, count: usize) -> Result<(), String> {
+        let (min, max): (usize, usize) = self.arity();
+
+        match count >= min && count <= max {
+            true => Ok(()),
+            false if min == max => Err(format!("takes {} arguments but got {}", min, count)),
+            false => Err(format!("takes {} to {} arguments but got {}", min, max, count)),
+        }
+    }
+
+    // Index of the argument which is a regular expression
+    pub fn pattern_arg(&self) -> Option<usize> {
+        match self {
+            Function::ReplaceRegex | Function::Matches | Function::Find => Some(1),
+            _ => None,
+        }
+    }
+
+    // Matching applies to the whole value, rather than anywhere within it like find
+    pub fn compile(&self, pattern: &str) -> Result<Regex, regex::Error> {
+        match self {
+            Function::Matches => Regex::new(&format!("^(?:{})$", pattern)),
+            _ => Regex::new(pattern),
+        }
+    }
+
+    /// Call the function with evaluated arguments
+    /// The pattern is used if it was compiled while parsing, otherwise it's compiled from the argument
+    pub fn call(
+        &self,
+        args: &[ExpressionValue],
+        pattern: Option<&Regex>,
+        message: &Message,
+    ) -> Result<ExpressionValue, ExpressionError> {
+        let text = |index: usize| args[index].to_string();
+
+        let compiled: Option<Regex> = match (self.pattern_arg(), pattern) {
+            (Some(index), None) => Some(
+                self.compile(&text(index))
+                    .map_err(|err| ExpressionError::Evaluation(err.to_string()))?,
+            ),
+            _ => None,
+        };
+        let pattern: Option<&Regex> = pattern.or(compiled.as_ref());
+
+        let value: ExpressionValue = match self {
+            Function::ToUpper => ExpressionValue::Text(text(0).to_uppercase()),
+            Function::ToLower => ExpressionValue::Text(text(0).to_lowercase()),
+            Function::Trim => ExpressionValue::Text(text(0).trim().to_string()),
+            Function::Length => ExpressionValue::Integer(text(0).chars().count() as i64),
+            Function::Substring => {
+                let value: String = text(0);
+                let start: usize = integer_arg(self, &args[1])?.max(0) as usize;
+                let end: usize = match args.get(2) {
+                    Some(end) => integer_arg(self, end)?.max(0) as usize,
+                    None => usize::MAX,
+                };
+
+                ExpressionValue::Text(
+                    value
+                        .chars()
+                        .skip(start)
+                        .take(end.saturating_sub(start))
+                        .collect(),
+                )
+            }
+            Function::Replace => ExpressionValue::Text(text(0).replace(&text(1), &text(2))),
+            Function::ReplaceRegex => ExpressionValue::Text(
+                pattern
+                    .unwrap()
+                    .replace_all(&text(0), text(2).as_str())
+                    .into_owned(),
+            ),
+            Function::Matches | Function::Find => {
+                ExpressionValue::Boolean(pattern.unwrap().is_match(&text(0)))
+            }
+            Function::Contains => ExpressionValue::Boolean(text(0).contains(&text(1))),
+            Function::StartsWith => ExpressionValue::Boolean(text(0).starts_with(&text(1))),
+            Function::EndsWith => ExpressionValue::Boolean(text(0).ends_with(&text(1))),
+            Function::IsEmpty => ExpressionValue::Boolean(text(0).trim().is_empty()),
+            Function::IfEmpty => match text(0).trim().is_empty() {
+                true => args[1].clone(),
+                false => args[0].clone(),
+            },
+            Function::Abs => match number_arg(self, &args[0])? {
+                ExpressionValue::Integer(value) => {
+                    ExpressionValue::Integer(value.checked_abs().ok_or(
+                        ExpressionError::Evaluation(format!("abs of {} overflowed", value)),
+                    )?)
+                }
+                value => ExpressionValue::Decimal(value.as_decimal().abs()),
+            },
+            Function::Round => round(self, &args[0], f64::round)?,
+            Function::Floor => round(self, &args[0], f64::floor)?,
+            Function::Ceil => round(self, &args[0], f64::ceil)?,
+            Function::Min | Function::Max => {
+                let left: ExpressionValue = number_arg(self, &args[0])?;
+                let right: ExpressionValue = number_arg(self, &args[1])?;
+                let left_smaller: bool = left.as_decimal() <= right.as_decimal();
+
+                match (self, left_smaller) {
+                    (Function::Min, true) | (Function::Max, false) => left,
+                    _ => right,
+                }
+            }
+            Function::ToNumber => number_arg(self, &args[0])?,
+            Function::FormatDate => {
+                let nanos: i64 = integer_arg(self, &args[0])?;
+                let date: DateTime<Utc> = DateTime::from_timestamp_nanos(nanos);
+
+                // Invalid format specifiers only show up when formatting
+                let mut formatted: String = String::new();
+                write!(formatted, "{}", date.format(&text(1))).map_err(|_| {
+                    ExpressionError::Evaluation(format!("Invalid date format {}", text(1)))
+                })?;
+
+                ExpressionValue::Text(formatted)
+            }
+            Function::Now => ExpressionValue::Integer(
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos() as i64,
+            ),
+            Function::CreatedNanos => ExpressionValue::Integer(message.created_nanos as i64),
+            Function::MessageId => ExpressionValue::Text(message.id.clone()),
+            Function::Property => message
+                .properties
+                .get(&text(0))
+                .map(|value| ExpressionValue::Text(value.clone()))
+                .unwrap_or(ExpressionValue::Null),
+            Function::Uuid => ExpressionValue::Text(Uuid::new_v4().to_string()),
+        };
+
+        Ok(value)
+    }
+}
+
+fn number_arg(function: &Function, value: &ExpressionValue) -> Result<ExpressionValue, ExpressionError> {
+    value.as_number().ok_or(ExpressionError::Evaluation(format!(
+        "{:?} expected a number but got {}",
+        function, value
+    )))
+}
+
+fn integer_arg(function: &Function, value: &ExpressionValue) -> Result<i64, ExpressionError> {
+    match number_arg(function, value)? {
+        ExpressionValue::Integer(value) => Ok(value),
+        value => Ok(value.as_decimal() as i64),
+    }
+}
+
+// Integers are already whole, so are left as they are
+fn round(
+    function: &Function,
+    value: &ExpressionValue,
+    rounding: fn(f64) -> f64,
+) -> Result<ExpressionValue, ExpressionError> {
+    match number_arg(function, value)? {
+        ExpressionValue::Integer(value) => Ok(ExpressionValue::Integer(value)),
+        value => Ok(ExpressionValue::Integer(rounding(value.as_decimal()) as i64)),
+    }
+}
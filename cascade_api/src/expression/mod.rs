@@ -0,0 +1,363 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::expression::parser::{parse_expression, BinaryOperator, Node, UnaryOperator};
+use crate::message::Message;
+
+mod function;
+mod parser;
+#[cfg(test)]
+mod tests;
+
+const EXPRESSION_START: &str = "${";
+// Written as $${ to include ${ as text
+const ESCAPED_START: &str = "$${";
+
+#[derive(Debug)]
+pub enum ExpressionError {
+    // Offset into the expression text and what was wrong there
+    Parse(usize, String),
+    Evaluation(String),
+}
+
+impl Display for ExpressionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpressionError::Parse(position, err) => f.write_fmt(format_args!(
+                "Invalid expression at character {}: {}",
+                position, err
+            )),
+            ExpressionError::Evaluation(err) => {
+                f.write_fmt(format_args!("Expression could not be evaluated: {}", err))
+            }
+        }
+    }
+}
+
+/// Result of evaluating an expression
+/// Message properties are text, which is treated as a number wherever one is expected
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Decimal(f64),
+    Text(String),
+}
+
+impl Display for ExpressionValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // Missing properties are left out of text
+            ExpressionValue::Null => Ok(()),
+            ExpressionValue::Boolean(value) => value.fmt(f),
+            ExpressionValue::Integer(value) => value.fmt(f),
+            ExpressionValue::Decimal(value) => value.fmt(f),
+            ExpressionValue::Text(value) => f.write_str(value),
+        }
+    }
+}
+
+impl ExpressionValue {
+    /// Empty text, zero, false and null are false, as is the text false
+    pub fn is_true(&self) -> bool {
+        match self {
+            ExpressionValue::Null => false,
+            ExpressionValue::Boolean(value) => *value,
+            ExpressionValue::Integer(value) => *value != 0,
+            ExpressionValue::Decimal(value) => *value != 0.0,
+            ExpressionValue::Text(value) => !value.is_empty() && value != "false",
+        }
+    }
+
+    /// The value as an Integer or Decimal, if it is or holds one
+    pub fn as_number(&self) -> Option<ExpressionValue> {
+        match self {
+            ExpressionValue::Integer(_) | ExpressionValue::Decimal(_) => Some(self.clone()),
+            ExpressionValue::Text(value) => {
+                let value: &str = value.trim();
+
+                value
+                    .parse()
+                    .map(ExpressionValue::Integer)
+                    .or_else(|_| value.parse().map(ExpressionValue::Decimal))
+                    .ok()
+            }
+            ExpressionValue::Null | ExpressionValue::Boolean(_) => None,
+        }
+    }
+
+    fn as_decimal(&self) -> f64 {
+        match self {
+            ExpressionValue::Integer(value) => *value as f64,
+            ExpressionValue::Decimal(value) => *value,
+            _ => f64::NAN,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Part {
+    Text(String),
+    Expression(Node),
+}
+
+/// Text with ${...} expressions embedded, evaluated against each message
+/// Expressions can reference properties by name, call functions and use operators:
+///     ${file_name.substring(0, 3).toUpper()}
+///     ${size > 1024 ? 'large' : 'small'}
+///     ${formatDate(createdNanos(), '%Y-%m-%d')}
+/// Names are letters, digits and underscores, so ${size-1} subtracts from size
+/// Properties with any other name are read with property, such as ${property('content-type')}
+/// Text without any expressions evaluates to itself, so plain values can be used as they were
+/// The text is parsed once and kept so the expression serialises as it was written
+#[derive(Debug, Clone)]
+pub struct Expression {
+    source: String,
+    parts: Vec<Part>,
+}
+
+impl Expression {
+    /// This will fail if any expression in the text is invalid, with where it went wrong
+    pub fn parse(source: &str) -> Result<Expression, ExpressionError> {
+        let mut parts: Vec<Part> = vec![];
+        let mut text: String = String::new();
+        let mut offset: usize = 0;
+
+        while let Some(start) = source[offset..].find(EXPRESSION_START).map(|start| offset + start) {
+            // Include ${ as text when escaped
+            if start > 0 && source[..start + EXPRESSION_START.len()].ends_with(ESCAPED_START) {
+                text.push_str(&source[offset..start - 1]);
+                text.push_str(EXPRESSION_START);
+                offset = start + EXPRESSION_START.len();
+                continue;
+            }
+
+            text.push_str(&source[offset..start]);
+            if !text.is_empty() {
+                parts.push(Part::Text(std::mem::take(&mut text)));
+            }
+
+            let (node, end): (Node, usize) =
+                parse_expression(source, start + EXPRESSION_START.len())?;
+            parts.push(Part::Expression(node));
+            offset = end;
+        }
+
+        text.push_str(&source[offset..]);
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Expression {
+            source: source.to_string(),
+            parts,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the text has no expressions, so always evaluates to the same value
+    pub fn is_literal(&self) -> bool {
+        self.parts.iter().all(|part| matches!(part, Part::Text(_)))
+    }
+
+    /// Evaluate against a message
+    /// A lone expression keeps the type of its value, anything else is joined into text
+    pub fn evaluate(&self, message: &Message) -> Result<ExpressionValue, ExpressionError> {
+        if let [Part::Expression(node)] = self.parts.as_slice() {
+            return evaluate(node, message);
+        }
+
+        let mut text: String = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(value) => text.push_str(value),
+                Part::Expression(node) => text.push_str(&evaluate(node, message)?.to_string()),
+            }
+        }
+
+        Ok(ExpressionValue::Text(text))
+    }
+
+    pub fn evaluate_text(&self, message: &Message) -> Result<String, ExpressionError> {
+        self.evaluate(message).map(|value| value.to_string())
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+// Parsed while deserialising so invalid expressions are rejected along with the rest of the config
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source: String = String::deserialize(deserializer)?;
+
+        Expression::parse(&source).map_err(serde::de::Error::custom)
+    }
+}
+
+fn evaluate(node: &Node, message: &Message) -> Result<ExpressionValue, ExpressionError> {
+    let value: ExpressionValue = match node {
+        Node::Literal(value) => value.clone(),
+        Node::Property(name) => message
+            .properties
+            .get(name)
+            .map(|value| ExpressionValue::Text(value.clone()))
+            .unwrap_or(ExpressionValue::Null),
+        Node::Unary(UnaryOperator::Not, operand) => {
+            ExpressionValue::Boolean(!evaluate(operand, message)?.is_true())
+        }
+        Node::Unary(UnaryOperator::Negate, operand) => {
+            let operand: ExpressionValue = evaluate(operand, message)?;
+
+            match operand.as_number() {
+                Some(ExpressionValue::Integer(value)) => {
+                    ExpressionValue::Integer(value.checked_neg().ok_or(
+                        ExpressionError::Evaluation(format!("Negating {} overflowed", value)),
+                    )?)
+                }
+                Some(value) => ExpressionValue::Decimal(-value.as_decimal()),
+                None => {
+                    return Err(ExpressionError::Evaluation(format!(
+                        "Can't negate {}",
+                        operand
+                    )))
+                }
+            }
+        }
+        // Only one branch is evaluated
+        Node::Conditional(condition, then, otherwise) => {
+            match evaluate(condition, message)?.is_true() {
+                true => evaluate(then, message)?,
+                false => evaluate(otherwise, message)?,
+            }
+        }
+        Node::Binary(BinaryOperator::And, left, right) => ExpressionValue::Boolean(
+            evaluate(left, message)?.is_true() && evaluate(right, message)?.is_true(),
+        ),
+        Node::Binary(BinaryOperator::Or, left, right) => ExpressionValue::Boolean(
+            evaluate(left, message)?.is_true() || evaluate(right, message)?.is_true(),
+        ),
+        Node::Binary(operator, left, right) => binary(
+            *operator,
+            evaluate(left, message)?,
+            evaluate(right, message)?,
+        )?,
+        Node::Call {
+            function,
+            args,
+            pattern,
+        } => {
+            let args: Vec<ExpressionValue> = args
+                .iter()
+                .map(|arg| evaluate(arg, message))
+                .collect::<Result<Vec<ExpressionValue>, ExpressionError>>()?;
+
+            function.call(&args, pattern.as_ref(), message)?
+        }
+    };
+
+    Ok(value)
+}
+
+// Operators on numbers if both sides are or hold numbers, otherwise on text
+fn binary(
+    operator: BinaryOperator,
+    left: ExpressionValue,
+    right: ExpressionValue,
+) -> Result<ExpressionValue, ExpressionError> {
+    let numbers: Option<(ExpressionValue, ExpressionValue)> =
+        left.as_number().zip(right.as_number());
+
+    let value: ExpressionValue = match (operator, numbers) {
+        (BinaryOperator::Equal, Some((left, right))) => {
+            ExpressionValue::Boolean(left.as_decimal() == right.as_decimal())
+        }
+        (BinaryOperator::Equal, None) => ExpressionValue::Boolean(left.to_string() == right.to_string()),
+        (BinaryOperator::NotEqual, _) => {
+            ExpressionValue::Boolean(!binary(BinaryOperator::Equal, left, right)?.is_true())
+        }
+        (
+            BinaryOperator::Less
+            | BinaryOperator::LessOrEqual
+            | BinaryOperator::Greater
+            | BinaryOperator::GreaterOrEqual,
+            numbers,
+        ) => {
+            let ordering: Option<std::cmp::Ordering> = match numbers {
+                Some((left, right)) => left.as_decimal().partial_cmp(&right.as_decimal()),
+                None => Some(left.to_string().cmp(&right.to_string())),
+            };
+
+            ExpressionValue::Boolean(ordering.is_some_and(|ordering| match operator {
+                BinaryOperator::Less => ordering.is_lt(),
+                BinaryOperator::LessOrEqual => ordering.is_le(),
+                BinaryOperator::Greater => ordering.is_gt(),
+                _ => ordering.is_ge(),
+            }))
+        }
+        // Adding anything other than numbers joins them as text
+        (BinaryOperator::Add, None) => ExpressionValue::Text(format!("{}{}", left, right)),
+        (_, Some((ExpressionValue::Integer(left), ExpressionValue::Integer(right)))) => {
+            integer_arithmetic(operator, left, right)?
+        }
+        (_, Some((left, right))) => {
+            let (left, right): (f64, f64) = (left.as_decimal(), right.as_decimal());
+
+            ExpressionValue::Decimal(match operator {
+                BinaryOperator::Add => left + right,
+                BinaryOperator::Subtract => left - right,
+                BinaryOperator::Multiply => left * right,
+                BinaryOperator::Divide => left / right,
+                _ => left % right,
+            })
+        }
+        (_, None) => {
+            return Err(ExpressionError::Evaluation(format!(
+                "{:?} needs numbers but got {} and {}",
+                operator, left, right
+            )))
+        }
+    };
+
+    Ok(value)
+}
+
+// Integers stay whole unless division leaves a remainder
+fn integer_arithmetic(
+    operator: BinaryOperator,
+    left: i64,
+    right: i64,
+) -> Result<ExpressionValue, ExpressionError> {
+    if matches!(operator, BinaryOperator::Divide | BinaryOperator::Remainder) && right == 0 {
+        return Err(ExpressionError::Evaluation("Division by zero".to_string()));
+    }
+
+    // The remainder overflows where the division would, which is caught below
+    if operator == BinaryOperator::Divide && left.checked_rem(right).is_some_and(|rem| rem != 0) {
+        return Ok(ExpressionValue::Decimal(left as f64 / right as f64));
+    }
+
+    let result: Option<i64> = match operator {
+        BinaryOperator::Add => left.checked_add(right),
+        BinaryOperator::Subtract => left.checked_sub(right),
+        BinaryOperator::Multiply => left.checked_mul(right),
+        BinaryOperator::Divide => left.checked_div(right),
+        _ => left.checked_rem(right),
+    };
+
+    result
+        .map(ExpressionValue::Integer)
+        .ok_or(ExpressionError::Evaluation(format!(
+            "{:?} of {} and {} overflowed",
+            operator, left, right
+        )))
+}
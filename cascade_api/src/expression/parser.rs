@@ -0,0 +1,415 @@
+use regex::Regex;
+
+use crate::expression::function::Function;
+use crate::expression::{ExpressionError, ExpressionValue};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+/// Parsed expression between ${ and }
+#[derive(Debug, Clone)]
+pub enum Node {
+    Literal(ExpressionValue),
+    // Value of a message property, null if the message doesn't have it
+    Property(String),
+    Unary(UnaryOperator, Box<Node>),
+    Binary(BinaryOperator, Box<Node>, Box<Node>),
+    // condition ? then : otherwise
+    Conditional(Box<Node>, Box<Node>, Box<Node>),
+    Call {
+        function: Function,
+        args: Vec<Node>,
+        // Patterns given as literals are compiled while parsing rather than for every message
+        pattern: Option<Regex>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Integer(i64),
+    Decimal(f64),
+    Text(String),
+    Identifier(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Dot,
+    Question,
+    Colon,
+    Operator(&'static str),
+    // Closes the expression
+    End,
+}
+
+// Operators longest first, so <= isn't read as <
+const OPERATORS: [&str; 14] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!",
+];
+
+/// Parse the expression starting at offset of the text, up to its closing }
+/// Returns the expression and the offset just after the closing }
+pub fn parse_expression(text: &str, offset: usize) -> Result<(Node, usize), ExpressionError> {
+    let (tokens, end): (Vec<(Token, usize)>, usize) = tokenize(text, offset)?;
+
+    let mut parser: Parser = Parser {
+        tokens,
+        position: 0,
+    };
+
+    let node: Node = parser.parse_conditional()?;
+
+    match parser.next() {
+        (Token::End, _) => Ok((node, end)),
+        (token, position) => Err(ExpressionError::Parse(
+            position,
+            format!("Unexpected {:?}", token),
+        )),
+    }
+}
+
+// Split the expression into tokens, with the offset each starts at
+fn tokenize(text: &str, offset: usize) -> Result<(Vec<(Token, usize)>, usize), ExpressionError> {
+    let mut tokens: Vec<(Token, usize)> = vec![];
+    let mut chars = text[offset..].char_indices().peekable();
+
+    while let Some((index, char)) = chars.next() {
+        let position: usize = offset + index;
+
+        let token: Token = match char {
+            _ if char.is_whitespace() => continue,
+            '}' => {
+                tokens.push((Token::End, position));
+                return Ok((tokens, position + 1));
+            }
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '.' => Token::Dot,
+            '?' => Token::Question,
+            ':' => Token::Colon,
+            '\'' | '"' => {
+                let mut value: String = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, escaped)) => value.push(escaped),
+                            None => break,
+                        },
+                        Some((_, next)) if next == char => break,
+                        Some((_, next)) => value.push(next),
+                        None => {
+                            return Err(ExpressionError::Parse(
+                                position,
+                                "Unterminated string".to_string(),
+                            ))
+                        }
+                    }
+                }
+
+                Token::Text(value)
+            }
+            _ if char.is_ascii_digit() => {
+                let mut number: String = char.to_string();
+
+                while let Some((_, next)) = chars.peek() {
+                    // A dot not followed by a digit is a method call on the number
+                    let decimal_point: bool = *next == '.'
+                        && !number.contains('.')
+                        && text[offset..]
+                            .get(index + number.len() + 1..)
+                            .and_then(|rest| rest.chars().next())
+                            .is_some_and(|after| after.is_ascii_digit());
+
+                    if !next.is_ascii_digit() && !decimal_point {
+                        break;
+                    }
+
+                    number.push(*next);
+                    chars.next();
+                }
+
+                match number.contains('.') {
+                    true => Token::Decimal(number.parse().unwrap()),
+                    false => Token::Integer(number.parse().map_err(|_| {
+                        ExpressionError::Parse(position, format!("{} is too large", number))
+                    })?),
+                }
+            }
+            _ if char.is_alphabetic() || char == '_' => {
+                let mut identifier: String = char.to_string();
+
+                while let Some((_, next)) = chars.peek() {
+                    if !next.is_alphanumeric() && *next != '_' {
+                        break;
+                    }
+
+                    identifier.push(*next);
+                    chars.next();
+                }
+
+                Token::Identifier(identifier)
+            }
+            _ => {
+                let rest: &str = &text[position..];
+                let operator: &'static str = OPERATORS
+                    .iter()
+                    .find(|operator| rest.starts_with(**operator))
+                    .ok_or(ExpressionError::Parse(
+                        position,
+                        format!("Unexpected character {}", char),
+                    ))?;
+
+                // Skip the rest of a two character operator
+                for _ in 1..operator.len() {
+                    chars.next();
+                }
+
+                Token::Operator(operator)
+            }
+        };
+
+        tokens.push((token, position));
+    }
+
+    Err(ExpressionError::Parse(
+        text.len(),
+        "Expression is missing its closing }".to_string(),
+    ))
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    // Tokens always end with End, which is never consumed past
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let token: (Token, usize) = self.tokens[self.position].clone();
+
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExpressionError> {
+        match self.next() {
+            (token, _) if token == expected => Ok(()),
+            (token, position) => Err(ExpressionError::Parse(
+                position,
+                format!("Expected {:?} but found {:?}", expected, token),
+            )),
+        }
+    }
+
+    fn parse_conditional(&mut self) -> Result<Node, ExpressionError> {
+        let condition: Node = self.parse_binary(0)?;
+
+        if *self.peek() != Token::Question {
+            return Ok(condition);
+        }
+
+        self.next();
+        let then: Node = self.parse_conditional()?;
+        self.expect(Token::Colon)?;
+        let otherwise: Node = self.parse_conditional()?;
+
+        Ok(Node::Conditional(
+            Box::new(condition),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    // Operators are grouped by precedence, lowest first
+    fn parse_binary(&mut self, precedence: usize) -> Result<Node, ExpressionError> {
+        const PRECEDENCE: [&[(&str, BinaryOperator)]; 6] = [
+            &[("||", BinaryOperator::Or)],
+            &[("&&", BinaryOperator::And)],
+            &[("==", BinaryOperator::Equal), ("!=", BinaryOperator::NotEqual)],
+            &[
+                ("<", BinaryOperator::Less),
+                ("<=", BinaryOperator::LessOrEqual),
+                (">", BinaryOperator::Greater),
+                (">=", BinaryOperator::GreaterOrEqual),
+            ],
+            &[("+", BinaryOperator::Add), ("-", BinaryOperator::Subtract)],
+            &[
+                ("*", BinaryOperator::Multiply),
+                ("/", BinaryOperator::Divide),
+                ("%", BinaryOperator::Remainder),
+            ],
+        ];
+
+        let Some(operators) = PRECEDENCE.get(precedence) else {
+            return self.parse_unary();
+        };
+
+        let mut left: Node = self.parse_binary(precedence + 1)?;
+
+        while let Token::Operator(symbol) = self.peek() {
+            let Some((_, operator)) = operators.iter().find(|(name, _)| name == symbol) else {
+                break;
+            };
+            let operator: BinaryOperator = *operator;
+
+            self.next();
+            let right: Node = self.parse_binary(precedence + 1)?;
+            left = Node::Binary(operator, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Node, ExpressionError> {
+        let operator: UnaryOperator = match self.peek() {
+            Token::Operator("!") => UnaryOperator::Not,
+            Token::Operator("-") => UnaryOperator::Negate,
+            _ => return self.parse_postfix(),
+        };
+
+        self.next();
+
+        Ok(Node::Unary(operator, Box::new(self.parse_unary()?)))
+    }
+
+    // Functions can be called on a value as value.function(args)
+    fn parse_postfix(&mut self) -> Result<Node, ExpressionError> {
+        let mut node: Node = self.parse_primary()?;
+
+        while *self.peek() == Token::Dot {
+            self.next();
+
+            let (name, position): (String, usize) = match self.next() {
+                (Token::Identifier(name), position) => (name, position),
+                (token, position) => {
+                    return Err(ExpressionError::Parse(
+                        position,
+                        format!("Expected a function name but found {:?}", token),
+                    ))
+                }
+            };
+
+            self.expect(Token::LeftParen)?;
+
+            let mut args: Vec<Node> = vec![node];
+            args.append(&mut self.parse_args()?);
+
+            node = call(&name, args, position)?;
+        }
+
+        Ok(node)
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, ExpressionError> {
+        match self.next() {
+            (Token::Integer(value), _) => Ok(Node::Literal(ExpressionValue::Integer(value))),
+            (Token::Decimal(value), _) => Ok(Node::Literal(ExpressionValue::Decimal(value))),
+            (Token::Text(value), _) => Ok(Node::Literal(ExpressionValue::Text(value))),
+            (Token::LeftParen, _) => {
+                let node: Node = self.parse_conditional()?;
+                self.expect(Token::RightParen)?;
+
+                Ok(node)
+            }
+            (Token::Identifier(name), position) => match name.as_str() {
+                "true" => Ok(Node::Literal(ExpressionValue::Boolean(true))),
+                "false" => Ok(Node::Literal(ExpressionValue::Boolean(false))),
+                "null" => Ok(Node::Literal(ExpressionValue::Null)),
+                _ if *self.peek() == Token::LeftParen => {
+                    self.next();
+                    let args: Vec<Node> = self.parse_args()?;
+
+                    call(&name, args, position)
+                }
+                _ => Ok(Node::Property(name)),
+            },
+            (token, position) => Err(ExpressionError::Parse(
+                position,
+                format!("Unexpected {:?}", token),
+            )),
+        }
+    }
+
+    // Arguments after the opening parenthesis, up to and including the closing one
+    fn parse_args(&mut self) -> Result<Vec<Node>, ExpressionError> {
+        let mut args: Vec<Node> = vec![];
+
+        if *self.peek() == Token::RightParen {
+            self.next();
+            return Ok(args);
+        }
+
+        loop {
+            args.push(self.parse_conditional()?);
+
+            match self.next() {
+                (Token::Comma, _) => continue,
+                (Token::RightParen, _) => return Ok(args),
+                (token, position) => {
+                    return Err(ExpressionError::Parse(
+                        position,
+                        format!("Expected , or ) but found {:?}", token),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+// Check a call against its function, compiling its pattern if it's a literal
+fn call(name: &str, args: Vec<Node>, position: usize) -> Result<Node, ExpressionError> {
+    let function: Function = Function::from_name(name)
+        .ok_or(ExpressionError::Parse(position, format!("Unknown function {}", name)))?;
+
+    function
+        .check_args(args.len())
+        .map_err(|err| ExpressionError::Parse(position, format!("{} {}", name, err)))?;
+
+    let pattern: Option<Regex> = match (function.pattern_arg(), &args) {
+        (Some(index), args) => match args.get(index) {
+            Some(Node::Literal(ExpressionValue::Text(pattern))) => Some(
+                function
+                    .compile(pattern)
+                    .map_err(|err| ExpressionError::Parse(position, err.to_string()))?,
+            ),
+            _ => None,
+        },
+        (None, _) => None,
+    };
+
+    Ok(Node::Call {
+        function,
+        args,
+        pattern,
+    })
+}
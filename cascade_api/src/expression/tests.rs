@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use crate::expression::{Expression, ExpressionError, ExpressionValue};
+use crate::message::Message;
+
+fn message() -> Message {
+    Message::new(HashMap::from([
+        ("file_name".to_string(), "report.csv".to_string()),
+        ("size".to_string(), "2048".to_string()),
+        ("min".to_string(), i64::MIN.to_string()),
+    ]))
+}
+
+fn evaluate(source: &str) -> Result<ExpressionValue, ExpressionError> {
+    Expression::parse(source)?.evaluate(&message())
+}
+
+fn text(source: &str) -> String {
+    Expression::parse(source)
+        .unwrap()
+        .evaluate_text(&message())
+        .unwrap()
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(evaluate("${1 + 2 * 3}").unwrap(), ExpressionValue::Integer(7));
+    assert_eq!(evaluate("${(1 + 2) * 3}").unwrap(), ExpressionValue::Integer(9));
+    assert_eq!(evaluate("${10 - 4 - 3}").unwrap(), ExpressionValue::Integer(3));
+}
+
+#[test]
+fn comparison_binds_tighter_than_logic() {
+    assert_eq!(
+        evaluate("${size > 1024 && size < 4096 || false}").unwrap(),
+        ExpressionValue::Boolean(true)
+    );
+    assert_eq!(
+        evaluate("${!false && 1 == 2}").unwrap(),
+        ExpressionValue::Boolean(false)
+    );
+}
+
+#[test]
+fn subtraction_without_spaces_uses_the_property() {
+    assert_eq!(evaluate("${size-1}").unwrap(), ExpressionValue::Integer(2047));
+}
+
+#[test]
+fn escaped_start_is_kept_as_text() {
+    assert_eq!(text("$${file_name}"), "${file_name}");
+    assert_eq!(text("$${file_name} is ${file_name}"), "${file_name} is report.csv");
+    assert!(Expression::parse("$${file_name}").unwrap().is_literal());
+}
+
+#[test]
+fn text_around_expressions_is_joined() {
+    assert_eq!(text("name: ${file_name.toUpper()}!"), "name: REPORT.CSV!");
+    assert_eq!(text("missing: ${nothing}"), "missing: ");
+}
+
+#[test]
+fn conditional_evaluates_one_branch() {
+    assert_eq!(text("${size > 1024 ? 'large' : 'small'}"), "large");
+    assert_eq!(text("${size > 4096 ? 'large' : 'small'}"), "small");
+    // The other branch would fail if it were evaluated
+    assert_eq!(text("${true ? 'ok' : 1 / 0}"), "ok");
+}
+
+#[test]
+fn conditionals_nest_to_the_right() {
+    assert_eq!(
+        text("${size > 4096 ? 'large' : size > 1024 ? 'medium' : 'small'}"),
+        "medium"
+    );
+}
+
+#[test]
+fn substring_clamps_its_bounds() {
+    assert_eq!(text("${file_name.substring(0, 6)}"), "report");
+    assert_eq!(text("${file_name.substring(7)}"), "csv");
+    assert_eq!(text("${file_name.substring(-3, 3)}"), "rep");
+    assert_eq!(text("${file_name.substring(6, 100)}"), ".csv");
+    assert_eq!(text("${file_name.substring(100)}"), "");
+    assert_eq!(text("${file_name.substring(5, 2)}"), "");
+}
+
+#[test]
+fn division_by_zero_is_an_error() {
+    assert!(matches!(evaluate("${1 / 0}"), Err(ExpressionError::Evaluation(_))));
+    assert!(matches!(evaluate("${1 % 0}"), Err(ExpressionError::Evaluation(_))));
+}
+
+#[test]
+fn division_only_leaves_integers_when_whole() {
+    assert_eq!(evaluate("${6 / 3}").unwrap(), ExpressionValue::Integer(2));
+    assert_eq!(evaluate("${7 / 2}").unwrap(), ExpressionValue::Decimal(3.5));
+}
+
+#[test]
+fn overflow_is_an_error() {
+    assert!(matches!(evaluate("${-min}"), Err(ExpressionError::Evaluation(_))));
+    assert!(matches!(evaluate("${abs(min)}"), Err(ExpressionError::Evaluation(_))));
+    assert!(matches!(evaluate("${min / -1}"), Err(ExpressionError::Evaluation(_))));
+    assert!(matches!(evaluate("${min * 2}"), Err(ExpressionError::Evaluation(_))));
+}
+
+#[test]
+fn format_date_uses_utc() {
+    assert_eq!(
+        text("${formatDate(86400000000000, '%Y-%m-%d %H:%M')}"),
+        "1970-01-02 00:00"
+    );
+}
+
+#[test]
+fn invalid_format_date_spec_is_an_error() {
+    assert!(matches!(
+        evaluate("${formatDate(0, '%Q')}"),
+        Err(ExpressionError::Evaluation(_))
+    ));
+}
+
+#[test]
+fn invalid_expressions_fail_to_parse() {
+    for source in ["${1 +}", "${file_name", "${unknown()}", "${matches(file_name, '(')}"] {
+        assert!(
+            matches!(Expression::parse(source), Err(ExpressionError::Parse(..))),
+            "{} should fail to parse",
+            source
+        );
+    }
+}
+
+#[test]
+fn properties_with_other_names_are_read_with_property() {
+    let message: Message = Message::new(HashMap::from([(
+        "content-type".to_string(),
+        "text/csv".to_string(),
+    )]));
+
+    let expression: Expression = Expression::parse("${property('content-type')}").unwrap();
+
+    assert_eq!(expression.evaluate_text(&message).unwrap(), "text/csv");
+}
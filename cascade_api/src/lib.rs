@@ -1,3 +1,4 @@
 pub mod component;
 pub mod connection;
+pub mod expression;
 pub mod message;
\ No newline at end of file
@@ -5,8 +5,10 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use nanoid::nanoid;
 
 use crate::message::content::Content;
+use crate::message::trace::TraceContext;
 
 pub mod content;
+pub mod trace;
 
 /// Wraps message to pass signals to the component runtime
 pub enum InternalMessage {
@@ -22,9 +24,11 @@ pub struct Message {
     // Map of string properties
     pub properties: HashMap<String, String>,
     pub content: HashMap<String, Content>,
+    // Carried between components to link their spans
+    pub trace: TraceContext,
 }
 
-const DEFAULT_CONTENT_REFERENCE: &str = "default";
+pub const DEFAULT_CONTENT_REFERENCE: &str = "default";
 
 impl Message {
     pub fn new(properties: HashMap<String, String>) -> Message {
@@ -37,6 +41,8 @@ impl Message {
             // No content to begin with
             content: Default::default(),
             properties,
+            // Each new message starts a new trace
+            trace: Default::default(),
         }
     }
 
@@ -50,6 +56,12 @@ impl Message {
             // Start with default content populated
             content: HashMap::from([(DEFAULT_CONTENT_REFERENCE.to_string(), content)]),
             properties,
+            trace: Default::default(),
         }
     }
+
+    // Content the message was created with, which components read and write by default
+    pub fn default_content(&self) -> Option<&Content> {
+        self.content.get(DEFAULT_CONTENT_REFERENCE)
+    }
 }
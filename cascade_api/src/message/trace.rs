@@ -0,0 +1,87 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nanoid::nanoid;
+use serde::Serialize;
+
+const HEX: [char; 16] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'a', 'b', 'c', 'd', 'e', 'f',
+];
+
+fn trace_id() -> String {
+    nanoid!(32, &HEX)
+}
+
+fn span_id() -> String {
+    nanoid!(16, &HEX)
+}
+
+pub(crate) fn now_nanos() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos()
+}
+
+/// Links a message to the trace it belongs to
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceContext {
+    pub trace_id: String,
+    // Span of the component which last handled the message
+    pub parent_span_id: Option<String>,
+}
+
+impl Default for TraceContext {
+    fn default() -> Self {
+        TraceContext {
+            trace_id: trace_id(),
+            parent_span_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum SpanStatus {
+    Ok,
+    Error(String),
+}
+
+/// Time spent by a component handling a single message
+#[derive(Debug, Clone, Serialize)]
+pub struct Span {
+    pub trace_id: String,
+    pub span_id: String,
+    pub parent_span_id: Option<String>,
+
+    pub name: String,
+    pub component_id: String,
+    pub component_type: String,
+    // Message which was received, if any
+    pub message_id: Option<String>,
+
+    pub start_nanos: u128,
+    pub end_nanos: u128,
+    pub status: SpanStatus,
+}
+
+impl Span {
+    pub(crate) fn start(
+        trace: &TraceContext,
+        name: String,
+        component_id: String,
+        component_type: String,
+        start_nanos: u128,
+    ) -> Span {
+        Span {
+            trace_id: trace.trace_id.clone(),
+            span_id: span_id(),
+            parent_span_id: trace.parent_span_id.clone(),
+            name,
+            component_id,
+            component_type,
+            message_id: None,
+            start_nanos,
+            end_nanos: start_nanos,
+            status: SpanStatus::Ok,
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt::{Display, Formatter};
+
+use hyper::body::{Bytes, HttpBody};
+use hyper::client::HttpConnector;
+use hyper::http::request::Builder;
+use hyper::{header, Body, Client, Method, Request, Response, StatusCode, Uri};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+
+const APPLICATION_JSON: &str = "application/json";
+// Every path is relative to the versioned API
+const API_PREFIX: &str = "/api/v1";
+const HTTPS_PORT: u16 = 443;
+
+#[derive(Debug)]
+pub enum ClientError {
+    // The instance could not be reached
+    Connection(String),
+    // The instance responded with an error status
+    Rejected(StatusCode, String),
+    InvalidResponse(String),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Connection(err) => {
+                f.write_fmt(format_args!("Could not reach instance: {}", err))
+            }
+            ClientError::Rejected(status, body) => {
+                f.write_fmt(format_args!("Request failed with {}: {}", status, body))
+            }
+            ClientError::InvalidResponse(err) => {
+                f.write_fmt(format_args!("Instance sent an invalid response: {}", err))
+            }
+        }
+    }
+}
+
+/// Body of a successful response
+pub enum Reply {
+    Json(Value),
+    Text(String),
+    Empty,
+}
+
+/// Thin wrapper over the HTTP endpoints of a cascade instance
+pub struct CascadeClient {
+    base_url: String,
+    token: Option<String>,
+    client: Client<HttpConnector>,
+    // Used instead of the client for https urls
+    tls: Option<TlsConnector>,
+}
+
+impl CascadeClient {
+    pub fn new(base_url: &str, token: Option<String>, tls: Option<TlsConnector>) -> CascadeClient {
+        CascadeClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            client: Client::new(),
+            tls,
+        }
+    }
+
+    pub async fn get(&self, path: &str, query: &[(&str, String)]) -> Result<Reply, ClientError> {
+        self.send(Method::GET, path, query, None).await
+    }
+
+    pub async fn post(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<String>,
+    ) -> Result<Reply, ClientError> {
+        self.send(Method::POST, path, query, body).await
+    }
+
+    pub async fn put(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<String>,
+    ) -> Result<Reply, ClientError> {
+        self.send(Method::PUT, path, query, body).await
+    }
+
+    pub async fn patch(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<String>,
+    ) -> Result<Reply, ClientError> {
+        self.send(Method::PATCH, path, query, body).await
+    }
+
+    pub async fn delete(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<String>,
+    ) -> Result<Reply, ClientError> {
+        self.send(Method::DELETE, path, query, body).await
+    }
+
+    pub async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<String>,
+    ) -> Result<Reply, ClientError> {
+        let response: Response<Body> = self.request(method, path, query, body).await?;
+        let status: StatusCode = response.status();
+
+        let is_json: bool = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|content_type| content_type == APPLICATION_JSON);
+
+        let bytes: Bytes = hyper::body::to_bytes(response.into_body())
+            .await
+            .map_err(|err| ClientError::Connection(err.to_string()))?;
+        let text: String = String::from_utf8_lossy(&bytes).into_owned();
+
+        if !status.is_success() {
+            return Err(ClientError::Rejected(status, error_message(&text)));
+        }
+
+        if status == StatusCode::NO_CONTENT {
+            Ok(Reply::Empty)
+        } else if is_json {
+            serde_json::from_str(&text)
+                .map(Reply::Json)
+                .map_err(|err| ClientError::InvalidResponse(err.to_string()))
+        } else {
+            Ok(Reply::Text(text))
+        }
+    }
+
+    /// Call the handler with each chunk of the response as it arrives
+    pub async fn stream<F: FnMut(&str)>(
+        &self,
+        path: &str,
+        mut handler: F,
+    ) -> Result<(), ClientError> {
+        let response: Response<Body> = self.request(Method::GET, path, &[], None).await?;
+
+        if !response.status().is_success() {
+            let status: StatusCode = response.status();
+            let bytes: Bytes = hyper::body::to_bytes(response.into_body())
+                .await
+                .map_err(|err| ClientError::Connection(err.to_string()))?;
+
+            return Err(ClientError::Rejected(
+                status,
+                error_message(&String::from_utf8_lossy(&bytes)),
+            ));
+        }
+
+        let mut body: Body = response.into_body();
+
+        while let Some(chunk) = body.data().await {
+            let chunk: Bytes = chunk.map_err(|err| ClientError::Connection(err.to_string()))?;
+
+            handler(&String::from_utf8_lossy(&chunk));
+        }
+
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &str,
+        query: &[(&str, String)],
+        body: Option<String>,
+    ) -> Result<Response<Body>, ClientError> {
+        let uri: Uri =
+            self.uri(path, query)
+                .parse()
+                .map_err(|err: hyper::http::uri::InvalidUri| {
+                    ClientError::Connection(err.to_string())
+                })?;
+
+        if let Some(connector) = &self.tls {
+            let mut builder: Builder = Request::builder().method(method).uri(
+                uri.path_and_query()
+                    .map(|path| path.as_str())
+                    .unwrap_or("/"),
+            );
+
+            if let Some(authority) = uri.authority() {
+                builder = builder.header(header::HOST, authority.as_str());
+            }
+
+            return self
+                .request_tls(connector, &uri, self.with_headers(builder, body)?)
+                .await;
+        }
+
+        let builder: Builder = Request::builder().method(method).uri(uri);
+
+        self.client
+            .request(self.with_headers(builder, body)?)
+            .await
+            .map_err(|err| ClientError::Connection(err.to_string()))
+    }
+
+    fn with_headers(
+        &self,
+        mut builder: Builder,
+        body: Option<String>,
+    ) -> Result<Request<Body>, ClientError> {
+        if let Some(token) = &self.token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+
+        if body.is_some() {
+            builder = builder.header(header::CONTENT_TYPE, APPLICATION_JSON);
+        }
+
+        builder
+            .body(body.map(Body::from).unwrap_or_else(Body::empty))
+            .map_err(|err| ClientError::Connection(err.to_string()))
+    }
+
+    // Send a request over a new TLS connection to the instance
+    async fn request_tls(
+        &self,
+        connector: &TlsConnector,
+        uri: &Uri,
+        request: Request<Body>,
+    ) -> Result<Response<Body>, ClientError> {
+        let connection_error = |err: std::io::Error| ClientError::Connection(err.to_string());
+
+        let host: &str = uri.host().unwrap_or_default();
+        let server_name: ServerName<'static> = ServerName::try_from(host.to_string())
+            .map_err(|err| ClientError::Connection(err.to_string()))?;
+
+        let stream: TcpStream = TcpStream::connect((host, uri.port_u16().unwrap_or(HTTPS_PORT)))
+            .await
+            .map_err(connection_error)?;
+        let stream = connector
+            .connect(server_name, stream)
+            .await
+            .map_err(connection_error)?;
+
+        let (mut sender, connection) = hyper::client::conn::handshake(stream)
+            .await
+            .map_err(|err| ClientError::Connection(err.to_string()))?;
+
+        // The connection is driven separately so streamed bodies keep arriving
+        tokio::spawn(connection);
+
+        sender
+            .send_request(request)
+            .await
+            .map_err(|err| ClientError::Connection(err.to_string()))
+    }
+
+    fn uri(&self, path: &str, query: &[(&str, String)]) -> String {
+        if query.is_empty() {
+            return format!("{}{}{}", self.base_url, API_PREFIX, path);
+        }
+
+        let query: String = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(query)
+            .finish();
+
+        format!("{}{}{}?{}", self.base_url, API_PREFIX, path, query)
+    }
+}
+
+// Errors are sent as a JSON body with a code and message
+fn error_message(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(error) => format!(
+            "{} ({})",
+            error["message"].as_str().unwrap_or(body),
+            error["code"].as_str().unwrap_or("unknown")
+        ),
+        Err(_) => body.to_string(),
+    }
+}
@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+
+use serde_json::{json, Map, Value};
+
+use crate::client::{CascadeClient, ClientError, Reply};
+use crate::output::{print_json, print_reply, OutputFormat};
+use crate::{
+    AddConnection, AddNode, Command, ComponentKind, ConnectionCommand, FlowCommand, NodeCommand,
+    ParameterCommand, SetParameters, UpdateConnection, UpdateNode,
+};
+
+// Columns shown when listing each resource as a table
+const NODE_COLUMNS: &[&str] = &[
+    "idx",
+    "id",
+    "display_name",
+    "type_name",
+    "running",
+    "schedule",
+];
+const CONNECTION_COLUMNS: &[&str] = &["idx", "id", "name", "source", "target", "max_items"];
+const STATS_COLUMNS: &[&str] = &[
+    "idx",
+    "id",
+    "name",
+    "stats.count",
+    "stats.max_items",
+    "stats.expired",
+];
+const MESSAGE_COLUMNS: &[&str] = &["id", "created_nanos", "properties", "content"];
+const BULLETIN_COLUMNS: &[&str] = &["timestamp_millis", "severity", "display_name", "message"];
+const PROVENANCE_COLUMNS: &[&str] = &[
+    "timestamp_millis",
+    "event_type",
+    "message_id",
+    "source_id",
+    "details",
+];
+const PARAMETER_CONTEXT_COLUMNS: &[&str] = &["name", "parameters"];
+const CHANGE_COLUMNS: &[&str] = &["field", "before", "after"];
+const AUDIT_COLUMNS: &[&str] = &[
+    "timestamp_millis",
+    "principal",
+    "operation",
+    "targets",
+    "error",
+];
+
+#[derive(Debug)]
+pub enum CommandError {
+    Client(ClientError),
+    // Reading or writing local files
+    Local(String),
+}
+
+impl From<ClientError> for CommandError {
+    fn from(value: ClientError) -> Self {
+        CommandError::Client(value)
+    }
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Client(err) => err.fmt(f),
+            CommandError::Local(err) => f.write_str(err),
+        }
+    }
+}
+
+pub async fn run(
+    client: &CascadeClient,
+    format: OutputFormat,
+    command: Command,
+) -> Result<(), CommandError> {
+    match command {
+        Command::Types => {
+            let reply: Reply = client.get("/component-types", &[]).await?;
+            print_reply(format, reply, &[]);
+        }
+        Command::Nodes(command) => run_node_command(client, format, command).await?,
+        Command::Connections(command) => run_connection_command(client, format, command).await?,
+        Command::Bulletins {
+            component_id,
+            severity,
+        } => {
+            let query: Vec<(&str, String)> =
+                [("component_id", component_id), ("severity", severity)]
+                    .into_iter()
+                    .filter_map(|(name, value)| value.map(|value| (name, value)))
+                    .collect();
+
+            let reply: Reply = client.get("/bulletins", &query).await?;
+            print_reply(format, reply, BULLETIN_COLUMNS);
+        }
+        Command::Provenance { message_id } => {
+            let query: Vec<(&str, String)> = message_id
+                .map(|message_id| vec![("message_id", message_id)])
+                .unwrap_or_default();
+
+            let reply: Reply = client.get("/provenance", &query).await?;
+            print_reply(format, reply, PROVENANCE_COLUMNS);
+        }
+        Command::Audit {
+            principal,
+            operation,
+            target,
+            since_millis,
+            limit,
+        } => {
+            let query: Vec<(&str, String)> = [
+                ("principal", principal),
+                ("operation", operation),
+                ("target", target),
+                ("since_millis", since_millis.map(|since| since.to_string())),
+                ("limit", limit.map(|limit| limit.to_string())),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .collect();
+
+            let reply: Reply = client.get("/audit", &query).await?;
+            print_reply(format, reply, AUDIT_COLUMNS);
+        }
+        Command::Events => {
+            client
+                .stream("/events", |chunk| {
+                    // Print the payload of each server sent event on its own line
+                    chunk
+                        .lines()
+                        .filter_map(|line| line.strip_prefix("data: "))
+                        .for_each(|data| println!("{}", data));
+                })
+                .await?
+        }
+        Command::Flow(command) => run_flow_command(client, format, command).await?,
+        Command::Parameters(command) => run_parameter_command(client, format, command).await?,
+    }
+
+    Ok(())
+}
+
+async fn run_node_command(
+    client: &CascadeClient,
+    format: OutputFormat,
+    command: NodeCommand,
+) -> Result<(), CommandError> {
+    let (reply, columns): (Reply, &[&str]) = match command {
+        NodeCommand::List => (client.get("/components", &[]).await?, NODE_COLUMNS),
+        NodeCommand::Show { id } => (client.get(&component_path(&id), &[]).await?, &[]),
+        NodeCommand::Add(add) => (
+            client
+                .post("/components", &[], Some(node_definition(add)?))
+                .await?,
+            &[],
+        ),
+        NodeCommand::Update(update) => {
+            let path: String = component_path(&update.id);
+            let query: [(&str, String); 1] = [("restart", update.restart.to_string())];
+
+            match client
+                .patch(&path, &query, Some(node_update(update)?))
+                .await?
+            {
+                // Only the changes are shown as a table
+                Reply::Json(value) if format == OutputFormat::Table => {
+                    (Reply::Json(value["changes"].clone()), CHANGE_COLUMNS)
+                }
+                reply => (reply, &[]),
+            }
+        }
+        NodeCommand::Remove { id } => {
+            client.delete(&component_path(&id), &[], None).await?;
+
+            (Reply::Text(format!("Removed component {}", id)), &[])
+        }
+        NodeCommand::Start { id } => (
+            client
+                .post(&format!("{}/start", component_path(&id)), &[], None)
+                .await?,
+            &[],
+        ),
+        NodeCommand::Stop { id } => (
+            client
+                .post(&format!("{}/stop", component_path(&id)), &[], None)
+                .await?,
+            &[],
+        ),
+        NodeCommand::Kill { id } => (
+            client
+                .post(&format!("{}/kill", component_path(&id)), &[], None)
+                .await?,
+            &[],
+        ),
+        NodeCommand::LogLevel { id, level } => (
+            client
+                .put(
+                    &format!("{}/log-level", component_path(&id)),
+                    &[],
+                    Some(json!({ "level": level }).to_string()),
+                )
+                .await?,
+            &[],
+        ),
+    };
+
+    print_reply(format, reply, columns);
+
+    Ok(())
+}
+
+fn component_path(id: &str) -> String {
+    format!("/components/{}", id)
+}
+
+fn connection_path(id: &str) -> String {
+    format!("/connections/{}", id)
+}
+
+fn node_definition(add: AddNode) -> Result<String, CommandError> {
+    if let Some(path) = add.file {
+        return read_file(&path);
+    }
+
+    let config: Value = serde_json::from_str(&add.config)
+        .map_err(|err| CommandError::Local(format!("Config was not valid JSON: {}", err)))?;
+    // Required by clap unless a file is given
+    let type_name: String = add.type_name.unwrap();
+
+    let mut def: Map<String, Value> = Map::new();
+    def.insert(
+        "display_name".to_string(),
+        json!(add.name.unwrap_or(type_name.clone())),
+    );
+    def.insert("type_name".to_string(), json!(type_name));
+    def.insert(
+        "component_type".to_string(),
+        json!(match add.kind {
+            ComponentKind::Producer => "Producer",
+            ComponentKind::Processor => "Processor",
+        }),
+    );
+    def.insert("config".to_string(), config);
+
+    if let Some(parameter_context) = add.parameter_context {
+        def.insert("parameter_context".to_string(), json!(parameter_context));
+    }
+
+    if let Some(period_millis) = add.period_millis {
+        def.insert(
+            "schedule".to_string(),
+            json!({ "type": "Interval", "period_millis": period_millis }),
+        );
+    }
+
+    if let Some(concurrency) = add.concurrency {
+        def.insert(
+            "schedule".to_string(),
+            json!({ "type": "Unbounded", "concurrency": concurrency }),
+        );
+    }
+
+    Ok(Value::Object(def).to_string())
+}
+
+fn node_update(update: UpdateNode) -> Result<String, CommandError> {
+    let mut body: Map<String, Value> = Map::new();
+
+    if let Some(name) = update.name {
+        body.insert("display_name".to_string(), json!(name));
+    }
+
+    if let Some(config) = update.config {
+        let config: Value = serde_json::from_str(&config)
+            .map_err(|err| CommandError::Local(format!("Config was not valid JSON: {}", err)))?;
+        body.insert("config".to_string(), config);
+    }
+
+    // Null stops using a context
+    if update.no_parameter_context || update.parameter_context.is_some() {
+        body.insert(
+            "parameter_context".to_string(),
+            json!(update.parameter_context),
+        );
+    }
+
+    if let Some(period_millis) = update.period_millis {
+        body.insert(
+            "schedule".to_string(),
+            json!({ "type": "Interval", "period_millis": period_millis }),
+        );
+    }
+
+    if let Some(concurrency) = update.concurrency {
+        body.insert(
+            "schedule".to_string(),
+            json!({ "type": "Unbounded", "concurrency": concurrency }),
+        );
+    }
+
+    Ok(Value::Object(body).to_string())
+}
+
+async fn run_connection_command(
+    client: &CascadeClient,
+    format: OutputFormat,
+    command: ConnectionCommand,
+) -> Result<(), CommandError> {
+    let (reply, columns): (Reply, &[&str]) = match command {
+        ConnectionCommand::List => (client.get("/connections", &[]).await?, CONNECTION_COLUMNS),
+        ConnectionCommand::Show { id } => (client.get(&connection_path(&id), &[]).await?, &[]),
+        ConnectionCommand::Add(add) => {
+            let def: String = connection_definition(client, add).await?;
+
+            (client.post("/connections", &[], Some(def)).await?, &[])
+        }
+        ConnectionCommand::Update(update) => {
+            let path: String = connection_path(&update.id);
+            let query: [(&str, String); 1] = [("restart", update.restart.to_string())];
+            let body: String = connection_update(client, update).await?;
+
+            match client.patch(&path, &query, Some(body)).await? {
+                // Only the changes are shown as a table
+                Reply::Json(value) if format == OutputFormat::Table => {
+                    (Reply::Json(value["changes"].clone()), CHANGE_COLUMNS)
+                }
+                reply => (reply, &[]),
+            }
+        }
+        ConnectionCommand::Remove { id } => {
+            client.delete(&connection_path(&id), &[], None).await?;
+
+            (Reply::Text(format!("Removed connection {}", id)), &[])
+        }
+        ConnectionCommand::Stats { id: Some(id) } => (
+            client
+                .get(&format!("{}/stats", connection_path(&id)), &[])
+                .await?,
+            &[],
+        ),
+        ConnectionCommand::Stats { id: None } => {
+            (client.get("/connections", &[]).await?, STATS_COLUMNS)
+        }
+        ConnectionCommand::Peek { id, limit } => {
+            let query: Vec<(&str, String)> = limit
+                .map(|limit| vec![("limit", limit.to_string())])
+                .unwrap_or_default();
+
+            (
+                client
+                    .get(&format!("{}/messages", connection_path(&id)), &query)
+                    .await?,
+                MESSAGE_COLUMNS,
+            )
+        }
+        ConnectionCommand::Purge {
+            id,
+            properties,
+            older_than_millis,
+        } => {
+            let filter: Value = json!({
+                "properties": properties.into_iter().collect::<HashMap<String, String>>(),
+                "older_than_millis": older_than_millis,
+            });
+
+            (
+                client
+                    .delete(
+                        &format!("{}/messages", connection_path(&id)),
+                        &[],
+                        Some(filter.to_string()),
+                    )
+                    .await?,
+                &[],
+            )
+        }
+    };
+
+    print_reply(format, reply, columns);
+
+    Ok(())
+}
+
+async fn connection_definition(
+    client: &CascadeClient,
+    add: AddConnection,
+) -> Result<String, CommandError> {
+    if let Some(path) = add.file {
+        return read_file(&path);
+    }
+
+    // Both are required by clap unless a file is given
+    Ok(json!({
+        "name": add.name,
+        "source": component_index(client, &add.source.unwrap()).await?,
+        "target": component_index(client, &add.target.unwrap()).await?,
+        "max_items": add.max_items,
+        "expiration_millis": add.expiration_millis,
+    })
+    .to_string())
+}
+
+async fn connection_update(
+    client: &CascadeClient,
+    update: UpdateConnection,
+) -> Result<String, CommandError> {
+    let mut body: Map<String, Value> = Map::new();
+
+    if let Some(name) = update.name {
+        body.insert("name".to_string(), json!(name));
+    }
+
+    if let Some(target) = update.target {
+        body.insert(
+            "target".to_string(),
+            json!(component_index(client, &target).await?),
+        );
+    }
+
+    if let Some(max_items) = update.max_items {
+        body.insert("max_items".to_string(), json!(max_items));
+    }
+
+    // Null removes the expiration
+    if update.no_expiration || update.expiration_millis.is_some() {
+        body.insert(
+            "expiration_millis".to_string(),
+            json!(update.expiration_millis),
+        );
+    }
+
+    Ok(Value::Object(body).to_string())
+}
+
+// Connections refer to components by index, so look up any ids
+async fn component_index(client: &CascadeClient, component: &str) -> Result<u64, CommandError> {
+    if let Ok(idx) = component.parse::<u64>() {
+        return Ok(idx);
+    }
+
+    let Reply::Json(component) = client.get(&component_path(component), &[]).await? else {
+        return Err(ClientError::InvalidResponse("Component was not JSON".to_string()).into());
+    };
+
+    component["idx"]
+        .as_u64()
+        .ok_or(ClientError::InvalidResponse("Component had no idx".to_string()).into())
+}
+
+async fn run_flow_command(
+    client: &CascadeClient,
+    format: OutputFormat,
+    command: FlowCommand,
+) -> Result<(), CommandError> {
+    match command {
+        FlowCommand::Export { file } => {
+            let Reply::Json(flow) = client.get("/flow", &[]).await? else {
+                return Err(ClientError::InvalidResponse("Flow was not JSON".to_string()).into());
+            };
+
+            match file {
+                Some(path) => fs::write(&path, serde_json::to_string_pretty(&flow).unwrap())
+                    .map_err(|err| {
+                        CommandError::Local(format!("Could not write {}: {}", path, err))
+                    })?,
+                // The flow is always printed as JSON so it can be imported again
+                None => print_json(&flow),
+            }
+        }
+        FlowCommand::Import { file } => {
+            let reply: Reply = client.post("/flow", &[], Some(read_file(&file)?)).await?;
+
+            print_reply(format, reply, &[]);
+        }
+        FlowCommand::Reload { file, dry_run } => {
+            let query: [(&str, String); 1] = [("dry_run", dry_run.to_string())];
+            let reply: Reply = client.put("/flow", &query, Some(read_file(&file)?)).await?;
+
+            print_reply(format, reply, &[]);
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_parameter_command(
+    client: &CascadeClient,
+    format: OutputFormat,
+    command: ParameterCommand,
+) -> Result<(), CommandError> {
+    let (reply, columns): (Reply, &[&str]) = match command {
+        ParameterCommand::List => (
+            client.get("/parameter-contexts", &[]).await?,
+            PARAMETER_CONTEXT_COLUMNS,
+        ),
+        ParameterCommand::Show { name } => {
+            match client.get(&parameter_context_path(&name), &[]).await? {
+                // Each parameter is shown as a row
+                Reply::Json(value) if format == OutputFormat::Table => {
+                    (Reply::Json(value["parameters"].clone()), &[])
+                }
+                reply => (reply, &[]),
+            }
+        }
+        ParameterCommand::Set(set) => {
+            let path: String = parameter_context_path(&set.name);
+
+            (client.put(&path, &[], Some(parameter_context(set)?)).await?, &[])
+        }
+        ParameterCommand::Remove { name } => {
+            client
+                .delete(&parameter_context_path(&name), &[], None)
+                .await?;
+
+            (Reply::Text(format!("Removed parameter context {}", name)), &[])
+        }
+    };
+
+    print_reply(format, reply, columns);
+
+    Ok(())
+}
+
+fn parameter_context_path(name: &str) -> String {
+    format!("/parameter-contexts/{}", name)
+}
+
+fn parameter_context(set: SetParameters) -> Result<String, CommandError> {
+    if let Some(path) = set.file {
+        return read_file(&path);
+    }
+
+    let parameters: Map<String, Value> = set
+        .parameters
+        .into_iter()
+        .map(|parameter| (parameter, false))
+        .chain(set.sensitive.into_iter().map(|parameter| (parameter, true)))
+        .map(|((name, value), sensitive)| {
+            // Anything which isn't valid JSON is taken as a string
+            let value: Value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+
+            (name, json!({ "value": value, "sensitive": sensitive }))
+        })
+        .collect();
+
+    Ok(json!({ "parameters": parameters }).to_string())
+}
+
+fn read_file(path: &str) -> Result<String, CommandError> {
+    fs::read_to_string(path)
+        .map_err(|err| CommandError::Local(format!("Could not read {}: {}", path, err)))
+}
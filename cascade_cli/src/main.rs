@@ -0,0 +1,358 @@
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use tokio_rustls::TlsConnector;
+
+use crate::client::{CascadeClient, ClientError};
+use crate::command::CommandError;
+use crate::output::OutputFormat;
+use crate::tls::TlsOptions;
+
+mod client;
+mod command;
+mod output;
+mod tls;
+
+// Exit codes for scripting, clap exits with 2 for usage errors
+const EXIT_REJECTED: u8 = 1;
+const EXIT_UNREACHABLE: u8 = 3;
+const EXIT_LOCAL: u8 = 4;
+
+/// Manage a running cascade instance
+#[derive(Debug, Parser)]
+#[command(name = "cascade", version)]
+pub struct Cli {
+    /// Base url of the instance
+    #[arg(
+        long,
+        env = "CASCADE_URL",
+        default_value = "http://127.0.0.1:3001",
+        global = true
+    )]
+    pub url: String,
+
+    /// Bearer token, either a static API token or a JWT
+    #[arg(long, env = "CASCADE_TOKEN", hide_env_values = true, global = true)]
+    pub token: Option<String>,
+
+    #[command(flatten)]
+    pub tls: TlsOptions,
+
+    /// How to print responses
+    #[arg(short, long, value_enum, default_value = "table", global = true)]
+    pub output: OutputFormat,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the component types known to the instance
+    Types,
+    /// Manage components in the graph
+    #[command(subcommand)]
+    Nodes(NodeCommand),
+    /// Manage connections between components
+    #[command(subcommand)]
+    Connections(ConnectionCommand),
+    /// List warnings and errors raised by components
+    Bulletins {
+        #[arg(long)]
+        component_id: Option<String>,
+        /// Minimum severity, either Warning or Error
+        #[arg(long)]
+        severity: Option<String>,
+    },
+    /// List provenance events
+    Provenance {
+        #[arg(long)]
+        message_id: Option<String>,
+    },
+    /// List changes made through the API and who made them
+    Audit {
+        #[arg(long)]
+        principal: Option<String>,
+        /// Operation id such as createComponent
+        #[arg(long)]
+        operation: Option<String>,
+        /// Id of an affected component or connection
+        #[arg(long)]
+        target: Option<String>,
+        #[arg(long)]
+        since_millis: Option<u128>,
+        /// Only show the most recent records
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Print events from the instance as they happen
+    Events,
+    /// Import or export whole flows
+    #[command(subcommand)]
+    Flow(FlowCommand),
+    /// Manage parameter contexts referenced from component configs
+    #[command(subcommand)]
+    Parameters(ParameterCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum NodeCommand {
+    /// List components with their status
+    List,
+    /// Describe a component and its recent bulletins
+    Show { id: String },
+    /// Add a component from flags or a JSON definition file
+    Add(AddNode),
+    /// Change the name, schedule or config of a component and show what changed
+    Update(UpdateNode),
+    /// Remove a stopped component along with its connections
+    Remove { id: String },
+    /// Start a component
+    Start { id: String },
+    /// Stop a component once its current invocation finishes
+    Stop { id: String },
+    /// Stop a component immediately
+    Kill { id: String },
+    /// Set the log level of a component
+    LogLevel { id: String, level: String },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ComponentKind {
+    Producer,
+    Processor,
+}
+
+#[derive(Debug, Args)]
+pub struct AddNode {
+    /// JSON component definition, used instead of the other flags
+    #[arg(long, conflicts_with_all = ["type_name", "name"])]
+    pub file: Option<String>,
+
+    #[arg(long = "type", required_unless_present = "file")]
+    pub type_name: Option<String>,
+
+    /// Display name, defaults to the type
+    #[arg(long)]
+    pub name: Option<String>,
+
+    #[arg(long, value_enum, default_value = "processor")]
+    pub kind: ComponentKind,
+
+    /// JSON config for the component
+    #[arg(long, default_value = "{}")]
+    pub config: String,
+
+    /// Run on an interval, the instance default is used if not given
+    #[arg(long, conflicts_with = "concurrency")]
+    pub period_millis: Option<u64>,
+
+    /// Run continuously with this many concurrent invocations
+    #[arg(long)]
+    pub concurrency: Option<u8>,
+
+    /// Context to resolve #{name} references in the config from
+    #[arg(long)]
+    pub parameter_context: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateNode {
+    pub id: String,
+
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// JSON config replacing the current config
+    #[arg(long)]
+    pub config: Option<String>,
+
+    /// Run on an interval
+    #[arg(long, conflicts_with = "concurrency")]
+    pub period_millis: Option<u64>,
+
+    /// Run continuously with this many concurrent invocations
+    #[arg(long)]
+    pub concurrency: Option<u8>,
+
+    /// Context to resolve #{name} references in the config from
+    #[arg(long, conflicts_with = "no_parameter_context")]
+    pub parameter_context: Option<String>,
+
+    /// Stop resolving references from a parameter context
+    #[arg(long)]
+    pub no_parameter_context: bool,
+
+    /// Stop and start the component again if it's running
+    #[arg(long)]
+    pub restart: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConnectionCommand {
+    /// List connection definitions
+    List,
+    /// Describe a connection
+    Show { id: String },
+    /// Connect two components
+    Add(AddConnection),
+    /// Change the settings or target of a connection, keeping its queued messages
+    Update(UpdateConnection),
+    /// Remove a connection between stopped components
+    Remove { id: String },
+    /// Show queue stats of one or every connection
+    Stats { id: Option<String> },
+    /// Show the next queued messages without removing them
+    Peek {
+        id: String,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// Drop queued messages, all of them unless filtered
+    Purge {
+        id: String,
+        /// Only drop messages with this property, as name=value
+        #[arg(long = "property", value_parser = parse_property)]
+        properties: Vec<(String, String)>,
+        #[arg(long)]
+        older_than_millis: Option<u64>,
+    },
+}
+
+#[derive(Debug, Args)]
+pub struct AddConnection {
+    /// JSON connection definition, used instead of the other arguments
+    #[arg(long, conflicts_with_all = ["source", "target"])]
+    pub file: Option<String>,
+
+    /// Id or index of the component to connect from
+    #[arg(required_unless_present = "file")]
+    pub source: Option<String>,
+    /// Id or index of the component to connect to
+    #[arg(required_unless_present = "file")]
+    pub target: Option<String>,
+
+    /// Output of the source component to connect
+    #[arg(long, default_value = "default")]
+    pub name: String,
+
+    #[arg(long, default_value_t = 1000)]
+    pub max_items: usize,
+
+    #[arg(long)]
+    pub expiration_millis: Option<u64>,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateConnection {
+    pub id: String,
+
+    /// Output of the source component to connect
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Id or index of the component to redirect to
+    #[arg(long)]
+    pub target: Option<String>,
+
+    #[arg(long)]
+    pub max_items: Option<usize>,
+
+    #[arg(long, conflicts_with = "no_expiration")]
+    pub expiration_millis: Option<u64>,
+
+    /// Stop messages on the connection from expiring
+    #[arg(long)]
+    pub no_expiration: bool,
+
+    /// Stop and start any running components which need rewiring
+    #[arg(long)]
+    pub restart: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum FlowCommand {
+    /// Write the graph as a JSON flow to a file or stdout
+    Export {
+        #[arg(long)]
+        file: Option<String>,
+    },
+    /// Add the components and connections of a JSON flow to the graph
+    Import { file: String },
+    /// Replace the graph with a JSON flow, restarting only the components it affects
+    Reload {
+        file: String,
+        /// Show what would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ParameterCommand {
+    /// List parameter contexts, with sensitive values masked
+    List,
+    /// Show the parameters of a context
+    Show { name: String },
+    /// Create or replace a context from flags or a JSON file
+    Set(SetParameters),
+    /// Remove a context which no component refers to
+    Remove { name: String },
+}
+
+#[derive(Debug, Args)]
+pub struct SetParameters {
+    pub name: String,
+
+    /// JSON parameter context, used instead of the other flags
+    #[arg(long, conflicts_with_all = ["parameters", "sensitive"])]
+    pub file: Option<String>,
+
+    /// Parameter as name=value, values which are valid JSON keep their type
+    #[arg(long = "param", value_parser = parse_property)]
+    pub parameters: Vec<(String, String)>,
+
+    /// Parameter as name=value which is masked whenever it's shown
+    #[arg(long = "sensitive", value_parser = parse_property)]
+    pub sensitive: Vec<(String, String)>,
+}
+
+fn parse_property(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or(format!("Expected name=value but got {}", value))
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> ExitCode {
+    let cli: Cli = Cli::parse();
+
+    // Certificates are only needed for https urls
+    let tls: Option<TlsConnector> = if cli.url.starts_with("https://") {
+        match tls::connector(&cli.tls) {
+            Ok(connector) => Some(connector),
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::from(EXIT_LOCAL);
+            }
+        }
+    } else {
+        None
+    };
+
+    let client: CascadeClient = CascadeClient::new(&cli.url, cli.token, tls);
+
+    match command::run(&client, cli.output, cli.command).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+
+            ExitCode::from(match err {
+                CommandError::Client(ClientError::Rejected(..)) => EXIT_REJECTED,
+                CommandError::Client(_) => EXIT_UNREACHABLE,
+                CommandError::Local(_) => EXIT_LOCAL,
+            })
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::client::Reply;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Print a reply from the instance
+/// Tables show the given columns of each object, or every field of a single object
+/// Columns of nested fields are separated with dots, such as stats.count
+pub fn print_reply(format: OutputFormat, reply: Reply, columns: &[&str]) {
+    match (format, reply) {
+        (OutputFormat::Json, Reply::Json(value)) => print_json(&value),
+        // Keep JSON output parseable when the instance replies with a message
+        (OutputFormat::Json, Reply::Text(text)) => print_json(&json!({ "message": text })),
+        (OutputFormat::Table, Reply::Text(text)) => println!("{}", text),
+        (_, Reply::Empty) => {}
+        (OutputFormat::Table, Reply::Json(value)) => print_table(&value, columns),
+    }
+}
+
+pub fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+fn print_table(value: &Value, columns: &[&str]) {
+    match value {
+        Value::Array(items) if items.iter().all(Value::is_object) && !columns.is_empty() => {
+            let rows: Vec<Vec<String>> = items
+                .iter()
+                .map(|item| {
+                    columns
+                        .iter()
+                        .map(|column| render_cell(item.pointer(&column_pointer(column))))
+                        .collect()
+                })
+                .collect();
+
+            // Nested columns are labelled by their last field
+            let headers: Vec<&str> = columns
+                .iter()
+                .map(|column| column.rsplit('.').next().unwrap_or(column))
+                .collect();
+
+            print_rows(&headers, rows);
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| println!("{}", render_cell(Some(item)))),
+        Value::Object(fields) => print_fields(fields),
+        other => println!("{}", render_cell(Some(other))),
+    }
+}
+
+fn column_pointer(column: &str) -> String {
+    format!("/{}", column.replace('.', "/"))
+}
+
+fn print_fields(fields: &Map<String, Value>) {
+    let rows: Vec<Vec<String>> = fields
+        .iter()
+        .map(|(key, value)| vec![key.clone(), render_cell(Some(value))])
+        .collect();
+
+    print_rows(&["field", "value"], rows);
+}
+
+fn print_rows(headers: &[&str], rows: Vec<Vec<String>>) {
+    // Each column is as wide as its widest cell
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(idx, header)| {
+            rows.iter()
+                .map(|row| row[idx].chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let header: Vec<String> = headers.iter().map(|header| header.to_uppercase()).collect();
+    print_row(&header, &widths);
+
+    for row in rows {
+        print_row(&row, &widths);
+    }
+}
+
+fn print_row(cells: &[String], widths: &[usize]) {
+    let line: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+        .collect();
+
+    println!("{}", line.join("  ").trim_end());
+}
+
+fn render_cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::from("-"),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) if items.is_empty() => String::from("-"),
+        // Nested values are shown compactly
+        Some(other) => other.to_string(),
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use clap::Args;
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// Certificates used when the url is https
+#[derive(Debug, Args)]
+pub struct TlsOptions {
+    /// PEM certificates to trust instead of the public web roots
+    #[arg(long, env = "CASCADE_CA_CERT", global = true)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// PEM certificate to present for mutual TLS
+    #[arg(
+        long,
+        env = "CASCADE_CLIENT_CERT",
+        requires = "client_key",
+        global = true
+    )]
+    pub client_cert: Option<PathBuf>,
+
+    /// PEM private key of the client certificate
+    #[arg(
+        long,
+        env = "CASCADE_CLIENT_KEY",
+        requires = "client_cert",
+        global = true
+    )]
+    pub client_key: Option<PathBuf>,
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, String> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| format!("Could not read {}: {}", path.display(), err))
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<_, _>>()
+        .map_err(|err| format!("Could not read {}: {}", path.display(), err))
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|err| format!("Could not read {}: {}", path.display(), err))?
+        .ok_or(format!("No PEM private key found in {}", path.display()))
+}
+
+pub fn connector(options: &TlsOptions) -> Result<TlsConnector, String> {
+    let mut roots: RootCertStore = RootCertStore::empty();
+
+    match &options.ca_cert {
+        Some(path) => {
+            for certificate in load_certificates(path)? {
+                roots.add(certificate).map_err(|err| err.to_string())?;
+            }
+        }
+        None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+    }
+
+    let provider: Arc<CryptoProvider> = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|err| err.to_string())?
+        .with_root_certificates(roots);
+
+    let config: ClientConfig = match (&options.client_cert, &options.client_key) {
+        (Some(certificate), Some(private_key)) => builder
+            .with_client_auth_cert(
+                load_certificates(certificate)?,
+                load_private_key(private_key)?,
+            )
+            .map_err(|err| err.to_string())?,
+        _ => builder.with_no_client_auth(),
+    };
+
+    Ok(TlsConnector::from(Arc::new(config)))
+}
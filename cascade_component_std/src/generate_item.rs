@@ -30,10 +30,10 @@ impl NamedComponent for GenerateItem {
 #[async_trait]
 
 impl Process for GenerateItem {
-    fn create_from_json(config: Value) -> Arc<dyn Process> {
-        let generate_item: GenerateItem = serde_json::from_value(config).unwrap();
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        let generate_item: GenerateItem = serde_json::from_value(config)?;
 
-        Arc::new(generate_item)
+        Ok(Arc::new(generate_item))
     }
 
     async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
@@ -1,26 +1,142 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::{DirEntry, Metadata, ReadDir};
-use std::path::Path;
+use std::fs::{DirEntry, File, Metadata, OpenOptions, ReadDir};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
-use serde::Deserialize;
+use globset::{Glob, GlobMatcher};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use tokio::sync::{Mutex, MutexGuard};
 
 use cascade_api::component::{NamedComponent, Process};
 use cascade_api::component::environment::ExecutionEnvironment;
 use cascade_api::component::error::ComponentError;
+use cascade_api::expression::Expression;
 use cascade_api::message::content::Content;
 use cascade_api::message::Message;
 
-#[derive(Deserialize)]
-pub struct GetFile {
+/// Matched against the path of a file relative to the polled directory
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FilePattern {
+    Glob { pattern: String },
+    Regex { pattern: String },
+}
+
+/// What happens to a file once it has been emitted
+#[derive(Default, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Completion {
+    // Left where it is, it's only emitted again if it's modified
+    #[default]
+    Keep,
+    Delete,
+    // Moved to the same path relative to the directory, never replacing a file already there
+    Move { directory: PathBuf },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GetFileConfig {
     // Amount of files to emit from each scheduled run
     pub batch_size: i32,
-    // Path to poll for files
-    pub path: Box<Path>,
+    // Path to poll for files, an expression evaluated at the start of each run
+    // There's no message to evaluate against, but functions such as now() can be used
+    pub path: Expression,
+    // Also poll every directory within the path
+    #[serde(default)]
+    pub recurse: bool,
+    // Files must match one of these if any are given
+    #[serde(default)]
+    pub include: Vec<FilePattern>,
+    // Files matching any of these are skipped
+    #[serde(default)]
+    pub exclude: Vec<FilePattern>,
+    // Skip files modified more recently than this, which may still be being written
+    #[serde(default)]
+    pub min_age_millis: Option<u64>,
+    #[serde(default)]
+    pub max_age_millis: Option<u64>,
+    #[serde(default)]
+    pub min_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+    #[serde(default)]
+    pub completion: Completion,
+}
+
+// Patterns compiled when the component is created
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn compile(pattern: &FilePattern) -> Result<Matcher, ComponentError> {
+        match pattern {
+            FilePattern::Glob { pattern } => Glob::new(pattern)
+                .map(|glob| Matcher::Glob(glob.compile_matcher()))
+                .map_err(|err| ComponentError::InvalidConfig(err.to_string())),
+            FilePattern::Regex { pattern } => Regex::new(pattern)
+                .map(Matcher::Regex)
+                .map_err(|err| ComponentError::InvalidConfig(err.to_string())),
+        }
+    }
+
+    fn is_match(&self, relative_path: &str) -> bool {
+        match self {
+            Matcher::Glob(glob) => glob.is_match(relative_path),
+            Matcher::Regex(regex) => regex.is_match(relative_path),
+        }
+    }
+}
+
+/// Files already emitted by path and when they were modified, so each is emitted once
+/// Persisted as the component state
+#[derive(Default, Serialize, Deserialize)]
+struct ListedFiles {
+    files: HashMap<String, u64>,
+
+    // Only saved if something changed during the run
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl ListedFiles {
+    fn record(&mut self, path: String, modified_nanos: u64) {
+        self.files.insert(path, modified_nanos);
+        self.changed = true;
+    }
+
+    // Forget files which have gone, so the state doesn't grow forever
+    fn retain_present(&mut self, present: &HashSet<String>) {
+        let before: usize = self.files.len();
+        self.files.retain(|path, _| present.contains(path));
+
+        self.changed |= self.files.len() != before;
+    }
+}
+
+// File found while listing the directory
+struct ListedFile {
+    path: PathBuf,
+    relative_path: String,
+    modified_nanos: u64,
+    metadata: Metadata,
+}
+
+pub struct GetFile {
+    config: GetFileConfig,
+    include: Vec<Matcher>,
+    exclude: Vec<Matcher>,
+
+    // Loaded from the component state on the first run
+    // Held for the whole run so concurrent runs don't emit the same files
+    listed: Mutex<Option<ListedFiles>>,
 }
 
 impl NamedComponent for GetFile {
@@ -34,70 +150,258 @@ impl NamedComponent for GetFile {
 
 #[async_trait]
 impl Process for GetFile {
-    fn create_from_json(config: Value) -> Arc<dyn Process>
+    /// This will fail if either:
+    ///     The path isn't a valid expression
+    ///     A pattern is invalid
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError>
     where
         Self: Sized,
     {
-        let get_file: GetFile = serde_json::from_value(config).unwrap();
+        let config: GetFileConfig = serde_json::from_value(config)?;
+
+        let compile = |patterns: &Vec<FilePattern>| {
+            patterns
+                .iter()
+                .map(Matcher::compile)
+                .collect::<Result<Vec<Matcher>, ComponentError>>()
+        };
 
-        Arc::new(get_file)
+        Ok(Arc::new(GetFile {
+            include: compile(&config.include)?,
+            exclude: compile(&config.exclude)?,
+            config,
+            listed: Default::default(),
+        }))
     }
 
     async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
+        let mut guard: MutexGuard<Option<ListedFiles>> = self.listed.lock().await;
+
+        if guard.is_none() {
+            *guard = Some(execution.state.load()?.unwrap_or_default());
+        }
+        let listed: &mut ListedFiles = guard.as_mut().unwrap();
+
+        let result: Result<(), ComponentError> = self.emit_files(execution, listed).await;
+
+        // Saved even if the run failed part way, so anything emitted isn't emitted again
+        if listed.changed {
+            execution.state.save(&*listed)?;
+            listed.changed = false;
+        }
+
+        result
+    }
+}
+
+impl GetFile {
+    async fn emit_files(
+        &self,
+        execution: &mut ExecutionEnvironment,
+        listed: &mut ListedFiles,
+    ) -> Result<(), ComponentError> {
+        let root: PathBuf = PathBuf::from(
+            self.config
+                .path
+                .evaluate_text(&Message::new(HashMap::new()))?,
+        );
+
         // Error if the directory can't be read
-        let entries: ReadDir = fs::read_dir(&self.path)?;
+        let mut files: Vec<ListedFile> = vec![];
+        self.list_directory(&root, &root, &mut files)?;
+
+        // Emitted in a stable order
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let present: HashSet<String> = files
+            .iter()
+            .map(|file| file.path.to_string_lossy().to_string())
+            .collect();
+        listed.retain_present(&present);
 
         let mut files_read: i32 = 0;
 
-        for entry in entries {
+        for file in files {
             // Break if we've read more files than batch_size
-            if files_read >= self.batch_size {
+            if files_read >= self.config.batch_size {
                 break;
             }
 
-            let dir_entry: DirEntry = entry?;
-            let metadata: Metadata = dir_entry.metadata()?;
+            let key: String = file.path.to_string_lossy().to_string();
 
-            // Skip anything other than a file
-            if !metadata.is_file() {
-                // TODO support dir recursing
+            // Skip files emitted before which haven't been modified since
+            if listed.files.get(&key) == Some(&file.modified_nanos) || !self.is_selected(&file) {
                 continue;
             }
 
-            let buffer: Vec<u8> = fs::read(dir_entry.path())?;
+            let buffer: Vec<u8> = fs::read(&file.path)?;
 
             let content: Content = Content::Memory { buffer };
 
             // Emit the file as a message
             execution
-                .send_default(Message::new_with_content(
-                    file_properties(dir_entry, metadata),
-                    content,
-                ))
+                .send_default(Message::new_with_content(file_properties(&file), content))
                 .await?;
 
+            listed.record(key, file.modified_nanos);
             files_read += 1;
+
+            // The file has already been emitted, so failing to clean it up isn't an error
+            if let Err(err) = self.complete(&file) {
+                execution.logger.warn(format_args!(
+                    "Failed to clean up {} after emitting it: {}",
+                    file.path.display(),
+                    err
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Every file within the directory, and those within it if recursing
+    fn list_directory(
+        &self,
+        root: &Path,
+        directory: &Path,
+        files: &mut Vec<ListedFile>,
+    ) -> Result<(), ComponentError> {
+        let entries: ReadDir = fs::read_dir(directory)?;
+        let mut directories: Vec<PathBuf> = vec![];
+
+        for entry in entries {
+            let dir_entry: DirEntry = entry?;
+            let metadata: Metadata = dir_entry.metadata()?;
+
+            if metadata.is_dir() && self.config.recurse {
+                directories.push(dir_entry.path());
+                continue;
+            }
+
+            // Skip anything other than a file
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let path: PathBuf = dir_entry.path();
+            let relative_path: String = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+
+            files.push(ListedFile {
+                modified_nanos: nanos_since_epoch(metadata.modified()?),
+                path,
+                relative_path,
+                metadata,
+            });
+        }
+
+        for directory in directories {
+            self.list_directory(root, &directory, files)?;
         }
 
         Ok(())
     }
+
+    // Whether the file passes the patterns, age and size limits
+    fn is_selected(&self, file: &ListedFile) -> bool {
+        let included: bool = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|matcher| matcher.is_match(&file.relative_path));
+        let excluded: bool = self
+            .exclude
+            .iter()
+            .any(|matcher| matcher.is_match(&file.relative_path));
+
+        let age: Duration = Duration::from_nanos(
+            nanos_since_epoch(SystemTime::now()).saturating_sub(file.modified_nanos),
+        );
+        let size: u64 = file.metadata.len();
+
+        included
+            && !excluded
+            && self
+                .config
+                .min_age_millis
+                .is_none_or(|min| age >= Duration::from_millis(min))
+            && self
+                .config
+                .max_age_millis
+                .is_none_or(|max| age <= Duration::from_millis(max))
+            && self.config.min_size_bytes.is_none_or(|min| size >= min)
+            && self.config.max_size_bytes.is_none_or(|max| size <= max)
+    }
+
+    fn complete(&self, file: &ListedFile) -> std::io::Result<()> {
+        match &self.config.completion {
+            Completion::Keep => Ok(()),
+            Completion::Delete => fs::remove_file(&file.path),
+            Completion::Move { directory } => {
+                // Files from different directories may share a name
+                let target: PathBuf = directory.join(&file.relative_path);
+
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+
+                move_file(&file.path, &target)
+            }
+        }
+    }
 }
 
-fn file_properties(file: DirEntry, metadata: Metadata) -> HashMap<String, String> {
+// Move a file without replacing one already at the target
+fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if target.exists() {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        ));
+    }
+
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+
+    // Renaming fails across file systems, so copy instead
+    let mut copy: File = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(target)?;
+    std::io::copy(&mut File::open(source)?, &mut copy)?;
+    copy.sync_all()?;
+
+    fs::remove_file(source)
+}
+
+fn nanos_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
+
+fn file_properties(file: &ListedFile) -> HashMap<String, String> {
+    let millis = |time: std::io::Result<SystemTime>| {
+        time.map(|time| (nanos_since_epoch(time) / 1_000_000).to_string())
+            .unwrap_or_default()
+    };
+
     HashMap::from([
         (
             "file_name".to_string(),
-            file.file_name().into_string().unwrap(),
-        ),
-        (
-            "file_created".to_string(),
-            metadata
-                .created()
-                .unwrap()
-                .duration_since(UNIX_EPOCH)
+            file.path
+                .file_name()
                 .unwrap()
-                .as_millis()
+                .to_string_lossy()
                 .to_string(),
         ),
+        ("file_path".to_string(), file.path.display().to_string()),
+        ("file_created".to_string(), millis(file.metadata.created())),
+        ("file_modified".to_string(), millis(file.metadata.modified())),
+        ("file_size".to_string(), file.metadata.len().to_string()),
     ])
 }
@@ -2,3 +2,7 @@ pub mod log_message;
 pub mod update_properties;
 pub mod generate_item;
 pub mod get_file;
+pub mod route_on_property;
+pub mod routing;
+pub mod route_on_content;
+pub mod put_file;
@@ -3,19 +3,22 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use async_trait::async_trait;
-use log::info;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use cascade_api::component::{NamedComponent, Process};
 use cascade_api::component::environment::ExecutionEnvironment;
 use cascade_api::component::error::ComponentError;
+use cascade_api::expression::{Expression, ExpressionError};
 use cascade_api::message::Message;
 
 #[derive(Serialize, Deserialize)]
 pub struct LogMessageConfig {
     // Only log every x results
     pub log_every_x: usize,
+    // Logged instead of the whole message if given, an expression over the message
+    #[serde(default)]
+    pub message: Option<Expression>,
 }
 
 pub struct LogMessage {
@@ -35,13 +38,13 @@ impl NamedComponent for LogMessage {
 
 #[async_trait]
 impl Process for LogMessage {
-    fn create_from_json(config: Value) -> Arc<dyn Process> {
-        let config: LogMessageConfig = serde_json::from_value(config).unwrap();
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        let config: LogMessageConfig = serde_json::from_value(config)?;
 
-        Arc::new(LogMessage {
+        Ok(Arc::new(LogMessage {
             config,
             item_count: Default::default(),
-        })
+        }))
     }
 
     async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
@@ -51,7 +54,7 @@ impl Process for LogMessage {
         let count: usize = self.item_count.fetch_add(1, Ordering::SeqCst);
 
         // Only log every x results
-        if count % self.config.log_every_x == 0 {
+        if count.is_multiple_of(self.config.log_every_x) {
             let now = SystemTime::now()
                 .duration_since(UNIX_EPOCH)
                 .unwrap()
@@ -59,10 +62,29 @@ impl Process for LogMessage {
 
             let elapsed_millis: f64 = (now - item.created_nanos) as f64 / 1_000_000.0;
 
-            info!(
-                "Item number {} took {:.2}ms, contents {:?}",
-                count, elapsed_millis, item
-            );
+            let evaluated: Option<Result<String, ExpressionError>> = self
+                .config
+                .message
+                .as_ref()
+                .map(|message| message.evaluate_text(&item));
+
+            match evaluated {
+                Some(Ok(message)) => execution.logger.info(format_args!(
+                    "Item number {} took {:.2}ms, {}",
+                    count, elapsed_millis, message
+                )),
+                // The whole message is logged if the expression can't be evaluated
+                evaluated => {
+                    if let Some(Err(err)) = evaluated {
+                        execution.logger.warn(format_args!("Message couldn't be logged: {}", err));
+                    }
+
+                    execution.logger.info(format_args!(
+                        "Item number {} took {:.2}ms, contents {:?}",
+                        count, elapsed_millis, item
+                    ))
+                }
+            }
         }
 
         execution.send_default(item).await
@@ -0,0 +1,282 @@
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use cascade_api::component::{NamedComponent, Process};
+use cascade_api::component::environment::ExecutionEnvironment;
+use cascade_api::component::error::ComponentError;
+use cascade_api::expression::Expression;
+use cascade_api::message::content::Content;
+use cascade_api::message::Message;
+
+pub const SUCCESS_OUTPUT: &str = "success";
+pub const FAILURE_OUTPUT: &str = "failure";
+
+// Set on messages sent to the success output
+const FILE_PATH_PROPERTY: &str = "file_path";
+// Set on messages sent to the failure output
+const FAILURE_REASON_PROPERTY: &str = "failure_reason";
+
+// Named after the property GetFile sets
+const DEFAULT_FILE_NAME: &str = "${file_name}";
+
+/// What to do when a file with the same name is already in the directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ConflictStrategy {
+    Replace,
+    // Leave the existing file and treat the message as written
+    Ignore,
+    #[default]
+    Fail,
+    // Write as name_1.ext, name_2.ext and so on until a name is free
+    UniqueSuffix,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PutFileConfig {
+    // Directory to write files into, an expression over the message
+    pub directory: Expression,
+    // Name to write each message as, an expression over the message
+    #[serde(default = "default_file_name")]
+    pub file_name: Expression,
+    #[serde(default)]
+    pub conflict_strategy: ConflictStrategy,
+    // Create the directory and any parents if it doesn't exist
+    #[serde(default)]
+    pub create_directory: bool,
+    // Octal mode for written files such as 644, only applied on unix
+    #[serde(default)]
+    pub permissions: Option<String>,
+}
+
+fn default_file_name() -> Expression {
+    Expression::parse(DEFAULT_FILE_NAME).unwrap()
+}
+
+pub struct PutFile {
+    config: PutFileConfig,
+    mode: Option<u32>,
+    // Keeps temporary names apart when copies of a message are written at once
+    temporary_count: AtomicU64,
+}
+
+impl NamedComponent for PutFile {
+    fn type_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        "PutFile"
+    }
+}
+
+#[async_trait]
+impl Process for PutFile {
+    /// This will fail if either:
+    ///     The directory or file name isn't a valid expression
+    ///     The permissions aren't an octal mode
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        let config: PutFileConfig = serde_json::from_value(config)?;
+
+        let mode: Option<u32> = config
+            .permissions
+            .as_ref()
+            .map(|permissions| {
+                u32::from_str_radix(permissions, 8).map_err(|_| {
+                    ComponentError::InvalidConfig(format!(
+                        "Permissions {} are not an octal mode",
+                        permissions
+                    ))
+                })
+            })
+            .transpose()?;
+
+        Ok(Arc::new(PutFile {
+            config,
+            mode,
+            temporary_count: Default::default(),
+        }))
+    }
+
+    async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
+        let mut item: Message = execution.recv().await?.clone();
+
+        match self.put(&item) {
+            Ok(Some(path)) => {
+                item.properties
+                    .insert(FILE_PATH_PROPERTY.to_string(), path.display().to_string());
+
+                execution.send(SUCCESS_OUTPUT, item).await
+            }
+            Ok(None) => execution.send(SUCCESS_OUTPUT, item).await,
+            Err(reason) => {
+                execution
+                    .logger
+                    .warn(format_args!("Failed to write file: {}", reason));
+
+                item.properties
+                    .insert(FAILURE_REASON_PROPERTY.to_string(), reason);
+
+                execution.send(FAILURE_OUTPUT, item).await
+            }
+        }
+    }
+
+    fn required_outputs(&self) -> Vec<&str> {
+        vec![FAILURE_OUTPUT]
+    }
+
+    // The file has been written, so there's nothing lost if the message goes no further
+    fn optional_outputs(&self) -> Vec<&str> {
+        vec![SUCCESS_OUTPUT]
+    }
+}
+
+impl PutFile {
+    /// Write the content of a message, returning where it was written or None if it was ignored
+    /// This will fail if either:
+    ///     The directory or file name can't be evaluated or is empty
+    ///     The file name isn't a plain file name
+    ///     The message has no content in memory
+    ///     A file with the same name exists and the conflict strategy is to fail
+    ///     The file couldn't be written
+    fn put(&self, item: &Message) -> Result<Option<PathBuf>, String> {
+        let file_name: String = evaluate(&self.config.file_name, item)?;
+
+        // Names such as ../name would write outside of the directory
+        if Path::new(&file_name).file_name() != Some(file_name.as_ref()) {
+            return Err(format!("{} is not a valid file name", file_name));
+        }
+
+        let Some(Content::Memory { buffer }) = item.default_content() else {
+            return Err("Message has no content in memory to write".to_string());
+        };
+
+        let directory: PathBuf = PathBuf::from(evaluate(&self.config.directory, item)?);
+
+        if self.config.create_directory {
+            fs::create_dir_all(&directory).map_err(|err| err.to_string())?;
+        }
+
+        let target: PathBuf = directory.join(&file_name);
+
+        if target.exists() {
+            match self.config.conflict_strategy {
+                ConflictStrategy::Ignore => return Ok(None),
+                ConflictStrategy::Fail => {
+                    return Err(format!("{} already exists", target.display()))
+                }
+                ConflictStrategy::Replace | ConflictStrategy::UniqueSuffix => {}
+            }
+        }
+
+        // Written to a hidden temporary file first so nothing reads it half written
+        // Named without the file name, which may already be as long as names can be
+        let count: u64 = self.temporary_count.fetch_add(1, Ordering::Relaxed);
+        let temporary: PathBuf = directory.join(format!(".{}.{}.tmp", item.id, count));
+
+        let result: Result<Option<PathBuf>, String> = self
+            .write_temporary(&temporary, buffer)
+            .and_then(|_| self.move_into_place(&temporary, target));
+
+        // Removed whether or not it was moved into place
+        let _ = fs::remove_file(&temporary);
+
+        result
+    }
+
+    // Synced before it's moved into place, so a crash can't leave an empty or partial file
+    fn write_temporary(&self, temporary: &Path, buffer: &[u8]) -> Result<(), String> {
+        let mut file: File = File::create(temporary).map_err(|err| err.to_string())?;
+        file.write_all(buffer).map_err(|err| err.to_string())?;
+
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+
+            file.set_permissions(fs::Permissions::from_mode(mode))
+                .map_err(|err| err.to_string())?;
+        }
+
+        file.sync_all().map_err(|err| err.to_string())
+    }
+
+    // A file created since the check isn't lost, as only Replace overwrites
+    fn move_into_place(&self, temporary: &Path, target: PathBuf) -> Result<Option<PathBuf>, String> {
+        if self.config.conflict_strategy == ConflictStrategy::Replace {
+            fs::rename(temporary, &target).map_err(|err| err.to_string())?;
+
+            return Ok(Some(target));
+        }
+
+        let mut suffix: usize = 0;
+
+        loop {
+            let candidate: PathBuf = match suffix {
+                0 => target.clone(),
+                _ => suffixed(&target, suffix),
+            };
+
+            match move_without_replacing(temporary, &candidate) {
+                Ok(()) => return Ok(Some(candidate)),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    match self.config.conflict_strategy {
+                        ConflictStrategy::Ignore => return Ok(None),
+                        ConflictStrategy::UniqueSuffix => suffix += 1,
+                        _ => return Err(format!("{} already exists", candidate.display())),
+                    }
+                }
+                Err(err) => return Err(err.to_string()),
+            }
+        }
+    }
+}
+
+// Linking never replaces a file, but not every file system supports it
+// Otherwise the name is claimed with an empty file, which renaming then replaces
+fn move_without_replacing(temporary: &Path, target: &Path) -> std::io::Result<()> {
+    match fs::hard_link(temporary, target) {
+        Err(err) if err.kind() != ErrorKind::AlreadyExists => {
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(target)?;
+
+            fs::rename(temporary, target)
+        }
+        result => result,
+    }
+}
+
+// Missing properties evaluate to nothing, which is never a usable path
+fn evaluate(expression: &Expression, item: &Message) -> Result<String, String> {
+    let value: String = expression
+        .evaluate_text(item)
+        .map_err(|err| err.to_string())?;
+
+    match value.is_empty() {
+        true => Err(format!("{} evaluated to nothing", expression.source())),
+        false => Ok(value),
+    }
+}
+
+// name.ext becomes name_1.ext
+fn suffixed(path: &Path, suffix: usize) -> PathBuf {
+    let stem: String = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let file_name: String = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, suffix, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, suffix),
+    };
+
+    path.with_file_name(file_name)
+}
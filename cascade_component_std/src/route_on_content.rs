@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use encoding_rs::Encoding;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+use cascade_api::component::{NamedComponent, Process};
+use cascade_api::component::environment::ExecutionEnvironment;
+use cascade_api::component::error::ComponentError;
+use cascade_api::message::content::Content;
+use cascade_api::message::Message;
+
+use crate::routing::{check_outputs, RoutingStrategy, send_to_matched, UNMATCHED_OUTPUT};
+
+const DEFAULT_CHARACTER_SET: &str = "UTF-8";
+const DEFAULT_MAX_BUFFER_BYTES: usize = 1024 * 1024;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ContentMatch {
+    // Text content with a match for the pattern anywhere within it
+    Regex { pattern: String },
+    // JSON content where the path selects anything, or a value equal to the given one
+    JsonPath {
+        path: String,
+        #[serde(default)]
+        equals: Option<Value>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContentRoute {
+    // Name of the connections to send matching messages to
+    pub output: String,
+    #[serde(flatten)]
+    pub matches: ContentMatch,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RouteOnContentConfig {
+    // Checked in order, which matters for the first match
+    // Every output must be connected, messages matching no route are dropped unless unmatched is
+    pub routes: Vec<ContentRoute>,
+    #[serde(default)]
+    pub strategy: RoutingStrategy,
+    // Encoding the content is decoded from before matching
+    #[serde(default = "default_character_set")]
+    pub character_set: String,
+    // Only the start of larger content is matched against, and JSON paths don't match it at all
+    #[serde(default = "default_max_buffer_bytes")]
+    pub max_buffer_bytes: usize,
+}
+
+fn default_character_set() -> String {
+    DEFAULT_CHARACTER_SET.to_string()
+}
+
+fn default_max_buffer_bytes() -> usize {
+    DEFAULT_MAX_BUFFER_BYTES
+}
+
+// Route matchers compiled when the component is created
+enum Matcher {
+    Regex(Regex),
+    JsonPath(JsonPath, Option<Value>),
+}
+
+pub struct RouteOnContent {
+    config: RouteOnContentConfig,
+    matchers: Vec<Matcher>,
+    encoding: &'static Encoding,
+}
+
+impl NamedComponent for RouteOnContent {
+    fn type_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        "RouteOnContent"
+    }
+}
+
+#[async_trait]
+impl Process for RouteOnContent {
+    /// This will fail if either:
+    ///     A pattern or JSON path is invalid
+    ///     The character set isn't known
+    ///     An output is used by more than one route or is the unmatched output
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        let config: RouteOnContentConfig = serde_json::from_value(config)?;
+
+        check_outputs(config.routes.iter().map(|route| route.output.as_str()))?;
+
+        let encoding: &'static Encoding = Encoding::for_label(config.character_set.as_bytes())
+            .ok_or(ComponentError::InvalidConfig(format!(
+                "Unknown character set {}",
+                config.character_set
+            )))?;
+
+        let matchers: Vec<Matcher> = config
+            .routes
+            .iter()
+            .map(|route| match &route.matches {
+                ContentMatch::Regex { pattern } => Regex::new(pattern)
+                    .map(Matcher::Regex)
+                    .map_err(|err| ComponentError::InvalidConfig(err.to_string())),
+                ContentMatch::JsonPath { path, equals } => JsonPath::parse(path)
+                    .map(|path| Matcher::JsonPath(path, equals.clone()))
+                    .map_err(|err| ComponentError::InvalidConfig(err.to_string())),
+            })
+            .collect::<Result<Vec<Matcher>, ComponentError>>()?;
+
+        Ok(Arc::new(RouteOnContent {
+            config,
+            matchers,
+            encoding,
+        }))
+    }
+
+    async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
+        let item: Message = execution.recv().await?.clone();
+
+        // Content held elsewhere can't be matched against
+        let Some((text, truncated)) = self.read_text(&item) else {
+            execution
+                .logger
+                .warn("Message has no content in memory to match against");
+
+            return send_to_matched(execution, &[], item).await;
+        };
+
+        let has_json_path: bool = self
+            .matchers
+            .iter()
+            .any(|matcher| matches!(matcher, Matcher::JsonPath(..)));
+
+        // Only part of the document was read, so it can't be parsed
+        if has_json_path && truncated {
+            execution.logger.warn(format_args!(
+                "Content is larger than {} bytes so JSON paths weren't matched",
+                self.config.max_buffer_bytes
+            ));
+        }
+
+        // Content which isn't JSON matches no JSON path
+        let json: Option<Value> = (has_json_path && !truncated)
+            .then(|| serde_json::from_str(&text).ok())
+            .flatten();
+
+        let mut matched: Vec<&str> = vec![];
+
+        for (route, matcher) in self.config.routes.iter().zip(&self.matchers) {
+            let is_match: bool = match (matcher, &json) {
+                (Matcher::Regex(pattern), _) => pattern.is_match(&text),
+                (Matcher::JsonPath(path, equals), Some(json)) => {
+                    let mut selected = path.query(json).into_iter();
+
+                    match equals {
+                        Some(expected) => selected.any(|value| value == expected),
+                        None => selected.next().is_some(),
+                    }
+                }
+                (Matcher::JsonPath(..), None) => false,
+            };
+
+            if !is_match {
+                continue;
+            }
+
+            matched.push(&route.output);
+
+            if self.config.strategy == RoutingStrategy::FirstMatch {
+                break;
+            }
+        }
+
+        send_to_matched(execution, &matched, item).await
+    }
+
+    fn required_outputs(&self) -> Vec<&str> {
+        self.config
+            .routes
+            .iter()
+            .map(|route| route.output.as_str())
+            .collect()
+    }
+
+    // Messages matching no route are only kept if something is connected to take them
+    fn optional_outputs(&self) -> Vec<&str> {
+        vec![UNMATCHED_OUTPUT]
+    }
+}
+
+impl RouteOnContent {
+    // Decode up to the buffer limit of the default content, and whether any was left out
+    fn read_text(&self, item: &Message) -> Option<(String, bool)> {
+        let Some(Content::Memory { buffer }) = item.default_content() else {
+            return None;
+        };
+
+        let truncated: bool = buffer.len() > self.config.max_buffer_bytes;
+        let buffered: &[u8] = &buffer[..buffer.len().min(self.config.max_buffer_bytes)];
+        let (text, _, _) = self.encoding.decode(buffered);
+
+        Some((text.into_owned(), truncated))
+    }
+}
@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use cascade_api::component::{NamedComponent, Process};
+use cascade_api::component::environment::ExecutionEnvironment;
+use cascade_api::component::error::ComponentError;
+use cascade_api::expression::Expression;
+use cascade_api::message::Message;
+
+use crate::routing::{check_outputs, RoutingStrategy, send_to_matched, UNMATCHED_OUTPUT};
+
+#[derive(Serialize, Deserialize)]
+pub struct Route {
+    // Name of the connections to send matching messages to
+    pub output: String,
+    // Expression over the message which is true for messages to route here
+    pub condition: Expression,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RouteOnProperty {
+    // Checked in order, which matters for the first match
+    // Every output must be connected, messages matching no route are dropped unless unmatched is
+    pub routes: Vec<Route>,
+    #[serde(default)]
+    pub strategy: RoutingStrategy,
+}
+
+impl NamedComponent for RouteOnProperty {
+    fn type_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        "RouteOnProperty"
+    }
+}
+
+#[async_trait]
+impl Process for RouteOnProperty {
+    /// This will fail if either:
+    ///     A condition isn't a valid expression
+    ///     An output is used by more than one route or is the unmatched output
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        let route_on_property: RouteOnProperty = serde_json::from_value(config)?;
+
+        check_outputs(route_on_property.routes.iter().map(|route| route.output.as_str()))?;
+
+        Ok(Arc::new(route_on_property))
+    }
+
+    async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
+        let item: Message = execution.recv().await?.clone();
+
+        let mut matched: Vec<&str> = vec![];
+
+        for route in &self.routes {
+            // A condition which can't be evaluated doesn't match, so the message isn't lost
+            let is_match: bool = match route.condition.evaluate(&item) {
+                Ok(value) => value.is_true(),
+                Err(err) => {
+                    execution
+                        .logger
+                        .warn(format_args!("Route to {} was skipped: {}", route.output, err));
+
+                    false
+                }
+            };
+
+            if !is_match {
+                continue;
+            }
+
+            matched.push(&route.output);
+
+            if self.strategy == RoutingStrategy::FirstMatch {
+                break;
+            }
+        }
+
+        send_to_matched(execution, &matched, item).await
+    }
+
+    fn required_outputs(&self) -> Vec<&str> {
+        self.routes.iter().map(|route| route.output.as_str()).collect()
+    }
+
+    // Messages matching no route are only kept if something is connected to take them
+    fn optional_outputs(&self) -> Vec<&str> {
+        vec![UNMATCHED_OUTPUT]
+    }
+}
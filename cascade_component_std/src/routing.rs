@@ -0,0 +1,59 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use cascade_api::component::environment::ExecutionEnvironment;
+use cascade_api::component::error::ComponentError;
+use cascade_api::message::Message;
+
+// Output for messages which match no route
+pub const UNMATCHED_OUTPUT: &str = "unmatched";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RoutingStrategy {
+    // Send to the first route which matches
+    #[default]
+    FirstMatch,
+    // Send a copy to every route which matches
+    AllMatches,
+}
+
+/// Check routes don't share an output or use the unmatched output
+pub(crate) fn check_outputs<'a>(outputs: impl Iterator<Item = &'a str>) -> Result<(), ComponentError> {
+    let mut seen: HashSet<&str> = HashSet::new();
+
+    for output in outputs {
+        if output == UNMATCHED_OUTPUT {
+            return Err(ComponentError::InvalidConfig(format!(
+                "Output {} is reserved for messages matching no route",
+                UNMATCHED_OUTPUT
+            )));
+        }
+
+        if !seen.insert(output) {
+            return Err(ComponentError::InvalidConfig(format!(
+                "Output {} is used more than once",
+                output
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Send a copy of the message to each matched output, or to the unmatched output if there are none
+pub(crate) async fn send_to_matched(
+    execution: &mut ExecutionEnvironment,
+    matched: &[&str],
+    item: Message,
+) -> Result<(), ComponentError> {
+    let Some((last, rest)) = matched.split_last() else {
+        return execution.send(UNMATCHED_OUTPUT, item).await;
+    };
+
+    for output in rest {
+        execution.send(output, item.clone()).await?;
+    }
+
+    execution.send(last, item).await
+}
@@ -8,12 +8,13 @@ use serde_json::Value;
 use cascade_api::component::{NamedComponent, Process};
 use cascade_api::component::environment::ExecutionEnvironment;
 use cascade_api::component::error::ComponentError;
+use cascade_api::expression::{Expression, ExpressionError};
 use cascade_api::message::Message;
 
 #[derive(Serialize, Deserialize)]
 pub struct UpdateProperties {
-    // Key value pairs to update on item properties
-    pub updates: HashMap<String, String>,
+    // Properties to set, with values which can be expressions over the incoming message
+    pub updates: HashMap<String, Expression>,
 }
 
 impl NamedComponent for UpdateProperties {
@@ -27,18 +28,28 @@ impl NamedComponent for UpdateProperties {
 
 #[async_trait]
 impl Process for UpdateProperties {
-    fn create_from_json(config: Value) -> Arc<dyn Process> {
-        let update_properties: UpdateProperties = serde_json::from_value(config).unwrap();
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        let update_properties: UpdateProperties = serde_json::from_value(config)?;
 
-        Arc::new(update_properties)
+        Ok(Arc::new(update_properties))
     }
 
     async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
         let mut item: Message = execution.recv().await?.clone();
 
-        // Loop through updates and update FlowItem
-        for (key, value) in self.updates.clone() {
-            item.properties.insert(key, value);
+        // Every value is evaluated against the message as it arrived
+        let updates: Result<HashMap<String, String>, ExpressionError> = self
+            .updates
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), value.evaluate_text(&item)?)))
+            .collect();
+
+        // Passed on unchanged rather than lost if any value can't be evaluated
+        match updates {
+            Ok(updates) => item.properties.extend(updates),
+            Err(err) => execution
+                .logger
+                .warn(format_args!("Properties were left unchanged: {}", err)),
         }
 
         execution.send_default(item).await
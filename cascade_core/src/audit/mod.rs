@@ -0,0 +1,167 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const DEFAULT_MAX_RECORDS: usize = 10_000;
+pub const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// A change made, or attempted, through the control plane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp_millis: u128,
+    // Name of whoever made the request
+    pub principal: String,
+    pub operation: String,
+    // Ids of the components or connections affected
+    pub targets: Vec<String>,
+    // Definitions before and after the change, where there are any
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    // Set if the operation was rejected or failed
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(principal: &str, operation: &str, targets: Vec<String>) -> AuditRecord {
+        AuditRecord {
+            timestamp_millis: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis(),
+            principal: principal.to_string(),
+            operation: operation.to_string(),
+            targets,
+            before: None,
+            after: None,
+            error: None,
+        }
+    }
+}
+
+/// Restricts which records are listed, every field is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditQuery {
+    pub principal: Option<String>,
+    pub operation: Option<String>,
+    pub target: Option<String>,
+    pub since_millis: Option<u128>,
+    // Only the most recent matching records are returned
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &AuditRecord) -> bool {
+        self.principal
+            .as_ref()
+            .is_none_or(|principal| &record.principal == principal)
+            && self
+                .operation
+                .as_ref()
+                .is_none_or(|operation| &record.operation == operation)
+            && self
+                .target
+                .as_ref()
+                .is_none_or(|target| record.targets.contains(target))
+            && self
+                .since_millis
+                .is_none_or(|since| record.timestamp_millis >= since)
+    }
+}
+
+/// Bounded in-memory store of audit records, oldest first
+/// Records are also appended to a journal file if opened with a directory
+/// The default log has no journal, so servers should always open one to keep their records
+/// The journal is never rewritten, so it keeps every record even once evicted from memory
+pub struct AuditLog {
+    max_records: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+    journal: Option<Mutex<File>>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog::new(DEFAULT_MAX_RECORDS)
+    }
+}
+
+impl AuditLog {
+    pub fn new(max_records: usize) -> AuditLog {
+        AuditLog {
+            max_records,
+            records: Default::default(),
+            journal: None,
+        }
+    }
+
+    /// Open the journal within a directory, restoring the most recent records from it
+    pub fn open(max_records: usize, directory: &Path) -> std::io::Result<AuditLog> {
+        let path: PathBuf = directory.join(AUDIT_FILE_NAME);
+        let mut records: VecDeque<AuditRecord> = VecDeque::new();
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                // Skip any partially written lines
+                let Ok(record) = serde_json::from_str::<AuditRecord>(&line?) else {
+                    continue;
+                };
+
+                if records.len() >= max_records {
+                    records.pop_front();
+                }
+
+                records.push_back(record);
+            }
+        }
+
+        let journal: File = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(AuditLog {
+            max_records,
+            records: Mutex::new(records),
+            journal: Some(Mutex::new(journal)),
+        })
+    }
+
+    pub fn record(&self, record: AuditRecord) {
+        if let Some(journal) = &self.journal {
+            let line: String = serde_json::to_string(&record).unwrap();
+            let mut journal = journal.lock().unwrap();
+
+            // Changes are rare enough to sync each one to disk
+            if let Err(err) = writeln!(journal, "{}", line).and_then(|_| journal.sync_data()) {
+                warn!("Failed to write audit record to journal: {}", err);
+            }
+        }
+
+        let mut records = self.records.lock().unwrap();
+
+        if records.len() >= self.max_records {
+            records.pop_front();
+        }
+
+        records.push_back(record);
+    }
+
+    // Records matching the query, oldest first
+    pub fn list(&self, query: &AuditQuery) -> Vec<AuditRecord> {
+        let records = self.records.lock().unwrap();
+
+        let mut matching: Vec<AuditRecord> = records
+            .iter()
+            .rev()
+            .filter(|record| query.matches(record))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        matching.reverse();
+
+        matching
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use cascade_api::component::component::ComponentMetadata;
+use cascade_api::component::logger::LogListener;
+use log::Level;
+
+use crate::event::{CascadeEvent, EventBus};
+
+pub const DEFAULT_MAX_BULLETINS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("Warning"),
+            Severity::Error => f.write_str("Error"),
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "warn" | "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("Unknown severity {}", value)),
+        }
+    }
+}
+
+/// Warning or error raised by a component
+#[derive(Debug, Clone, Serialize)]
+pub struct Bulletin {
+    pub id: u64,
+    pub component_id: String,
+    pub display_name: String,
+    pub severity: Severity,
+    pub message: String,
+    pub timestamp_millis: u128,
+}
+
+/// Bounded in-memory store of component bulletins
+/// The oldest bulletins are evicted once max_bulletins is reached
+pub struct BulletinBoard {
+    max_bulletins: usize,
+    next_id: AtomicU64,
+    bulletins: Mutex<VecDeque<Bulletin>>,
+
+    // Bulletins are also pushed to event subscribers
+    events: EventBus,
+}
+
+impl BulletinBoard {
+    pub fn new(max_bulletins: usize, events: EventBus) -> BulletinBoard {
+        BulletinBoard {
+            max_bulletins,
+            next_id: Default::default(),
+            bulletins: Default::default(),
+            events,
+        }
+    }
+
+    pub fn record(&self, metadata: &ComponentMetadata, severity: Severity, message: String) {
+        let bulletin: Bulletin = Bulletin {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            component_id: metadata.id.clone(),
+            display_name: metadata.display_name.clone(),
+            severity,
+            message,
+            timestamp_millis: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis(),
+        };
+
+        {
+            let mut bulletins = self.bulletins.lock().unwrap();
+
+            if bulletins.len() >= self.max_bulletins {
+                bulletins.pop_front();
+            }
+
+            bulletins.push_back(bulletin.clone());
+        }
+
+        self.events.publish(CascadeEvent::Bulletin(bulletin));
+    }
+
+    // Bulletins at or above a severity, optionally for a single component
+    pub fn list(
+        &self,
+        component_id: Option<&str>,
+        min_severity: Option<Severity>,
+    ) -> Vec<Bulletin> {
+        self.bulletins
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|bulletin| component_id.is_none_or(|id| bulletin.component_id == id))
+            .filter(|bulletin| min_severity.is_none_or(|severity| bulletin.severity >= severity))
+            .cloned()
+            .collect()
+    }
+
+    // Most recent bulletins for a component, oldest first
+    pub fn list_recent(&self, component_id: &str, limit: usize) -> Vec<Bulletin> {
+        let mut recent: Vec<Bulletin> = self
+            .bulletins
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|bulletin| bulletin.component_id == component_id)
+            .take(limit)
+            .cloned()
+            .collect();
+        recent.reverse();
+
+        recent
+    }
+}
+
+// Warnings and errors logged by components become bulletins
+impl LogListener for BulletinBoard {
+    fn on_log(&self, metadata: &ComponentMetadata, level: Level, message: &str) {
+        let severity: Severity = match level {
+            Level::Error => Severity::Error,
+            Level::Warn => Severity::Warning,
+            _ => return,
+        };
+
+        self.record(metadata, severity, message.to_string());
+    }
+}
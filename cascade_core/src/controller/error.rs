@@ -1,9 +1,20 @@
 use std::fmt::{Display, Formatter};
 
+use cascade_api::connection::error::ConnectionError;
+
 #[derive(Debug)]
 pub enum StartComponentError {
     InvalidNodeIndex(usize),
+    AlreadyRunning(usize),
     MissingComponent(String),
+    // The component type rejected the config
+    InvalidConfig(String),
+    // Parameters referenced by the config couldn't be resolved
+    UnresolvedParameters(String),
+    // Connections sharing a name have different distributions
+    ConflictingDistribution(String),
+    // The component needs the output to be connected
+    MissingOutput(String),
 }
 
 impl Display for StartComponentError {
@@ -12,10 +23,60 @@ impl Display for StartComponentError {
             StartComponentError::InvalidNodeIndex(idx) => {
                 f.write_fmt(format_args!("No node in graph at index {}", idx))
             }
+            StartComponentError::AlreadyRunning(idx) => {
+                f.write_fmt(format_args!("Component at index {} is already running", idx))
+            }
             StartComponentError::MissingComponent(type_name) => f.write_fmt(format_args!(
                 "Component {} not known to instance",
                 type_name
             )),
+            StartComponentError::InvalidConfig(err) => f.write_str(err),
+            StartComponentError::UnresolvedParameters(err) => f.write_str(err),
+            StartComponentError::ConflictingDistribution(name) => f.write_fmt(format_args!(
+                "Connections named {} have conflicting distributions",
+                name
+            )),
+            StartComponentError::MissingOutput(name) => f.write_fmt(format_args!(
+                "Output {} must be connected for the component to start",
+                name
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadFlowError {
+    MissingComponent(String),
+    // A component type rejected the config it was given
+    InvalidConfig(String),
+    // Connection refers to a component outside of the flow
+    InvalidComponentIndex(usize),
+    // More than one component or connection in the flow has the id
+    DuplicateId(String),
+    // Ids may only contain letters, digits, underscores and dashes
+    InvalidId(String),
+    InvalidConnection(ConnectionError),
+}
+
+impl Display for LoadFlowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadFlowError::MissingComponent(type_name) => f.write_fmt(format_args!(
+                "Component {} not known to instance",
+                type_name
+            )),
+            LoadFlowError::InvalidConfig(err) => f.write_str(err),
+            LoadFlowError::InvalidComponentIndex(idx) => {
+                f.write_fmt(format_args!("No component in flow at index {}", idx))
+            }
+            LoadFlowError::DuplicateId(id) => {
+                f.write_fmt(format_args!("Id {} is used more than once in flow", id))
+            }
+            LoadFlowError::InvalidId(id) => f.write_fmt(format_args!(
+                "Id {} may only contain letters, digits, underscores and dashes",
+                id
+            )),
+            LoadFlowError::InvalidConnection(err) => err.fmt(f),
         }
     }
 }
@@ -39,17 +100,239 @@ impl Display for StopComponentError {
     }
 }
 
+#[derive(Debug)]
+pub enum RemoveComponentError {
+    InvalidNodeIndex(usize),
+    ComponentRunning(usize),
+}
+
+impl Display for RemoveComponentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RemoveComponentError::InvalidNodeIndex(idx) => {
+                f.write_fmt(format_args!("No node in graph at index {}", idx))
+            }
+            RemoveComponentError::ComponentRunning(idx) => {
+                f.write_fmt(format_args!("Component at index {} is still running", idx))
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum RemoveConnectionError {
+    InvalidEdgeIndex(usize),
+    // Indices of the running components at either end
     ConnectionRunning(Vec<usize>),
 }
 
 impl Display for RemoveConnectionError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            RemoveConnectionError::InvalidEdgeIndex(idx) => {
+                f.write_fmt(format_args!("No connection in graph at index {}", idx))
+            }
             RemoveConnectionError::ConnectionRunning(indices) => {
                 f.write_fmt(format_args!("Connection is still in use {:?}", indices))
             }
         }
     }
 }
+
+#[derive(Debug)]
+pub enum PurgeConnectionError {
+    InvalidEdgeIndex(usize),
+}
+
+impl Display for PurgeConnectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PurgeConnectionError::InvalidEdgeIndex(idx) => {
+                f.write_fmt(format_args!("No connection in graph at index {}", idx))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LogLevelError {
+    InvalidNodeIndex(usize),
+}
+
+impl Display for LogLevelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LogLevelError::InvalidNodeIndex(idx) => {
+                f.write_fmt(format_args!("No node in graph at index {}", idx))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UpdateComponentError {
+    InvalidNodeIndex(usize),
+    MissingComponent(String),
+    // The component type rejected the new config
+    InvalidConfig(String),
+    // Running components are only updated if asked to restart them
+    ComponentRunning(usize),
+    FailedToStop,
+    // The update was applied but the component couldn't be started again
+    FailedToRestart(String),
+}
+
+impl Display for UpdateComponentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateComponentError::InvalidNodeIndex(idx) => {
+                f.write_fmt(format_args!("No node in graph at index {}", idx))
+            }
+            UpdateComponentError::MissingComponent(type_name) => f.write_fmt(format_args!(
+                "Component {} not known to instance",
+                type_name
+            )),
+            UpdateComponentError::InvalidConfig(err) => f.write_str(err),
+            UpdateComponentError::ComponentRunning(idx) => f.write_fmt(format_args!(
+                "Component at index {} is running, stop it or ask for a restart",
+                idx
+            )),
+            UpdateComponentError::FailedToStop => f.write_str("Component failed to stop"),
+            UpdateComponentError::FailedToRestart(err) => f.write_fmt(format_args!(
+                "Component was updated but failed to restart: {}",
+                err
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum UpdateConnectionError {
+    InvalidEdgeIndex(usize),
+    // The connection can't be redirected to a component which doesn't exist
+    InvalidTarget(usize),
+    InvalidConnection(ConnectionError),
+    // Every connection in an output must distribute the same way
+    ConflictingDistribution(String),
+    // Components using the connection are only updated if asked to restart them
+    ComponentsRunning(Vec<usize>),
+    FailedToStop,
+    // The update was applied but the components couldn't be started again
+    FailedToRestart(String),
+}
+
+impl Display for UpdateConnectionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateConnectionError::InvalidEdgeIndex(idx) => {
+                f.write_fmt(format_args!("No connection in graph at index {}", idx))
+            }
+            UpdateConnectionError::InvalidTarget(idx) => {
+                f.write_fmt(format_args!("No node in graph at index {}", idx))
+            }
+            UpdateConnectionError::InvalidConnection(err) => err.fmt(f),
+            UpdateConnectionError::ConflictingDistribution(name) => f.write_fmt(format_args!(
+                "Connections named {} have conflicting distributions",
+                name
+            )),
+            UpdateConnectionError::ComponentsRunning(indices) => f.write_fmt(format_args!(
+                "Components at {:?} are running, stop them or ask for a restart",
+                indices
+            )),
+            UpdateConnectionError::FailedToStop => f.write_str("Component failed to stop"),
+            UpdateConnectionError::FailedToRestart(err) => f.write_fmt(format_args!(
+                "Connection was updated but failed to restart components: {}",
+                err
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReloadFlowError {
+    InvalidFlow(LoadFlowError),
+    FailedToStop,
+    // The flow was applied but affected components couldn't be started again
+    FailedToRestart(String),
+}
+
+impl From<LoadFlowError> for ReloadFlowError {
+    fn from(value: LoadFlowError) -> Self {
+        ReloadFlowError::InvalidFlow(value)
+    }
+}
+
+impl Display for ReloadFlowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReloadFlowError::InvalidFlow(err) => err.fmt(f),
+            ReloadFlowError::FailedToStop => f.write_str("Component failed to stop"),
+            ReloadFlowError::FailedToRestart(err) => f.write_fmt(format_args!(
+                "Flow was reloaded but failed to restart components: {}",
+                err
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParameterError {
+    MissingContext(String),
+    // Context and name of a parameter which is referenced but not set
+    MissingParameter(String, String),
+}
+
+impl Display for ParameterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterError::MissingContext(name) => {
+                f.write_fmt(format_args!("No parameter context named {}", name))
+            }
+            ParameterError::MissingParameter(context, name) => f.write_fmt(format_args!(
+                "Parameter {} is not set in context {}",
+                name, context
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParameterContextError {
+    MissingContext(String),
+    // Contexts can't be removed while components refer to them
+    ContextInUse(Vec<String>),
+    // A running component couldn't be restarted with the new context
+    InvalidComponent(String, String),
+    // A sensitive parameter was given masked but there's no value to keep
+    MaskedValue(String),
+    FailedToStop,
+    // The context was changed but components couldn't be started again
+    FailedToRestart(String),
+}
+
+impl Display for ParameterContextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterContextError::MissingContext(name) => {
+                f.write_fmt(format_args!("No parameter context named {}", name))
+            }
+            ParameterContextError::ContextInUse(ids) => f.write_fmt(format_args!(
+                "Parameter context is still used by components {:?}",
+                ids
+            )),
+            ParameterContextError::InvalidComponent(id, err) => f.write_fmt(format_args!(
+                "Component {} can't use the parameter context: {}",
+                id, err
+            )),
+            ParameterContextError::MaskedValue(name) => f.write_fmt(format_args!(
+                "Sensitive parameter {} is masked but has no value to keep",
+                name
+            )),
+            ParameterContextError::FailedToStop => f.write_str("Component failed to stop"),
+            ParameterContextError::FailedToRestart(err) => f.write_fmt(format_args!(
+                "Parameter context was changed but failed to restart components: {}",
+                err
+            )),
+        }
+    }
+}
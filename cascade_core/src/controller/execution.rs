@@ -1,36 +1,60 @@
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-use log::error;
+use log::warn;
+use tokio::sync::watch;
 use tokio::task::{JoinError, JoinSet};
-use tokio::time::{interval, Interval};
+use tokio::time::error::Elapsed;
+use tokio::time::{interval, timeout, Interval};
 use tokio::time::MissedTickBehavior::Delay;
 
 use cascade_api::component::component::{Component, ComponentMetadata, Schedule};
 use cascade_api::component::environment::ExecutionEnvironment;
 use cascade_api::component::error::ComponentError;
+use cascade_api::component::logger::ComponentLogger;
+use cascade_api::component::state::ComponentState;
 use cascade_api::component::Process;
 use cascade_api::connection::ComponentChannels;
+use cascade_api::message::trace::Span;
 use cascade_api::message::InternalMessage;
 
+use crate::trace::SpanExporter;
+
+// How long stopping waits for current invocations to finish before they are aborted
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct ComponentExecution {
     // Active task for this execution
     tasks: JoinSet<()>,
 
     pub component: Arc<Component>,
 
-    stopped: Arc<AtomicBool>,
+    // Set once the execution is asked to stop, waking any task waiting for its next tick
+    stopped: watch::Sender<bool>,
     channels: ComponentChannels,
+
+    logger: ComponentLogger,
+    span_exporter: Option<Arc<dyn SpanExporter>>,
+
+    // Given to every environment of the execution
+    pub state: ComponentState,
 }
 
 impl ComponentExecution {
-    pub fn new(component: Component, channels: ComponentChannels) -> ComponentExecution {
+    pub fn new(
+        component: Component,
+        channels: ComponentChannels,
+        logger: ComponentLogger,
+        span_exporter: Option<Arc<dyn SpanExporter>>,
+    ) -> ComponentExecution {
         ComponentExecution {
             tasks: JoinSet::new(),
             component: Arc::new(component),
-            stopped: Default::default(),
+            stopped: watch::channel(false).0,
             channels,
+            logger,
+            span_exporter,
+            state: Default::default(),
         }
     }
 
@@ -41,8 +65,13 @@ impl ComponentExecution {
             // Allow the component to manage it's own scheduling
             Schedule::Unbounded { concurrency } => {
                 for _ in 0..concurrency {
-                    let environment: ExecutionEnvironment =
-                        ExecutionEnvironment::new(metadata.clone(), self.channels.clone());
+                    let mut environment: ExecutionEnvironment = ExecutionEnvironment::new(
+                        metadata.clone(),
+                        self.channels.clone(),
+                        self.logger.clone(),
+                    );
+                    environment.state = self.state.clone();
+                    self.ignore_optional_outputs(&mut environment);
 
                     self.schedule_component(environment, None);
                 }
@@ -53,16 +82,30 @@ impl ComponentExecution {
                 // Don't try and catch up with missed ticks
                 interval.set_missed_tick_behavior(Delay);
 
-                let environment: ExecutionEnvironment =
-                    ExecutionEnvironment::new(metadata.clone(), self.channels.clone());
+                let mut environment: ExecutionEnvironment = ExecutionEnvironment::new(
+                    metadata.clone(),
+                    self.channels.clone(),
+                    self.logger.clone(),
+                );
+                environment.state = self.state.clone();
+                self.ignore_optional_outputs(&mut environment);
 
                 self.schedule_component(environment, Some(interval));
             }
         };
     }
 
+    fn ignore_optional_outputs(&self, environment: &mut ExecutionEnvironment) {
+        for output in self.component.implementation.optional_outputs() {
+            environment.ignore_output(output);
+        }
+    }
+
+    /// Ask every task to stop and wait for any current invocation to finish
+    /// Tasks still running after the timeout are left to be aborted by kill
+    /// This will fail if a task panicked
     pub async fn stop(&mut self) -> Result<(), JoinError> {
-        self.stopped.store(true, Ordering::Relaxed);
+        self.stopped.send_replace(true);
 
         for _ in 0..self.tasks.len() {
             // Send a cancellation message to the thread
@@ -73,9 +116,32 @@ impl ComponentExecution {
                 .unwrap();
         }
 
+        let tasks: &mut JoinSet<()> = &mut self.tasks;
+        let joined: Result<Result<(), JoinError>, Elapsed> = timeout(STOP_TIMEOUT, async {
+            while let Some(result) = tasks.join_next().await {
+                result?;
+            }
+
+            Ok(())
+        })
+        .await;
 
+        match joined {
+            Ok(result) => result,
+            Err(_) => {
+                warn!(
+                    "{} didn't stop within {:?} and will be aborted",
+                    self.component.metadata, STOP_TIMEOUT
+                );
 
-        Ok(())
+                Ok(())
+            }
+        }
+    }
+
+    // Whether the execution has been asked to stop
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.borrow()
     }
 
     pub async fn kill(&mut self) {
@@ -88,25 +154,42 @@ impl ComponentExecution {
         mut interval: Option<Interval>,
     ) {
         let implementation: Arc<dyn Process> = self.component.implementation.clone();
-        let stopped: Arc<AtomicBool> = self.stopped.clone();
+        let mut stopped: watch::Receiver<bool> = self.stopped.subscribe();
+        let span_exporter: Option<Arc<dyn SpanExporter>> = self.span_exporter.clone();
 
         self.tasks.spawn(async move {
             loop {
                 if let Some(interval) = interval.as_mut() {
-                    interval.tick().await;
+                    // Stopping doesn't wait for the next tick
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = stopped.wait_for(|stopped| *stopped) => break,
+                    }
                 }
 
-                if stopped.load(Ordering::Relaxed) {
+                if *stopped.borrow() {
                     break;
                 }
 
-                if let Err(err) = implementation.process(&mut environment).await {
+                environment.begin_invocation();
+                let result: Result<(), ComponentError> =
+                    implementation.process(&mut environment).await;
+
+                let spans: Vec<Span> = environment.end_invocation(&result);
+                if let Some(exporter) = span_exporter.as_ref().filter(|_| !spans.is_empty()) {
+                    exporter.export(spans);
+                }
+
+                if let Err(err) = result {
                     match err {
                         ComponentError::ComponentShutdown => {
                             // Break loop and join task
                             break;
                         }
-                        err => error!("Encountered error with {:?}", err),
+                        // Raised as a bulletin through the logger
+                        err => environment
+                            .logger
+                            .error(format_args!("Encountered error with {:?}", err)),
                     }
                 }
             }
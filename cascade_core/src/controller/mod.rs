@@ -1,28 +1,67 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use async_channel::{Receiver, Sender, unbounded};
-use log::info;
+use log::{info, LevelFilter};
 use petgraph::Direction;
 use petgraph::graph::{EdgeIndex, NodeIndex};
-use tokio::sync::{RwLock, RwLockWriteGuard};
+use petgraph::visit::EdgeRef;
+use serde_json::Value;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use cascade_api::component::component::{Component, ComponentMetadata, Schedule};
-use cascade_api::component::definition::ComponentDefinition;
+use cascade_api::component::definition::{ComponentDefinition, ComponentUpdate, FieldChange};
+use cascade_api::component::logger::{ComponentLogLevel, ComponentLogger};
+use cascade_api::component::state::ComponentState;
 use cascade_api::connection::{ComponentChannels, Connection};
-use cascade_api::connection::definition::ConnectionDefinition;
-use cascade_api::message::InternalMessage;
+use cascade_api::connection::definition::{ConnectionDefinition, ConnectionUpdate};
+use cascade_api::connection::distribution::NamedOutput;
+use cascade_api::connection::filter::MessageFilter;
+use cascade_api::connection::queue::MessageQueue;
+use cascade_api::message::{InternalMessage, Message};
 
-use crate::controller::error::{RemoveConnectionError, StartComponentError, StopComponentError};
+use crate::audit::AuditLog;
+use crate::bulletin::{BulletinBoard, DEFAULT_MAX_BULLETINS};
+use crate::controller::error::{
+    LoadFlowError, LogLevelError, ParameterContextError, ParameterError, PurgeConnectionError,
+    ReloadFlowError, RemoveComponentError, RemoveConnectionError, StartComponentError,
+    StopComponentError, UpdateComponentError, UpdateConnectionError,
+};
 use crate::controller::execution::ComponentExecution;
+use crate::event::{CascadeEvent, ComponentStatus, EventBus, QueueStats};
 use crate::graph::CascadeGraph;
-use crate::registry::ComponentRegistry;
+use crate::graph::flow::{FlowDefinition, FlowDiff};
+use crate::parameter::{references, resolve, ParameterContext};
+use crate::provenance::{ProvenanceEvent, ProvenanceRepository};
+use crate::registry::{ComponentRegistry, RegistryError};
+use crate::trace::SpanExporter;
 
 pub mod error;
 mod execution;
 
 pub type ConnectionsMap = HashMap<EdgeIndex, Connection>;
 
+/// Outcome of updating a component, with the definitions either side of the update
+pub struct ComponentUpdateResult {
+    pub before: ComponentDefinition,
+    pub after: ComponentDefinition,
+    pub changes: Vec<FieldChange>,
+    // Whether the component was stopped and started again to apply the update
+    pub restarted: bool,
+}
+
+/// Outcome of updating a connection, with the definitions either side of the update
+pub struct ConnectionUpdateResult {
+    // Redirecting a connection moves it to a new index
+    pub edge_idx: EdgeIndex,
+    pub before: ConnectionDefinition,
+    pub after: ConnectionDefinition,
+    pub changes: Vec<FieldChange>,
+    // Indices of the components which were stopped and started again to apply the update
+    pub restarted: Vec<usize>,
+}
+
 pub struct CascadeController {
     pub component_registry: ComponentRegistry,
 
@@ -31,10 +70,36 @@ pub struct CascadeController {
     pub executions: HashMap<NodeIndex, ComponentExecution>,
 
     pub connections: Arc<RwLock<ConnectionsMap>>,
+
+    pub provenance: Arc<ProvenanceRepository>,
+
+    // Changes made through the control plane
+    pub audit: Arc<AuditLog>,
+
+    pub events: EventBus,
+
+    pub bulletins: Arc<BulletinBoard>,
+
+    // Log level of each component by definition id, kept across restarts
+    pub log_levels: HashMap<String, Arc<ComponentLogLevel>>,
+
+    // Level components start at until one is set for them, by component type
+    pub default_log_levels: HashMap<String, LevelFilter>,
+
+    // Values referenced from component configs, by context name
+    pub parameter_contexts: BTreeMap<String, ParameterContext>,
+
+    // Spans are discarded if no exporter is configured
+    pub span_exporter: Option<Arc<dyn SpanExporter>>,
+
+    // Where component state is persisted across restarts
+    pub state_directory: Option<PathBuf>,
 }
 
 impl CascadeController {
     pub fn new(component_registry: ComponentRegistry) -> CascadeController {
+        let events: EventBus = Default::default();
+
         CascadeController {
             graph_definition: Arc::new(RwLock::new(CascadeGraph {
                 graph_internal: Default::default(),
@@ -43,43 +108,553 @@ impl CascadeController {
 
             connections: Default::default(),
             executions: Default::default(),
+            provenance: Default::default(),
+            audit: Default::default(),
+            bulletins: Arc::new(BulletinBoard::new(DEFAULT_MAX_BULLETINS, events.clone())),
+            events,
+            log_levels: Default::default(),
+            default_log_levels: Default::default(),
+            parameter_contexts: Default::default(),
+            span_exporter: None,
+            state_directory: None,
+        }
+    }
+
+    /// Add every component and connection in a flow to the graph
+    /// Nothing is added if any component type is unknown or a connection is invalid
+    pub async fn load_flow(&mut self, mut flow: FlowDefinition) -> Result<(), LoadFlowError> {
+        for def in flow.components.iter_mut() {
+            self.component_registry
+                .protect(&def.type_name, &mut def.config, None)
+                .map_err(|err| LoadFlowError::InvalidConfig(err.to_string()))?;
+        }
+
+        self.validate_flow(&flow)?;
+
+        let connection_count: usize = flow.connections.len();
+        let mut graph: RwLockWriteGuard<CascadeGraph> = self.graph_definition.write().await;
+
+        let node_indices: Vec<NodeIndex> = flow
+            .components
+            .into_iter()
+            .map(|def| {
+                let node_idx: NodeIndex = graph.graph_internal.add_node(def.clone());
+
+                self.events.publish(CascadeEvent::ComponentCreated {
+                    idx: node_idx.index(),
+                    definition: def,
+                });
+
+                node_idx
+            })
+            .collect();
+
+        for mut def in flow.connections {
+            let from: NodeIndex = node_indices[def.source];
+            let to: NodeIndex = node_indices[def.target];
+
+            // Point the definition at the nodes in the graph rather than the flow
+            def.source = from.index();
+            def.target = to.index();
+
+            let edge_idx: EdgeIndex = graph.graph_internal.add_edge(from, to, def.clone());
+
+            self.events.publish(CascadeEvent::ConnectionCreated {
+                idx: edge_idx.index(),
+                definition: def,
+            });
+        }
+
+        info!(
+            "Loaded flow with {} components and {} connections",
+            node_indices.len(),
+            connection_count
+        );
+
+        Ok(())
+    }
+
+    /// Copy of a definition with parameters in its config replaced by their values
+    pub fn resolve_definition(
+        &self,
+        def: &ComponentDefinition,
+    ) -> Result<ComponentDefinition, ParameterError> {
+        if references(&def.config).is_empty() {
+            return Ok(def.clone());
+        }
+
+        // Configs can't reference parameters without a context to find them in
+        let name: &str = def.parameter_context.as_deref().unwrap_or_default();
+        let context: &ParameterContext = self
+            .parameter_contexts
+            .get(name)
+            .ok_or(ParameterError::MissingContext(name.to_string()))?;
+
+        let mut resolved: ComponentDefinition = def.clone();
+        resolved.config = resolve(&def.config, context)
+            .map_err(|parameter| ParameterError::MissingParameter(name.to_string(), parameter))?;
+
+        Ok(resolved)
+    }
+
+    /// Check a definition could be used to create its component
+    /// Parameters may be set after the component is added, so until they are only the type is checked
+    pub fn validate_component(&self, def: &ComponentDefinition) -> Result<(), RegistryError> {
+        match self.resolve_definition(def) {
+            Ok(resolved) => self.component_registry.validate(&resolved),
+            Err(_) if self.component_registry.is_known_component(&def.type_name) => Ok(()),
+            Err(_) => Err(RegistryError::UnknownType(def.type_name.clone())),
+        }
+    }
+
+    /// Create or replace a parameter context
+    /// Sensitive values given back masked keep the value they already had
+    /// Running components referencing a parameter which changed are stopped and started again
+    /// Stopping lets any current invocation finish so no message in progress is lost
+    /// Returns the previous context if there was one along with the ids of restarted components
+    /// This will fail if either:
+    ///     A sensitive value is masked but had no value to keep
+    ///     A component to restart is missing a parameter or rejects its resolved config
+    ///     A component fails to stop or start again
+    pub async fn set_parameter_context(
+        &mut self,
+        name: &str,
+        mut context: ParameterContext,
+    ) -> Result<(Option<ParameterContext>, Vec<String>), ParameterContextError> {
+        context
+            .unmask(self.parameter_contexts.get(name))
+            .map_err(ParameterContextError::MaskedValue)?;
+
+        let changed: BTreeSet<String> = self
+            .parameter_contexts
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+            .changed_parameters(&context);
+
+        let running: Vec<(String, NodeIndex)> = {
+            let graph: RwLockReadGuard<CascadeGraph> = self.graph_definition.read().await;
+
+            graph
+                .graph_internal
+                .node_indices()
+                .filter(|node_idx| self.is_running(*node_idx))
+                .map(|node_idx| (&graph.graph_internal[node_idx], node_idx))
+                .filter(|(def, _)| def.parameter_context.as_deref() == Some(name))
+                .filter(|(def, _)| !references(&def.config).is_disjoint(&changed))
+                .map(|(def, node_idx)| (def.id.clone(), node_idx))
+                .collect()
+        };
+
+        // Checked before anything is stopped, so a context they can't use leaves them running
+        {
+            let graph: RwLockReadGuard<CascadeGraph> = self.graph_definition.read().await;
+
+            for (id, node_idx) in &running {
+                let mut resolved: ComponentDefinition = graph.graph_internal[*node_idx].clone();
+                resolved.config = resolve(&resolved.config, &context).map_err(|parameter| {
+                    ParameterContextError::InvalidComponent(
+                        id.clone(),
+                        ParameterError::MissingParameter(name.to_string(), parameter).to_string(),
+                    )
+                })?;
+
+                self.component_registry
+                    .validate(&resolved)
+                    .map_err(|err| {
+                        ParameterContextError::InvalidComponent(id.clone(), err.to_string())
+                    })?;
+            }
+        }
+
+        for (_, node_idx) in &running {
+            self.stop_component(*node_idx)
+                .await
+                .map_err(|_| ParameterContextError::FailedToStop)?;
+        }
+
+        let previous: Option<ParameterContext> =
+            self.parameter_contexts.insert(name.to_string(), context);
+
+        info!(
+            "Set parameter context {} with {} changed parameters",
+            name,
+            changed.len()
+        );
+
+        for (_, node_idx) in &running {
+            self.start_component(*node_idx)
+                .await
+                .map_err(|err| ParameterContextError::FailedToRestart(err.to_string()))?;
+        }
+
+        Ok((previous, running.into_iter().map(|(id, _)| id).collect()))
+    }
+
+    /// Remove a parameter context which no component refers to
+    pub async fn remove_parameter_context(
+        &mut self,
+        name: &str,
+    ) -> Result<ParameterContext, ParameterContextError> {
+        if !self.parameter_contexts.contains_key(name) {
+            return Err(ParameterContextError::MissingContext(name.to_string()));
+        }
+
+        let users: Vec<String> = self
+            .graph_definition
+            .read()
+            .await
+            .graph_internal
+            .node_weights()
+            .filter(|def| def.parameter_context.as_deref() == Some(name))
+            .map(|def| def.id.clone())
+            .collect();
+
+        if !users.is_empty() {
+            return Err(ParameterContextError::ContextInUse(users));
+        }
+
+        info!("Removed parameter context {}", name);
+
+        Ok(self.parameter_contexts.remove(name).unwrap())
+    }
+
+    // Check every component in a flow could be created and every connection is between them
+    fn validate_flow(&self, flow: &FlowDefinition) -> Result<(), LoadFlowError> {
+        for def in &flow.components {
+            self.validate_component(def)
+                .map_err(|err| match err {
+                    RegistryError::UnknownType(type_name) => {
+                        LoadFlowError::MissingComponent(type_name)
+                    }
+                    err => LoadFlowError::InvalidConfig(err.to_string()),
+                })?;
+        }
+
+        for def in &flow.connections {
+            def.validate().map_err(LoadFlowError::InvalidConnection)?;
+        }
+
+        if let Some(idx) = flow
+            .connections
+            .iter()
+            .flat_map(|def| [def.source, def.target])
+            .find(|idx| *idx >= flow.components.len())
+        {
+            return Err(LoadFlowError::InvalidComponentIndex(idx));
+        }
+
+        // Component ids name the files their state is kept in
+        if let Some(id) = flow
+            .components
+            .iter()
+            .map(|def| def.id.as_str())
+            .chain(flow.connections.iter().map(|def| def.id.as_str()))
+            .find(|id| !is_valid_id(id))
+        {
+            return Err(LoadFlowError::InvalidId(id.to_string()));
+        }
+
+        // Ids are how a reloaded flow is matched up with the graph
+        let mut ids: HashSet<&str> = HashSet::new();
+        if let Some(id) = flow
+            .components
+            .iter()
+            .map(|def| def.id.as_str())
+            .chain(flow.connections.iter().map(|def| def.id.as_str()))
+            .find(|id| !ids.insert(id))
+        {
+            return Err(LoadFlowError::DuplicateId(id.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// Replace the graph with a flow, changing only what differs between them
+    /// Components and connections are matched by id, so anything without one is added
+    /// Running components affected by the difference are stopped and started again,
+    /// everything else keeps running with its queues intact
+    /// Nothing is changed if dry_run is set, but the difference is still returned
+    pub async fn reload_flow(
+        &mut self,
+        mut flow: FlowDefinition,
+        dry_run: bool,
+    ) -> Result<FlowDiff, ReloadFlowError> {
+        // Sensitive values which are masked or unchanged keep what the graph already holds
+        {
+            let graph: RwLockReadGuard<CascadeGraph> = self.graph_definition.read().await;
+
+            for def in flow.components.iter_mut() {
+                let existing: Option<&Value> = graph
+                    .find_node(&def.id)
+                    .map(|node_idx| &graph.graph_internal[node_idx])
+                    .filter(|existing| existing.type_name == def.type_name)
+                    .map(|existing| &existing.config);
+
+                self.component_registry
+                    .protect(&def.type_name, &mut def.config, existing)
+                    .map_err(|err| LoadFlowError::InvalidConfig(err.to_string()))?;
+            }
+        }
+
+        self.validate_flow(&flow)?;
+
+        let mut diff: FlowDiff = self.graph_definition.read().await.diff_flow(&flow);
+
+        let running: Vec<(String, NodeIndex)> = {
+            let graph: RwLockReadGuard<CascadeGraph> = self.graph_definition.read().await;
+
+            diff.affected
+                .iter()
+                .filter_map(|id| graph.find_node(id).map(|node_idx| (id.clone(), node_idx)))
+                .filter(|(_, node_idx)| self.is_running(*node_idx))
+                .collect()
+        };
+        diff.restarted = running.iter().map(|(id, _)| id.clone()).collect();
+
+        if dry_run || diff.is_empty() {
+            return Ok(diff);
+        }
+
+        for (_, node_idx) in &running {
+            self.stop_component(*node_idx)
+                .await
+                .map_err(|_| ReloadFlowError::FailedToStop)?;
+        }
+
+        let removed: Vec<NodeIndex> = {
+            let graph: RwLockReadGuard<CascadeGraph> = self.graph_definition.read().await;
+
+            diff.components
+                .removed
+                .iter()
+                .filter_map(|id| graph.find_node(id))
+                .collect()
+        };
+        for node_idx in removed {
+            // Stopping lets any current invocation finish before the execution is cleared out
+            if self.is_running(node_idx) {
+                self.stop_component(node_idx)
+                    .await
+                    .map_err(|_| ReloadFlowError::FailedToStop)?;
+            }
+
+            self.kill_component(node_idx).await;
         }
+
+        self.apply_flow_diff(flow, &diff).await;
+
+        info!(
+            "Reloaded flow adding {}, removing {} and changing {} components and connections",
+            diff.components.added.len() + diff.connections.added.len(),
+            diff.components.removed.len() + diff.connections.removed.len(),
+            diff.components.changed.len() + diff.connections.changed.len(),
+        );
+
+        for (id, _) in running {
+            // Indices move as components are removed
+            let node_idx: Option<NodeIndex> = self.graph_definition.read().await.find_node(&id);
+
+            if let Some(node_idx) = node_idx {
+                self.start_component(node_idx)
+                    .await
+                    .map_err(|err| ReloadFlowError::FailedToRestart(err.to_string()))?;
+            }
+        }
+
+        Ok(diff)
+    }
+
+    // Make the graph match the flow, with any components being removed already killed
+    async fn apply_flow_diff(&mut self, flow: FlowDefinition, diff: &FlowDiff) {
+        let mut graph: RwLockWriteGuard<CascadeGraph> = self.graph_definition.write().await;
+        let mut connections_lock: RwLockWriteGuard<ConnectionsMap> =
+            self.connections.write().await;
+
+        let added: HashSet<&String> = diff.components.added.iter().collect();
+        let changed: HashSet<&String> = diff.components.changed.iter().collect();
+
+        // Components are added first so connections can be redirected to them
+        for def in &flow.components {
+            if added.contains(&def.id) {
+                let node_idx: NodeIndex = graph.graph_internal.add_node(def.clone());
+
+                self.events.publish(CascadeEvent::ComponentCreated {
+                    idx: node_idx.index(),
+                    definition: def.clone(),
+                });
+            } else if changed.contains(&def.id) {
+                let node_idx: NodeIndex = graph.find_node(&def.id).unwrap();
+                graph.graph_internal[node_idx] = def.clone();
+
+                self.events.publish(CascadeEvent::ComponentUpdated {
+                    idx: node_idx.index(),
+                    definition: def.clone(),
+                });
+            }
+        }
+
+        // Point connections at the nodes in the graph rather than the flow
+        let node_indices: Vec<NodeIndex> = flow
+            .components
+            .iter()
+            .map(|def| graph.find_node(&def.id).unwrap())
+            .collect();
+        let to_graph = |mut def: ConnectionDefinition| {
+            def.source = node_indices[def.source].index();
+            def.target = node_indices[def.target].index();
+            def
+        };
+
+        let added: HashSet<&String> = diff.connections.added.iter().collect();
+        let changed: HashSet<&String> = diff.connections.changed.iter().collect();
+
+        for def in flow.connections.iter().filter(|def| changed.contains(&def.id)) {
+            let def: ConnectionDefinition = to_graph(def.clone());
+            let edge_idx: EdgeIndex = graph.find_edge(&def.id).unwrap();
+            let (source, target): (NodeIndex, NodeIndex) =
+                graph.graph_internal.edge_endpoints(edge_idx).unwrap();
+
+            let edge_idx: EdgeIndex = if source.index() != def.source || target.index() != def.target
+            {
+                redirect_edge(&mut graph, &mut connections_lock, edge_idx, def.clone())
+            } else {
+                graph.graph_internal[edge_idx] = def.clone();
+                edge_idx
+            };
+
+            if let Some(connection) = connections_lock.get_mut(&edge_idx) {
+                connection.reconfigure(&def);
+            }
+
+            self.events.publish(CascadeEvent::ConnectionUpdated {
+                idx: edge_idx.index(),
+                definition: def,
+            });
+        }
+
+        for id in &diff.connections.removed {
+            let edge_idx: EdgeIndex = graph.find_edge(id).unwrap();
+            remove_edge(&mut graph, &mut connections_lock, edge_idx);
+
+            self.events.publish(CascadeEvent::ConnectionRemoved {
+                idx: edge_idx.index(),
+            });
+        }
+
+        // Only removed connections were attached to removed components
+        for id in &diff.components.removed {
+            let node_idx: NodeIndex = graph.find_node(id).unwrap();
+            remove_node(&mut graph, &mut self.executions, node_idx);
+
+            self.events.publish(CascadeEvent::ComponentRemoved {
+                idx: node_idx.index(),
+            });
+        }
+
+        // Removing components moves others, so look up where they ended up
+        for def in flow.connections.into_iter().filter(|def| added.contains(&def.id)) {
+            let mut def: ConnectionDefinition = def;
+            let from: NodeIndex = graph.find_node(&flow.components[def.source].id).unwrap();
+            let to: NodeIndex = graph.find_node(&flow.components[def.target].id).unwrap();
+
+            def.source = from.index();
+            def.target = to.index();
+
+            let edge_idx: EdgeIndex = graph.graph_internal.add_edge(from, to, def.clone());
+
+            self.events.publish(CascadeEvent::ConnectionCreated {
+                idx: edge_idx.index(),
+                definition: def,
+            });
+        }
+
+        sync_endpoints(&mut graph);
     }
 
     pub async fn start_component(
         &mut self,
         node_idx: NodeIndex,
     ) -> Result<ComponentMetadata, StartComponentError> {
+        if self.is_running(node_idx) {
+            return Err(StartComponentError::AlreadyRunning(node_idx.index()));
+        }
+
+        // Clear out anything left over from stopping the previous execution
+        if let Some(mut previous) = self.executions.remove(&node_idx) {
+            previous.kill().await;
+        }
+
         let graph: RwLockWriteGuard<CascadeGraph> = self.graph_definition.write().await;
         let connections_lock: RwLockWriteGuard<ConnectionsMap> = self.connections.write().await;
 
         // Initialise any missing connections and return all relevant references
         let channels: ComponentChannels =
-            init_channels_for_node(&graph, connections_lock, node_idx);
+            init_channels_for_node(&graph, connections_lock, node_idx, &self.provenance)?;
 
         // Fail if the index isn't present in the graph
         let def: &ComponentDefinition = graph
             .get_component_for_node(node_idx)
             .ok_or(StartComponentError::InvalidNodeIndex(node_idx.index()))?;
 
-        // Fail if the component impl type isn't in the registry
-        let component: Component = self
-            .component_registry
-            .get_component(def)
-            .ok_or(StartComponentError::MissingComponent(def.type_name.clone()))?;
+        let resolved: ComponentDefinition = self
+            .resolve_definition(def)
+            .map_err(|err| StartComponentError::UnresolvedParameters(err.to_string()))?;
+
+        // Fail if the component impl type isn't in the registry or rejects the config
+        let component: Component =
+            self.component_registry
+                .get_component(&resolved)
+                .map_err(|err| match err {
+                    RegistryError::UnknownType(type_name) => {
+                        StartComponentError::MissingComponent(type_name)
+                    }
+                    err => StartComponentError::InvalidConfig(err.to_string()),
+                })?;
+
+        // Messages sent to an unconnected output would be lost
+        if let Some(output) = component
+            .implementation
+            .required_outputs()
+            .into_iter()
+            .find(|output| !channels.tx_named.contains_key(*output))
+        {
+            return Err(StartComponentError::MissingOutput(output.to_string()));
+        }
 
         let metadata: ComponentMetadata = component.metadata.clone();
         let schedule: Schedule = component.schedule.clone();
 
         info!("{} is starting with schedule {:?}", metadata, schedule);
 
-        let mut execution: ComponentExecution = ComponentExecution::new(component, channels);
+        let logger: ComponentLogger = ComponentLogger::new(
+            metadata.clone(),
+            log_level_of(&mut self.log_levels, &self.default_log_levels, def),
+            Some(self.bulletins.clone()),
+        );
+
+        let mut execution: ComponentExecution =
+            ComponentExecution::new(component, channels, logger, self.span_exporter.clone());
+        execution.state = ComponentState::new(self.state_directory.as_deref(), &metadata.id);
         execution.start();
         self.executions.insert(node_idx, execution);
 
+        self.events.publish(CascadeEvent::ComponentStatusChanged {
+            idx: node_idx.index(),
+            component_id: metadata.id.clone(),
+            status: ComponentStatus::Started,
+        });
+
         Ok(metadata)
     }
 
+    // Whether the component has an execution which hasn't been asked to stop
+    pub fn is_running(&self, node_idx: NodeIndex) -> bool {
+        self.executions
+            .get(&node_idx)
+            .is_some_and(|execution| !execution.is_stopped())
+    }
+
     pub async fn stop_component(
         &mut self,
         node_idx: NodeIndex,
@@ -88,6 +663,7 @@ impl CascadeController {
         let execution: &mut ComponentExecution = self
             .executions
             .get_mut(&node_idx)
+            .filter(|execution| !execution.is_stopped())
             // Error if there was no execution started
             .ok_or(StopComponentError::ComponentNotStarted(node_idx.index()))?;
 
@@ -96,7 +672,15 @@ impl CascadeController {
             .await
             .map_err(|_| StopComponentError::FailedToStop)?;
 
-        Ok(execution.component.metadata.clone())
+        let metadata: ComponentMetadata = execution.component.metadata.clone();
+
+        self.events.publish(CascadeEvent::ComponentStatusChanged {
+            idx: node_idx.index(),
+            component_id: metadata.id.clone(),
+            status: ComponentStatus::Stopped,
+        });
+
+        Ok(metadata)
     }
 
     pub async fn kill_component(&mut self, node_idx: NodeIndex) {
@@ -104,33 +688,535 @@ impl CascadeController {
         if let Some(mut execution) = self.executions.remove(&node_idx) {
             // Kill all associated threads
             execution.kill().await;
+
+            self.events.publish(CascadeEvent::ComponentStatusChanged {
+                idx: node_idx.index(),
+                component_id: execution.component.metadata.id.clone(),
+                status: ComponentStatus::Killed,
+            });
+        }
+    }
+
+    /// Change the name, schedule or config of a component without recreating its node
+    /// Running components are stopped, updated and started again if restart is set
+    /// Stopping lets any current invocation finish so no message in progress is lost
+    /// This will fail if either:
+    ///     The node doesn't exist
+    ///     The component type rejects the new config
+    ///     The component is running and restart isn't set
+    pub async fn update_component(
+        &mut self,
+        node_idx: NodeIndex,
+        mut update: ComponentUpdate,
+        restart: bool,
+    ) -> Result<ComponentUpdateResult, UpdateComponentError> {
+        let before: ComponentDefinition = self
+            .graph_definition
+            .read()
+            .await
+            .get_component_for_node(node_idx)
+            .ok_or(UpdateComponentError::InvalidNodeIndex(node_idx.index()))?
+            .clone();
+
+        // Sensitive values which are masked or unchanged keep what the component already has
+        if let Some(config) = update.config.as_mut() {
+            self.component_registry
+                .protect(&before.type_name, config, Some(&before.config))
+                .map_err(|err| UpdateComponentError::InvalidConfig(err.to_string()))?;
+        }
+
+        let mut after: ComponentDefinition = before.clone();
+        let mut changes: Vec<FieldChange> = after.apply_update(update);
+        self.component_registry
+            .mask_changes(&after.type_name, &mut changes);
+
+        // Nothing to do, so don't disturb the component
+        if changes.is_empty() {
+            return Ok(ComponentUpdateResult {
+                before,
+                after,
+                changes,
+                restarted: false,
+            });
+        }
+
+        self.validate_component(&after)
+            .map_err(|err| match err {
+                RegistryError::UnknownType(type_name) => {
+                    UpdateComponentError::MissingComponent(type_name)
+                }
+                err => UpdateComponentError::InvalidConfig(err.to_string()),
+            })?;
+
+        let running: bool = self.is_running(node_idx);
+
+        if running {
+            if !restart {
+                return Err(UpdateComponentError::ComponentRunning(node_idx.index()));
+            }
+
+            self.stop_component(node_idx)
+                .await
+                .map_err(|_| UpdateComponentError::FailedToStop)?;
         }
+
+        self.graph_definition.write().await.graph_internal[node_idx] = after.clone();
+
+        self.events.publish(CascadeEvent::ComponentUpdated {
+            idx: node_idx.index(),
+            definition: after.clone(),
+        });
+
+        info!("Updated {} with {} changes", after.id, changes.len());
+
+        if running {
+            self.start_component(node_idx)
+                .await
+                .map_err(|err| UpdateComponentError::FailedToRestart(err.to_string()))?;
+        }
+
+        Ok(ComponentUpdateResult {
+            before,
+            after,
+            changes,
+            restarted: running,
+        })
     }
 
+    /// Change the settings of a connection without losing the messages queued on it
+    /// Capacity, expiration and prioritizers apply immediately
+    /// Changing the name, distribution or target rewires the components using the connection,
+    /// which are stopped and started again around the update if restart is set
+    /// Stopping lets any current invocation finish so no message in progress is lost
+    /// This will fail if either:
+    ///     The connection doesn't exist
+    ///     The updated connection can't hold any items
+    ///     The new target doesn't exist
+    ///     Another connection in the output it joins distributes differently
+    ///     A component needing to be rewired is running and restart isn't set
+    pub async fn update_connection(
+        &mut self,
+        edge_idx: EdgeIndex,
+        update: ConnectionUpdate,
+        restart: bool,
+    ) -> Result<ConnectionUpdateResult, UpdateConnectionError> {
+        let before: ConnectionDefinition = self
+            .graph_definition
+            .read()
+            .await
+            .get_connection_for_edge(edge_idx)
+            .ok_or(UpdateConnectionError::InvalidEdgeIndex(edge_idx.index()))?
+            .clone();
+
+        let mut after: ConnectionDefinition = before.clone();
+        let changes: Vec<FieldChange> = after.apply_update(update);
+
+        // Nothing to do, so don't disturb the connection
+        if changes.is_empty() {
+            return Ok(ConnectionUpdateResult {
+                edge_idx,
+                before,
+                after,
+                changes,
+                restarted: vec![],
+            });
+        }
+
+        after
+            .validate()
+            .map_err(UpdateConnectionError::InvalidConnection)?;
+
+        let source: NodeIndex = NodeIndex::new(before.source);
+        let target: NodeIndex = NodeIndex::new(after.target);
+
+        if self
+            .graph_definition
+            .read()
+            .await
+            .get_component_for_node(target)
+            .is_none()
+        {
+            return Err(UpdateConnectionError::InvalidTarget(after.target));
+        }
+
+        // Outputs are grouped by name and distribution when the source starts
+        let mut rewired: Vec<NodeIndex> = vec![];
+        if before.name != after.name || before.distribution != after.distribution {
+            // Checked before anything is stopped, as the source couldn't be started again
+            let conflicting: bool = self
+                .graph_definition
+                .read()
+                .await
+                .graph_internal
+                .edges_directed(source, Direction::Outgoing)
+                .filter(|edge| edge.id() != edge_idx)
+                .any(|edge| {
+                    edge.weight().name == after.name
+                        && edge.weight().distribution != after.distribution
+                });
+
+            if conflicting {
+                return Err(UpdateConnectionError::ConflictingDistribution(after.name));
+            }
+
+            rewired.push(source);
+        }
+        // Inputs are taken when the target starts
+        if before.target != after.target {
+            rewired.extend([NodeIndex::new(before.target), target]);
+        }
+
+        let mut running: Vec<NodeIndex> = rewired
+            .into_iter()
+            .filter(|node_idx| self.is_running(*node_idx))
+            .collect();
+        running.sort();
+        running.dedup();
+
+        if !running.is_empty() {
+            if !restart {
+                return Err(UpdateConnectionError::ComponentsRunning(
+                    running.iter().map(|node_idx| node_idx.index()).collect(),
+                ));
+            }
+
+            for node_idx in &running {
+                self.stop_component(*node_idx)
+                    .await
+                    .map_err(|_| UpdateConnectionError::FailedToStop)?;
+            }
+        }
+
+        let edge_idx: EdgeIndex = {
+            let mut graph: RwLockWriteGuard<CascadeGraph> = self.graph_definition.write().await;
+            let mut connections_lock: RwLockWriteGuard<ConnectionsMap> =
+                self.connections.write().await;
+
+            let edge_idx: EdgeIndex = if before.target != after.target {
+                redirect_edge(&mut graph, &mut connections_lock, edge_idx, after.clone())
+            } else {
+                graph.graph_internal[edge_idx] = after.clone();
+                edge_idx
+            };
+
+            // Connections are created lazily so there may be nothing queued yet
+            if let Some(connection) = connections_lock.get_mut(&edge_idx) {
+                connection.reconfigure(&after);
+            }
+
+            edge_idx
+        };
+
+        self.events.publish(CascadeEvent::ConnectionUpdated {
+            idx: edge_idx.index(),
+            definition: after.clone(),
+        });
+
+        info!("Updated {} with {} changes", after.id, changes.len());
+
+        for node_idx in &running {
+            self.start_component(*node_idx)
+                .await
+                .map_err(|err| UpdateConnectionError::FailedToRestart(err.to_string()))?;
+        }
+
+        Ok(ConnectionUpdateResult {
+            edge_idx,
+            before,
+            after,
+            changes,
+            restarted: running.iter().map(|node_idx| node_idx.index()).collect(),
+        })
+    }
+
+    /// Change the log level of a component, taking effect immediately if it's running
+    /// Returns the component along with the level it was at before
+    pub async fn set_log_level(
+        &mut self,
+        node_idx: NodeIndex,
+        level: LevelFilter,
+    ) -> Result<(ComponentDefinition, LevelFilter), LogLevelError> {
+        let def: ComponentDefinition = self
+            .graph_definition
+            .read()
+            .await
+            .get_component_for_node(node_idx)
+            .ok_or(LogLevelError::InvalidNodeIndex(node_idx.index()))?
+            .clone();
+
+        let current: Arc<ComponentLogLevel> =
+            log_level_of(&mut self.log_levels, &self.default_log_levels, &def);
+        let previous: LevelFilter = current.get();
+        current.set(level);
+
+        Ok((def, previous))
+    }
+
+    // Current state of every initialised connection
+    pub async fn list_queue_stats(&self) -> Vec<QueueStats> {
+        let connections_lock: RwLockReadGuard<ConnectionsMap> = self.connections.read().await;
+
+        let mut stats: Vec<QueueStats> = connections_lock
+            .iter()
+            .map(|(idx, connection)| QueueStats {
+                idx: idx.index(),
+                stats: connection.stats(),
+            })
+            .collect();
+        stats.sort_by_key(|queue_stats| queue_stats.idx);
+
+        stats
+    }
+
+    /// Drop queued messages on a connection which match the filter
+    /// Returns the amount of messages which were dropped
+    pub async fn purge_connection(
+        &self,
+        edge_idx: EdgeIndex,
+        filter: &MessageFilter,
+    ) -> Result<usize, PurgeConnectionError> {
+        let graph: RwLockReadGuard<CascadeGraph> = self.graph_definition.read().await;
+
+        let def: &ConnectionDefinition = graph
+            .get_connection_for_edge(edge_idx)
+            .ok_or(PurgeConnectionError::InvalidEdgeIndex(edge_idx.index()))?;
+
+        let connections_lock: RwLockReadGuard<ConnectionsMap> = self.connections.read().await;
+
+        // Connections are created lazily so there may be nothing queued yet
+        let dropped: Vec<Message> = match connections_lock.get(&edge_idx) {
+            Some(connection) => connection.purge(filter),
+            None => vec![],
+        };
+
+        for message in &dropped {
+            self.provenance.record(ProvenanceEvent::drop(
+                message,
+                &def.id,
+                "Purged from connection",
+            ));
+        }
+
+        info!(
+            "Purged {} messages from connection {} at idx {}",
+            dropped.len(),
+            def.name,
+            edge_idx.index()
+        );
+
+        Ok(dropped.len())
+    }
+
+    /// Remove a component along with every connection to or from it
+    /// Messages still queued on the connections are dropped
+    /// Returns the ids of the connections which were removed
+    /// This will fail if either:
+    ///     The node doesn't exist
+    ///     The component is running
+    pub async fn remove_component(
+        &mut self,
+        node_idx: NodeIndex,
+    ) -> Result<Vec<String>, RemoveComponentError> {
+        if self.is_running(node_idx) {
+            return Err(RemoveComponentError::ComponentRunning(node_idx.index()));
+        }
+
+        // Clear out anything left over from stopping the component
+        self.kill_component(node_idx).await;
+
+        let mut graph: RwLockWriteGuard<CascadeGraph> = self.graph_definition.write().await;
+        let mut connections_lock: RwLockWriteGuard<ConnectionsMap> =
+            self.connections.write().await;
+
+        if graph.get_component_for_node(node_idx).is_none() {
+            return Err(RemoveComponentError::InvalidNodeIndex(node_idx.index()));
+        }
+
+        let mut removed: Vec<String> = vec![];
+
+        // Removing an edge moves another into its index, so look for the next one each time
+        while let Some(edge_idx) = graph
+            .graph_internal
+            .edges_directed(node_idx, Direction::Outgoing)
+            .chain(graph.graph_internal.edges_directed(node_idx, Direction::Incoming))
+            .map(|edge| edge.id())
+            .next()
+        {
+            removed.push(self.drop_edge(&mut graph, &mut connections_lock, edge_idx));
+        }
+
+        remove_node(&mut graph, &mut self.executions, node_idx);
+        sync_endpoints(&mut graph);
+
+        self.events.publish(CascadeEvent::ComponentRemoved {
+            idx: node_idx.index(),
+        });
+
+        Ok(removed)
+    }
+
+    /// Remove a connection, dropping any messages still queued on it
+    /// This will fail if either:
+    ///     The edge doesn't exist
+    ///     The component at either end of it is running
     pub async fn remove_connection(
         &mut self,
-        _edge_idx: EdgeIndex,
-    ) -> Result<ComponentMetadata, RemoveConnectionError> {
-        // Check if there are any active executions relating to the connection
-
-        // // Try and find a relevant execution
-        // let execution: ComponentExecution = self
-        //     .active_executions
-        //     .remove(&node_idx)
-        //     // Error if we there is no execution started
-        //     .ok_or(StopComponentError::ComponentNotStarted(node_idx.index()))?;
-        //
-        // // Indicate that the executions should stop
-        // execution.shutdown.store(true, Ordering::Relaxed);
-        // // Wait for it to stop
-        //
-        // // join!(&execution.handles).await;
-        // // .map_err(|_| StopComponentError::FailedToStop)?;
-        //
-        // let environment: Arc<ExecutionEnvironment> = execution.environment;
-        //
-        // Ok(environment.metadata.clone())
-        Err(RemoveConnectionError::ConnectionRunning(vec![]))
+        edge_idx: EdgeIndex,
+    ) -> Result<ConnectionDefinition, RemoveConnectionError> {
+        let mut graph: RwLockWriteGuard<CascadeGraph> = self.graph_definition.write().await;
+
+        let (source, target): (NodeIndex, NodeIndex) = graph
+            .graph_internal
+            .edge_endpoints(edge_idx)
+            .ok_or(RemoveConnectionError::InvalidEdgeIndex(edge_idx.index()))?;
+
+        let running: Vec<usize> = [source, target]
+            .into_iter()
+            .filter(|node_idx| self.is_running(*node_idx))
+            .map(|node_idx| node_idx.index())
+            .collect();
+
+        if !running.is_empty() {
+            return Err(RemoveConnectionError::ConnectionRunning(running));
+        }
+
+        let def: ConnectionDefinition = graph.graph_internal[edge_idx].clone();
+
+        let mut connections_lock: RwLockWriteGuard<ConnectionsMap> =
+            self.connections.write().await;
+        self.drop_edge(&mut graph, &mut connections_lock, edge_idx);
+
+        Ok(def)
+    }
+
+    // Remove an edge, recording anything still queued on it as dropped, and return its id
+    fn drop_edge(
+        &self,
+        graph: &mut CascadeGraph,
+        connections: &mut ConnectionsMap,
+        edge_idx: EdgeIndex,
+    ) -> String {
+        let id: String = graph.graph_internal[edge_idx].id.clone();
+
+        // Connections are created lazily so there may be nothing queued
+        let dropped: Vec<Message> = match connections.get(&edge_idx) {
+            Some(connection) => connection.purge(&MessageFilter::default()),
+            None => vec![],
+        };
+
+        for message in &dropped {
+            self.provenance.record(ProvenanceEvent::drop(
+                message,
+                &id,
+                "Connection was removed",
+            ));
+        }
+
+        remove_edge(graph, connections, edge_idx);
+
+        self.events.publish(CascadeEvent::ConnectionRemoved {
+            idx: edge_idx.index(),
+        });
+
+        id
+    }
+}
+
+// Ids are limited to the characters generated ids are made of
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// Level shared by every execution of a component, starting from the default for its type
+fn log_level_of(
+    log_levels: &mut HashMap<String, Arc<ComponentLogLevel>>,
+    default_log_levels: &HashMap<String, LevelFilter>,
+    def: &ComponentDefinition,
+) -> Arc<ComponentLogLevel> {
+    log_levels
+        .entry(def.id.clone())
+        .or_insert_with(|| match default_log_levels.get(&def.type_name) {
+            Some(level) => Arc::new(ComponentLogLevel::new(*level)),
+            None => Default::default(),
+        })
+        .clone()
+}
+
+// Move an edge to a new target and return its new index
+// Edges can't change endpoints, so it is removed and added again with the queue carried over
+fn redirect_edge(
+    graph: &mut CascadeGraph,
+    connections: &mut ConnectionsMap,
+    edge_idx: EdgeIndex,
+    def: ConnectionDefinition,
+) -> EdgeIndex {
+    // Removing an edge moves the last edge into its index
+    let last_idx: EdgeIndex = EdgeIndex::new(graph.graph_internal.edge_count() - 1);
+
+    let connection: Option<Connection> = connections.remove(&edge_idx);
+    let moved: Option<Connection> = connections.remove(&last_idx);
+
+    graph.graph_internal.remove_edge(edge_idx);
+    let new_idx: EdgeIndex = graph.graph_internal.add_edge(
+        NodeIndex::new(def.source),
+        NodeIndex::new(def.target),
+        def,
+    );
+
+    if let Some(moved) = moved.filter(|_| last_idx != edge_idx) {
+        connections.insert(edge_idx, moved);
+    }
+    if let Some(connection) = connection {
+        connections.insert(new_idx, connection);
+    }
+
+    new_idx
+}
+
+// Remove an edge, keeping the connections map in line with the edge moved into its index
+fn remove_edge(graph: &mut CascadeGraph, connections: &mut ConnectionsMap, edge_idx: EdgeIndex) {
+    let last_idx: EdgeIndex = EdgeIndex::new(graph.graph_internal.edge_count() - 1);
+
+    connections.remove(&edge_idx);
+    let moved: Option<Connection> = connections.remove(&last_idx);
+
+    graph.graph_internal.remove_edge(edge_idx);
+
+    if let Some(moved) = moved.filter(|_| last_idx != edge_idx) {
+        connections.insert(edge_idx, moved);
+    }
+}
+
+// Remove a node with no edges, keeping executions in line with the node moved into its index
+fn remove_node(
+    graph: &mut CascadeGraph,
+    executions: &mut HashMap<NodeIndex, ComponentExecution>,
+    node_idx: NodeIndex,
+) {
+    let last_idx: NodeIndex = NodeIndex::new(graph.graph_internal.node_count() - 1);
+
+    executions.remove(&node_idx);
+    let moved: Option<ComponentExecution> = executions.remove(&last_idx);
+
+    graph.graph_internal.remove_node(node_idx);
+
+    if let Some(moved) = moved.filter(|_| last_idx != node_idx) {
+        executions.insert(node_idx, moved);
+    }
+}
+
+// Connection definitions refer to nodes by index, so update them after nodes have moved
+fn sync_endpoints(graph: &mut CascadeGraph) {
+    for edge_idx in graph.graph_internal.edge_indices() {
+        let (source, target): (NodeIndex, NodeIndex) =
+            graph.graph_internal.edge_endpoints(edge_idx).unwrap();
+
+        let def: &mut ConnectionDefinition = &mut graph.graph_internal[edge_idx];
+        def.source = source.index();
+        def.target = target.index();
     }
 }
 
@@ -138,36 +1224,62 @@ fn init_channels_for_node(
     graph: &RwLockWriteGuard<CascadeGraph>,
     mut connections_lock: RwLockWriteGuard<ConnectionsMap>,
     node_idx: NodeIndex,
-) -> ComponentChannels {
+    // Told about messages which expire on the connections
+    provenance: &Arc<ProvenanceRepository>,
+) -> Result<ComponentChannels, StartComponentError> {
     // Receivers must be owned
-    let mut rx_channels: Vec<Receiver<InternalMessage>> = Default::default();
-    let mut tx_named: HashMap<String, Sender<InternalMessage>> = Default::default();
+    let mut rx_channels: Vec<Arc<MessageQueue>> = Default::default();
+    let mut tx_named: HashMap<String, NamedOutput> = Default::default();
+
+    let mut edges: Vec<(Direction, EdgeIndex)> = graph.get_edges_for_node(node_idx);
+    // Keep the order of connections within an output stable for partitioning
+    edges.sort_by_key(|(_, idx)| *idx);
 
-    for (direction, idx) in graph.get_edges_for_node(node_idx) {
-        let def: &ConnectionDefinition = graph.get_connection_for_edge(idx.clone()).unwrap();
+    for (direction, idx) in edges {
+        let def: &ConnectionDefinition = graph.get_connection_for_edge(idx).unwrap();
 
         // Insert the new connection into the map for sharing across components
         let connection: &mut Connection = connections_lock
-            .entry(idx.clone())
-            .or_insert_with(|| Connection::new(def));
+            .entry(idx)
+            .or_insert_with(|| {
+                let connection: Connection = Connection::new(def);
+                let provenance: Arc<ProvenanceRepository> = provenance.clone();
+                let id: String = def.id.clone();
+
+                connection.queue.set_expired_handler(Arc::new(move |message| {
+                    provenance.record(ProvenanceEvent::drop(message, &id, "Expired on connection"));
+                }));
+
+                connection
+            });
 
         match direction {
-            // Include entry in the map by name
+            // Group connections into outputs by name
             Direction::Outgoing => {
-                tx_named.insert(connection.name.clone(), connection.tx.clone());
+                let output: &mut NamedOutput = tx_named
+                    .entry(connection.name.clone())
+                    .or_insert_with(|| NamedOutput::new(def.distribution.clone()));
+
+                // Every connection in an output must agree on how to distribute
+                if output.distribution != def.distribution {
+                    return Err(StartComponentError::ConflictingDistribution(
+                        connection.name.clone(),
+                    ));
+                }
+
+                output.queues.push(connection.queue.clone());
             }
-            Direction::Incoming => rx_channels.push(connection.rx.clone()),
+            Direction::Incoming => rx_channels.push(connection.queue.clone()),
         };
     }
 
     // Create an extra channel to send signals to the components
     let (tx_signal, rx_signal): (Sender<InternalMessage>, Receiver<InternalMessage>) = unbounded();
 
-    rx_channels.push(rx_signal);
-
-    ComponentChannels {
+    Ok(ComponentChannels {
         rx: rx_channels,
+        rx_signal,
         tx_signal,
         tx_named,
-    }
+    })
 }
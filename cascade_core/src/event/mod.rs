@@ -0,0 +1,96 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::{Receiver, Sender};
+
+use cascade_api::component::definition::ComponentDefinition;
+use cascade_api::connection::definition::ConnectionDefinition;
+use cascade_api::connection::ConnectionStats;
+
+use crate::bulletin::Bulletin;
+
+// Events held for slow subscribers before they start missing them
+const EVENT_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub enum ComponentStatus {
+    Started,
+    Stopped,
+    Killed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStats {
+    pub idx: usize,
+    #[serde(flatten)]
+    pub stats: ConnectionStats,
+}
+
+/// Changes to the flow and its components which are pushed to subscribers
+#[derive(Clone, Serialize)]
+#[serde(tag = "type")]
+pub enum CascadeEvent {
+    ComponentCreated {
+        idx: usize,
+        definition: ComponentDefinition,
+    },
+    ComponentRemoved {
+        idx: usize,
+    },
+    ComponentUpdated {
+        idx: usize,
+        definition: ComponentDefinition,
+    },
+    ConnectionCreated {
+        idx: usize,
+        definition: ConnectionDefinition,
+    },
+    ConnectionRemoved {
+        idx: usize,
+    },
+    // The index changes if the connection was redirected to another component
+    ConnectionUpdated {
+        idx: usize,
+        definition: ConnectionDefinition,
+    },
+    ComponentStatusChanged {
+        idx: usize,
+        component_id: String,
+        status: ComponentStatus,
+    },
+    // Warning or error raised by a component
+    Bulletin(Bulletin),
+    QueueStats {
+        connections: Vec<QueueStats>,
+    },
+}
+
+/// Broadcasts events to every subscriber
+/// Publishing never blocks and events are discarded if nobody is listening
+#[derive(Clone)]
+pub struct EventBus {
+    tx: Sender<CascadeEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, _): (Sender<CascadeEvent>, Receiver<CascadeEvent>) =
+            broadcast::channel(EVENT_CAPACITY);
+
+        EventBus { tx }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: CascadeEvent) {
+        // Only errors when there are no subscribers
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> Receiver<CascadeEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn has_subscribers(&self) -> bool {
+        self.tx.receiver_count() > 0
+    }
+}
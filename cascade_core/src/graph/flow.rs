@@ -0,0 +1,239 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use cascade_api::component::definition::ComponentDefinition;
+use cascade_api::connection::definition::ConnectionDefinition;
+
+use crate::graph::CascadeGraph;
+
+/// Self contained description of a set of components and their connections
+/// Connection sources and targets are indices into the components of the flow
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FlowDefinition {
+    #[serde(default)]
+    pub components: Vec<ComponentDefinition>,
+    #[serde(default)]
+    pub connections: Vec<ConnectionDefinition>,
+}
+
+impl CascadeGraph {
+    /// Export every component and connection in the graph as a flow
+    pub fn export_flow(&self) -> FlowDefinition {
+        FlowDefinition {
+            // Node indices are contiguous so they line up with the flow indices
+            components: self
+                .graph_internal
+                .raw_nodes()
+                .iter()
+                .map(|node| node.weight.clone())
+                .collect(),
+            connections: self
+                .graph_internal
+                .raw_edges()
+                .iter()
+                .map(|edge| {
+                    let mut def: ConnectionDefinition = edge.weight.clone();
+                    def.source = edge.source().index();
+                    def.target = edge.target().index();
+
+                    def
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FlowDefinition {
+    /// Parse a flow keeping the ids given to its components and connections
+    /// Anything without an id is given a new one
+    pub fn with_ids(value: Value) -> Result<FlowDefinition, serde_json::Error> {
+        let mut flow: FlowDefinition = serde_json::from_value(value.clone())?;
+
+        for (def, id) in flow.components.iter_mut().zip(given_ids(&value["components"])) {
+            if let Some(id) = id {
+                def.id = id;
+            }
+        }
+        for (def, id) in flow.connections.iter_mut().zip(given_ids(&value["connections"])) {
+            if let Some(id) = id {
+                def.id = id;
+            }
+        }
+
+        Ok(flow)
+    }
+}
+
+// Ids are skipped when deserialising definitions so are read from the JSON directly
+fn given_ids(defs: &Value) -> Vec<Option<String>> {
+    defs.as_array()
+        .map(|defs| {
+            defs.iter()
+                .map(|def| def["id"].as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Ids of definitions added, removed and changed by a reload
+#[derive(Clone, Default, Serialize)]
+pub struct ChangeSet {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Difference between the graph and a flow replacing it, matched by id
+#[derive(Clone, Default, Serialize)]
+pub struct FlowDiff {
+    pub components: ChangeSet,
+    pub connections: ChangeSet,
+    // Ids of running components which are stopped and started again to apply the diff
+    pub restarted: Vec<String>,
+
+    // Ids of remaining components which need restarting to pick up the diff
+    #[serde(skip)]
+    pub affected: BTreeSet<String>,
+}
+
+impl FlowDiff {
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.connections.is_empty()
+    }
+}
+
+// Connection with its endpoints given as component ids, so graph and flow indices don't matter
+struct Endpoints<'a> {
+    def: &'a ConnectionDefinition,
+    source: &'a str,
+    target: &'a str,
+}
+
+impl Endpoints<'_> {
+    // Whether the components at either end need restarting to pick up the difference
+    fn rewires(&self, other: &Endpoints) -> bool {
+        self.source != other.source
+            || self.target != other.target
+            || self.def.name != other.def.name
+            || self.def.distribution != other.def.distribution
+    }
+
+    fn settings(&self) -> Value {
+        let mut settings: Value = to_value(self.def);
+        settings["source"] = Value::from(self.source);
+        settings["target"] = Value::from(self.target);
+
+        settings
+    }
+}
+
+// Definitions always serialise
+fn to_value<T: Serialize>(def: &T) -> Value {
+    serde_json::to_value(def).unwrap()
+}
+
+impl CascadeGraph {
+    /// Compare the graph to a flow which would replace it
+    /// Connection indices in the flow must already be known to be valid
+    pub fn diff_flow(&self, flow: &FlowDefinition) -> FlowDiff {
+        let mut diff: FlowDiff = Default::default();
+
+        let current: HashMap<&str, &ComponentDefinition> = self
+            .graph_internal
+            .node_weights()
+            .map(|def| (def.id.as_str(), def))
+            .collect();
+        let replacing: HashSet<&str> = flow.components.iter().map(|def| def.id.as_str()).collect();
+
+        for def in &flow.components {
+            match current.get(def.id.as_str()) {
+                None => diff.components.added.push(def.id.clone()),
+                Some(existing) if to_value(*existing) != to_value(def) => {
+                    diff.components.changed.push(def.id.clone());
+                    diff.affected.insert(def.id.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        diff.components.removed = self
+            .graph_internal
+            .node_weights()
+            .filter(|def| !replacing.contains(def.id.as_str()))
+            .map(|def| def.id.clone())
+            .collect();
+
+        let current: HashMap<&str, Endpoints> = self
+            .graph_internal
+            .raw_edges()
+            .iter()
+            .map(|edge| {
+                let endpoints: Endpoints = Endpoints {
+                    def: &edge.weight,
+                    source: &self.graph_internal[edge.source()].id,
+                    target: &self.graph_internal[edge.target()].id,
+                };
+
+                (edge.weight.id.as_str(), endpoints)
+            })
+            .collect();
+        let replacing: HashMap<&str, Endpoints> = flow
+            .connections
+            .iter()
+            .map(|def| {
+                let endpoints: Endpoints = Endpoints {
+                    def,
+                    source: &flow.components[def.source].id,
+                    target: &flow.components[def.target].id,
+                };
+
+                (def.id.as_str(), endpoints)
+            })
+            .collect();
+
+        for def in &flow.connections {
+            let new: &Endpoints = &replacing[def.id.as_str()];
+
+            match current.get(def.id.as_str()) {
+                None => {
+                    diff.connections.added.push(def.id.clone());
+                    diff.affected.extend([new.source.to_string(), new.target.to_string()]);
+                }
+                Some(existing) if existing.settings() != new.settings() => {
+                    diff.connections.changed.push(def.id.clone());
+
+                    // Capacity, expiration and prioritizers apply without a restart
+                    if existing.rewires(new) {
+                        diff.affected.extend(
+                            [existing.source, existing.target, new.source, new.target]
+                                .map(str::to_string),
+                        );
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+        for edge in self.graph_internal.raw_edges() {
+            if !replacing.contains_key(edge.weight.id.as_str()) {
+                let existing: &Endpoints = &current[edge.weight.id.as_str()];
+
+                diff.connections.removed.push(edge.weight.id.clone());
+                diff.affected.extend([existing.source.to_string(), existing.target.to_string()]);
+            }
+        }
+
+        // Removed components are killed rather than restarted
+        for id in &diff.components.removed {
+            diff.affected.remove(id);
+        }
+
+        diff
+    }
+}
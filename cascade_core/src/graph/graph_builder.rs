@@ -13,6 +13,12 @@ pub struct CascadeGraphBuilder {
     last_index: Option<NodeIndex>,
 }
 
+impl Default for CascadeGraphBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl CascadeGraphBuilder {
     pub fn new() -> CascadeGraphBuilder {
         CascadeGraphBuilder {
@@ -44,7 +50,7 @@ impl CascadeGraphBuilder {
             let destination: NodeIndex = self.graph_internal.add_node(def);
 
             self.graph_internal.add_edge(
-                source.clone(),
+                source,
                 destination,
                 ConnectionDefinition::new(source.index(), destination.index()),
             );
@@ -5,6 +5,7 @@ use petgraph::visit::EdgeRef;
 use cascade_api::component::definition::ComponentDefinition;
 use cascade_api::connection::definition::ConnectionDefinition;
 
+pub mod flow;
 pub mod graph_builder;
 
 pub type GraphInternal = Graph<ComponentDefinition, ConnectionDefinition>;
@@ -27,6 +28,18 @@ impl CascadeGraph {
         self.graph_internal.edge_weight(edge_idx)
     }
 
+    pub fn find_node(&self, id: &str) -> Option<NodeIndex> {
+        self.graph_internal
+            .node_indices()
+            .find(|idx| self.graph_internal[*idx].id == id)
+    }
+
+    pub fn find_edge(&self, id: &str) -> Option<EdgeIndex> {
+        self.graph_internal
+            .edge_indices()
+            .find(|idx| self.graph_internal[*idx].id == id)
+    }
+
     pub fn get_edges_for_node(&self, node_idx: NodeIndex) -> Vec<(Direction, EdgeIndex)> {
         self.graph_internal
             .edges_directed(node_idx, Incoming)
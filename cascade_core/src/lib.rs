@@ -3,3 +3,10 @@
 pub mod graph;
 pub mod registry;
 pub mod controller;
+pub mod bulletin;
+pub mod event;
+pub mod provenance;
+pub mod audit;
+pub mod trace;
+pub mod parameter;
+pub mod sensitive;
@@ -0,0 +1,172 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Shown in place of sensitive values
+pub const MASKED_VALUE: &str = "********";
+
+const REFERENCE_START: &str = "#{";
+const REFERENCE_END: char = '}';
+
+/// Value which component configs can reference as #{name}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Parameter {
+    pub value: Value,
+    // Sensitive values are masked whenever they are returned
+    #[serde(default)]
+    pub sensitive: bool,
+}
+
+/// Named set of parameters, so the same flow can be deployed with different values
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParameterContext {
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Parameter>,
+}
+
+impl ParameterContext {
+    /// Copy of the context with sensitive values masked
+    pub fn masked(&self) -> ParameterContext {
+        let parameters: BTreeMap<String, Parameter> = self
+            .parameters
+            .iter()
+            .map(|(name, parameter)| {
+                let value: Value = match parameter.sensitive {
+                    true => Value::from(MASKED_VALUE),
+                    false => parameter.value.clone(),
+                };
+
+                (
+                    name.clone(),
+                    Parameter {
+                        value,
+                        sensitive: parameter.sensitive,
+                    },
+                )
+            })
+            .collect();
+
+        ParameterContext { parameters }
+    }
+
+    /// Keep the existing value of sensitive parameters which were given back masked
+    /// This will fail with the name of the first masked parameter which has no value to keep
+    pub fn unmask(&mut self, existing: Option<&ParameterContext>) -> Result<(), String> {
+        for (name, parameter) in self.parameters.iter_mut() {
+            if !parameter.sensitive || parameter.value != MASKED_VALUE {
+                continue;
+            }
+
+            // Storing the mask would substitute it into every reference
+            match existing
+                .and_then(|existing| existing.parameters.get(name))
+                .filter(|existing| existing.sensitive)
+            {
+                Some(existing) => parameter.value = existing.value.clone(),
+                None => return Err(name.clone()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Names of parameters which differ from another context, including any added or removed
+    pub fn changed_parameters(&self, other: &ParameterContext) -> BTreeSet<String> {
+        self.parameters
+            .keys()
+            .chain(other.parameters.keys())
+            .filter(|name| self.parameters.get(*name) != other.parameters.get(*name))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Names of every parameter referenced within a config
+pub fn references(config: &Value) -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = BTreeSet::new();
+    collect_references(config, &mut names);
+
+    names
+}
+
+fn collect_references(config: &Value, names: &mut BTreeSet<String>) {
+    match config {
+        Value::String(text) => {
+            let mut rest: &str = text;
+
+            while let Some((name, after)) = next_reference(rest) {
+                names.insert(name.to_string());
+                rest = after;
+            }
+        }
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_references(value, names)),
+        Value::Object(values) => values
+            .values()
+            .for_each(|value| collect_references(value, names)),
+        _ => {}
+    }
+}
+
+// Find the first reference in some text, returning its name and the text after it
+fn next_reference(text: &str) -> Option<(&str, &str)> {
+    let start: usize = text.find(REFERENCE_START)? + REFERENCE_START.len();
+    let end: usize = start + text[start..].find(REFERENCE_END)?;
+
+    Some((&text[start..end], &text[end + 1..]))
+}
+
+/// Replace any #{name} references in a config with values from the context
+/// A string which is only a reference takes the value as it is, so it needn't be a string
+/// References within a longer string are replaced with the value as text
+/// This will fail with the name of the first parameter missing from the context
+pub fn resolve(config: &Value, context: &ParameterContext) -> Result<Value, String> {
+    let lookup = |name: &str| {
+        context
+            .parameters
+            .get(name)
+            .map(|parameter| &parameter.value)
+            .ok_or(name.to_string())
+    };
+
+    match config {
+        Value::String(text) => match next_reference(text) {
+            // Only a reference, so the value keeps its type
+            Some((name, "")) if text.len() == name.len() + REFERENCE_START.len() + 1 => {
+                lookup(name).cloned()
+            }
+            Some(_) => {
+                let mut resolved: String = String::new();
+                let mut rest: &str = text;
+
+                while let Some((name, after)) = next_reference(rest) {
+                    resolved.push_str(&rest[..rest.find(REFERENCE_START).unwrap()]);
+
+                    match lookup(name)? {
+                        Value::String(value) => resolved.push_str(value),
+                        value => resolved.push_str(&value.to_string()),
+                    }
+
+                    rest = after;
+                }
+                resolved.push_str(rest);
+
+                Ok(Value::String(resolved))
+            }
+            None => Ok(config.clone()),
+        },
+        Value::Array(values) => values
+            .iter()
+            .map(|value| resolve(value, context))
+            .collect::<Result<Vec<Value>, String>>()
+            .map(Value::Array),
+        Value::Object(values) => values
+            .iter()
+            .map(|(key, value)| resolve(value, context).map(|value| (key.clone(), value)))
+            .collect::<Result<serde_json::Map<String, Value>, String>>()
+            .map(Value::Object),
+        _ => Ok(config.clone()),
+    }
+}
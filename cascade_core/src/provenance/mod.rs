@@ -0,0 +1,128 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use cascade_api::message::Message;
+
+pub const DEFAULT_MAX_EVENTS: usize = 10_000;
+pub const JOURNAL_FILE_NAME: &str = "provenance.jsonl";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProvenanceEventType {
+    // Message was removed from the flow without being processed
+    Drop,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceEvent {
+    pub event_type: ProvenanceEventType,
+    pub message_id: String,
+    // Id of the component or connection the event occurred on
+    pub source_id: String,
+    pub timestamp_millis: u128,
+    pub details: String,
+}
+
+impl ProvenanceEvent {
+    pub fn drop(message: &Message, source_id: &str, details: &str) -> ProvenanceEvent {
+        ProvenanceEvent {
+            event_type: ProvenanceEventType::Drop,
+            message_id: message.id.clone(),
+            source_id: source_id.to_string(),
+            timestamp_millis: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis(),
+            details: details.to_string(),
+        }
+    }
+}
+
+/// Bounded in-memory store of provenance events
+/// The oldest events are evicted once max_events is reached
+/// Events are also appended to a journal file if opened with a directory
+pub struct ProvenanceRepository {
+    max_events: usize,
+    events: Mutex<VecDeque<ProvenanceEvent>>,
+    journal: Option<Mutex<File>>,
+}
+
+impl Default for ProvenanceRepository {
+    fn default() -> Self {
+        ProvenanceRepository::new(DEFAULT_MAX_EVENTS)
+    }
+}
+
+impl ProvenanceRepository {
+    pub fn new(max_events: usize) -> ProvenanceRepository {
+        ProvenanceRepository {
+            max_events,
+            events: Default::default(),
+            journal: None,
+        }
+    }
+
+    /// Open the journal within a directory, restoring the most recent events from it
+    pub fn open(max_events: usize, directory: &Path) -> std::io::Result<ProvenanceRepository> {
+        let path: PathBuf = directory.join(JOURNAL_FILE_NAME);
+        let mut events: VecDeque<ProvenanceEvent> = VecDeque::new();
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                // Skip any partially written lines
+                let Ok(event) = serde_json::from_str::<ProvenanceEvent>(&line?) else {
+                    continue;
+                };
+
+                if events.len() >= max_events {
+                    events.pop_front();
+                }
+
+                events.push_back(event);
+            }
+        }
+
+        let journal: File = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        Ok(ProvenanceRepository {
+            max_events,
+            events: Mutex::new(events),
+            journal: Some(Mutex::new(journal)),
+        })
+    }
+
+    pub fn record(&self, event: ProvenanceEvent) {
+        if let Some(journal) = &self.journal {
+            let line: String = serde_json::to_string(&event).unwrap();
+
+            if let Err(err) = writeln!(journal.lock().unwrap(), "{}", line) {
+                warn!("Failed to write provenance event to journal: {}", err);
+            }
+        }
+
+        let mut events = self.events.lock().unwrap();
+
+        if events.len() >= self.max_events {
+            events.pop_front();
+        }
+
+        events.push_back(event);
+    }
+
+    // Events relating to a message, or all events if no id is given
+    pub fn list_events(&self, message_id: Option<&str>) -> Vec<ProvenanceEvent> {
+        self.events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|event| message_id.is_none_or(|id| event.message_id == id))
+            .cloned()
+            .collect()
+    }
+}
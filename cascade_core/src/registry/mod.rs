@@ -1,18 +1,78 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::sync::Arc;
 
 use log::info;
 use serde_json::Value;
 
-use cascade_api::component::component::{Component, ComponentMetadata};
-use cascade_api::component::definition::ComponentDefinition;
+use cascade_api::component::component::{Component, ComponentMetadata, Schedule};
+use cascade_api::component::definition::{ComponentDefinition, FieldChange};
+use cascade_api::component::error::ComponentError;
 use cascade_api::component::Process;
 
-pub type ComponentMap = HashMap<&'static str, fn(Value) -> Arc<dyn Process>>;
+use crate::graph::flow::FlowDefinition;
+use crate::parameter::MASKED_VALUE;
+use crate::sensitive::{is_encrypted, SensitiveKey, SensitiveKeyError, should_encrypt};
+
+/// How to create a component type and which of its config properties are sensitive
+#[derive(Clone, Copy)]
+pub struct ComponentDescriptor {
+    pub create: fn(Value) -> Result<Arc<dyn Process>, ComponentError>,
+    pub sensitive_properties: &'static [&'static str],
+}
+
+impl ComponentDescriptor {
+    pub fn of<T: Process>() -> ComponentDescriptor {
+        ComponentDescriptor {
+            create: T::create_from_json,
+            sensitive_properties: T::sensitive_properties(),
+        }
+    }
+}
+
+pub type ComponentMap = HashMap<&'static str, ComponentDescriptor>;
+
+#[derive(Debug)]
+pub enum RegistryError {
+    UnknownType(String),
+    // The component type rejected the config
+    InvalidConfig(String, ComponentError),
+    // A sensitive property is encrypted but couldn't be decrypted
+    SensitiveProperty(String, SensitiveKeyError),
+    // A sensitive property was given masked but there's no value to keep
+    MaskedValue(String),
+}
+
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistryError::UnknownType(type_name) => f.write_fmt(format_args!(
+                "Component {} not known to instance",
+                type_name
+            )),
+            RegistryError::InvalidConfig(type_name, err) => {
+                f.write_fmt(format_args!("{} was not configured correctly: {}", type_name, err))
+            }
+            RegistryError::SensitiveProperty(property, err) => {
+                f.write_fmt(format_args!("Sensitive property {}: {}", property, err))
+            }
+            RegistryError::MaskedValue(property) => f.write_fmt(format_args!(
+                "Sensitive property {} is masked but has no value to keep",
+                property
+            )),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct ComponentRegistry {
     components: ComponentMap,
+
+    // Sensitive properties are kept in plain text if no key is configured
+    pub sensitive_key: Option<Arc<SensitiveKey>>,
+
+    // Given to components whose definition doesn't have a schedule
+    pub default_schedule: Schedule,
 }
 
 impl ComponentRegistry {
@@ -26,29 +86,177 @@ impl ComponentRegistry {
             components.len()
         );
 
-        ComponentRegistry { components }
+        ComponentRegistry {
+            components,
+            sensitive_key: None,
+            default_schedule: Default::default(),
+        }
     }
 
     pub fn list_component_types(&self) -> Vec<&str> {
         self.components.keys().cloned().collect()
     }
 
-    pub fn get_component(&self, def: &ComponentDefinition) -> Option<Component> {
+    /// Create the implementation of a component from its definition
+    /// Sensitive properties are only decrypted to be passed to the component
+    /// This will fail if either:
+    ///     The component type is not in the registry
+    ///     A sensitive property can't be decrypted
+    ///     The config doesn't match what the component type expects
+    pub fn get_component(&self, def: &ComponentDefinition) -> Result<Component, RegistryError> {
         let metadata: ComponentMetadata = ComponentMetadata::from_def(def);
 
         // Retrieve implementation from registry if present
-        let implementation: Arc<dyn Process> = self
+        let descriptor: &ComponentDescriptor = self
             .components
             .get(metadata.type_name.as_str())
-            .map(|constructor| constructor.call((def.config.clone(),)))?;
+            .ok_or(RegistryError::UnknownType(def.type_name.clone()))?;
+
+        let implementation: Arc<dyn Process> = descriptor
+            .create
+            .call((self.reveal(descriptor, &def.config)?,))
+            .map_err(|err| RegistryError::InvalidConfig(def.type_name.clone(), err))?;
 
-        Some(Component {
+        Ok(Component {
             metadata,
-            schedule: def.schedule.clone(),
+            schedule: def
+                .schedule
+                .clone()
+                .unwrap_or_else(|| self.default_schedule.clone()),
             implementation,
         })
     }
-    pub fn is_known_component(&self, type_name: &String) -> bool {
-        self.components.contains_key(type_name.as_str())
+
+    // Check a definition could be used to create its component
+    pub fn validate(&self, def: &ComponentDefinition) -> Result<(), RegistryError> {
+        self.get_component(def).map(|_| ())
+    }
+
+    pub fn is_known_component(&self, type_name: &str) -> bool {
+        self.components.contains_key(type_name)
+    }
+
+    fn sensitive_properties(&self, type_name: &str) -> &'static [&'static str] {
+        self.components
+            .get(type_name)
+            .map(|descriptor| descriptor.sensitive_properties)
+            .unwrap_or_default()
+    }
+
+    // Config with any encrypted sensitive properties decrypted
+    fn reveal(
+        &self,
+        descriptor: &ComponentDescriptor,
+        config: &Value,
+    ) -> Result<Value, RegistryError> {
+        let mut config: Value = config.clone();
+
+        for property in descriptor.sensitive_properties {
+            let Some(value) = config.get_mut(*property) else {
+                continue;
+            };
+
+            if !is_encrypted(value) {
+                continue;
+            }
+
+            let key: &SensitiveKey = self.sensitive_key.as_deref().ok_or(
+                RegistryError::SensitiveProperty(property.to_string(), SensitiveKeyError::MissingKey),
+            )?;
+            let decrypted: String = key
+                .decrypt(value.as_str().unwrap())
+                .map_err(|err| RegistryError::SensitiveProperty(property.to_string(), err))?;
+
+            *value = Value::String(decrypted);
+        }
+
+        Ok(config)
+    }
+
+    /// Encrypt sensitive properties given in plain text before the config is stored
+    /// Properties given back masked, or unchanged, keep the value they already had
+    /// This will fail if a property is masked but had no value, such as in an exported flow
+    pub fn protect(
+        &self,
+        type_name: &str,
+        config: &mut Value,
+        existing: Option<&Value>,
+    ) -> Result<(), RegistryError> {
+        for property in self.sensitive_properties(type_name) {
+            let Some(value) = config.get_mut(*property) else {
+                continue;
+            };
+            let previous: Option<&Value> = existing.and_then(|existing| existing.get(*property));
+
+            if let Some(previous) = previous {
+                let unchanged: bool = *value == MASKED_VALUE
+                    || *value == *previous
+                    || self.decrypts_to(previous, value);
+
+                if unchanged {
+                    *value = previous.clone();
+                    continue;
+                }
+            }
+
+            // Storing the mask would replace the secret with it
+            if *value == MASKED_VALUE {
+                return Err(RegistryError::MaskedValue(property.to_string()));
+            }
+
+            match &self.sensitive_key {
+                Some(key) if should_encrypt(value) => {
+                    *value = Value::String(key.encrypt(value.as_str().unwrap()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    // Whether an encrypted value holds the given plain text
+    fn decrypts_to(&self, encrypted: &Value, plaintext: &Value) -> bool {
+        match (&self.sensitive_key, encrypted.as_str(), plaintext.as_str()) {
+            (Some(key), Some(encrypted), Some(plaintext)) => key
+                .decrypt(encrypted)
+                .is_ok_and(|decrypted| decrypted == plaintext),
+            _ => false,
+        }
+    }
+
+    /// Copy of a definition with sensitive properties masked, for anything returned or recorded
+    pub fn mask(&self, def: &ComponentDefinition) -> ComponentDefinition {
+        let mut masked: ComponentDefinition = def.clone();
+
+        for property in self.sensitive_properties(&def.type_name) {
+            if let Some(value) = masked.config.get_mut(*property) {
+                *value = Value::from(MASKED_VALUE);
+            }
+        }
+
+        masked
+    }
+
+    pub fn mask_flow(&self, flow: &FlowDefinition) -> FlowDefinition {
+        FlowDefinition {
+            components: flow.components.iter().map(|def| self.mask(def)).collect(),
+            connections: flow.connections.clone(),
+        }
+    }
+
+    /// Mask the values of sensitive properties in the changes made to a component
+    pub fn mask_changes(&self, type_name: &str, changes: &mut [FieldChange]) {
+        for property in self.sensitive_properties(type_name) {
+            let field: String = format!("config.{}", property);
+
+            for change in changes.iter_mut().filter(|change| change.field == field) {
+                for value in [&mut change.before, &mut change.after] {
+                    if !value.is_null() {
+                        *value = Value::from(MASKED_VALUE);
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,106 @@
+use std::fmt::{Display, Formatter};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::aead::{Aad, AES_256_GCM, LessSafeKey, Nonce, NONCE_LEN, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::Value;
+
+use crate::parameter::references;
+
+// Marks a value as encrypted so it's never encrypted twice
+const ENCRYPTED_PREFIX: &str = "enc:";
+
+#[derive(Debug)]
+pub enum SensitiveKeyError {
+    // Keys are 32 bytes encoded as base64
+    InvalidKey,
+    // A value is encrypted but no key is configured
+    MissingKey,
+    // The value was altered or encrypted with a different key
+    DecryptionFailed,
+}
+
+impl Display for SensitiveKeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SensitiveKeyError::InvalidKey => f.write_str("Key must be 32 bytes encoded as base64"),
+            SensitiveKeyError::MissingKey => f.write_str("No sensitive key is configured"),
+            SensitiveKeyError::DecryptionFailed => {
+                f.write_str("Value could not be decrypted with the configured key")
+            }
+        }
+    }
+}
+
+/// Locally configured key which sensitive config properties are encrypted with
+/// Values are encrypted with AES-256-GCM and stored as enc: followed by the base64 nonce and ciphertext
+pub struct SensitiveKey {
+    key: LessSafeKey,
+    random: SystemRandom,
+}
+
+impl SensitiveKey {
+    pub fn from_base64(encoded: &str) -> Result<SensitiveKey, SensitiveKeyError> {
+        let bytes: Vec<u8> = STANDARD
+            .decode(encoded.trim())
+            .map_err(|_| SensitiveKeyError::InvalidKey)?;
+        let key: UnboundKey =
+            UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| SensitiveKeyError::InvalidKey)?;
+
+        Ok(SensitiveKey {
+            key: LessSafeKey::new(key),
+            random: SystemRandom::new(),
+        })
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let mut nonce: [u8; NONCE_LEN] = [0; NONCE_LEN];
+        // The system random source only fails if the OS can't provide randomness
+        self.random.fill(&mut nonce).unwrap();
+
+        let mut sealed: Vec<u8> = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut sealed,
+            )
+            .unwrap();
+
+        let mut encoded: Vec<u8> = nonce.to_vec();
+        encoded.append(&mut sealed);
+
+        format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(encoded))
+    }
+
+    pub fn decrypt(&self, encrypted: &str) -> Result<String, SensitiveKeyError> {
+        let mut bytes: Vec<u8> = encrypted
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(|encoded| STANDARD.decode(encoded).ok())
+            .filter(|bytes| bytes.len() > NONCE_LEN)
+            .ok_or(SensitiveKeyError::DecryptionFailed)?;
+
+        let mut sealed: Vec<u8> = bytes.split_off(NONCE_LEN);
+        let nonce: Nonce = Nonce::try_assume_unique_for_key(&bytes)
+            .map_err(|_| SensitiveKeyError::DecryptionFailed)?;
+
+        let plaintext: &mut [u8] = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| SensitiveKeyError::DecryptionFailed)?;
+
+        String::from_utf8(plaintext.to_vec()).map_err(|_| SensitiveKeyError::DecryptionFailed)
+    }
+}
+
+pub fn is_encrypted(value: &Value) -> bool {
+    value
+        .as_str()
+        .is_some_and(|value| value.starts_with(ENCRYPTED_PREFIX))
+}
+
+// Only literal strings are encrypted, references to parameters are resolved at start instead
+pub fn should_encrypt(value: &Value) -> bool {
+    value.is_string() && !is_encrypted(value) && references(value).is_empty()
+}
@@ -0,0 +1,104 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+use log::error;
+use serde_json::{json, Value};
+
+use cascade_api::message::trace::{Span, SpanStatus};
+
+pub const SERVICE_NAME: &str = "cascade";
+
+// OTLP span kind for work done internally
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_CODE_OK: u8 = 1;
+const STATUS_CODE_ERROR: u8 = 2;
+
+/// Receives spans as components finish each invocation
+/// Implementations must not block the calling component
+pub trait SpanExporter: Send + Sync {
+    fn export(&self, spans: Vec<Span>);
+}
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn otlp_span(span: &Span) -> Value {
+    let mut attributes: Vec<Value> = vec![
+        string_attribute("component.id", &span.component_id),
+        string_attribute("component.type", &span.component_type),
+    ];
+    if let Some(message_id) = &span.message_id {
+        attributes.push(string_attribute("message.id", message_id));
+    }
+
+    let status: Value = match &span.status {
+        SpanStatus::Ok => json!({ "code": STATUS_CODE_OK }),
+        SpanStatus::Error(message) => json!({ "code": STATUS_CODE_ERROR, "message": message }),
+    };
+
+    json!({
+        "traceId": span.trace_id,
+        "spanId": span.span_id,
+        "parentSpanId": span.parent_span_id.clone().unwrap_or_default(),
+        "name": span.name,
+        "kind": SPAN_KIND_INTERNAL,
+        // 64 bit integers are encoded as strings in OTLP JSON
+        "startTimeUnixNano": span.start_nanos.to_string(),
+        "endTimeUnixNano": span.end_nanos.to_string(),
+        "attributes": attributes,
+        "status": status,
+    })
+}
+
+/// Encode spans as an OTLP ExportTraceServiceRequest
+pub fn to_otlp_json(spans: &[Span]) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [string_attribute("service.name", SERVICE_NAME)]
+            },
+            "scopeSpans": [{
+                "scope": { "name": SERVICE_NAME },
+                "spans": spans.iter().map(otlp_span).collect::<Vec<Value>>(),
+            }]
+        }]
+    })
+}
+
+/// Appends spans to a file as OTLP JSON, one request per line
+/// Writing happens on a dedicated thread which batches pending spans
+pub struct FileSpanExporter {
+    tx: Sender<Vec<Span>>,
+}
+
+impl FileSpanExporter {
+    pub fn new(path: &Path) -> std::io::Result<FileSpanExporter> {
+        let mut file: File = OpenOptions::new().create(true).append(true).open(path)?;
+        let (tx, rx): (Sender<Vec<Span>>, Receiver<Vec<Span>>) = channel();
+
+        thread::spawn(move || {
+            // Ends once the exporter is dropped
+            while let Ok(mut spans) = rx.recv() {
+                // Combine anything else waiting into the same batch
+                spans.extend(rx.try_iter().flatten());
+
+                if let Err(err) = writeln!(file, "{}", to_otlp_json(&spans)) {
+                    error!("Failed to write {} spans with {}", spans.len(), err);
+                }
+            }
+        });
+
+        Ok(FileSpanExporter { tx })
+    }
+}
+
+impl SpanExporter for FileSpanExporter {
+    fn export(&self, spans: Vec<Span>) {
+        // Only fails if the writer thread has died, which it already logged
+        let _ = self.tx.send(spans);
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::{Display, Formatter};
+
+use hyper::{Body, header, Request};
+use jsonwebtoken::{Algorithm, decode, DecodingKey, TokenData, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::endpoint::EndpointError;
+
+const BEARER_PREFIX: &str = "Bearer ";
+
+/// Roles in increasing order of privilege, each granting everything the previous one does
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    // Read definitions, stats and monitoring
+    Viewer,
+    // Control components and inspect or purge queued messages
+    Operator,
+    // Change the graph
+    Admin,
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Viewer => f.write_str("viewer"),
+            Role::Operator => f.write_str("operator"),
+            Role::Admin => f.write_str("admin"),
+        }
+    }
+}
+
+/// A static token along with who it identifies
+#[derive(Clone, Debug, Deserialize)]
+pub struct ApiToken {
+    pub name: String,
+    pub token: String,
+    pub role: Role,
+}
+
+/// Shared secret and expected claims of HS256 signed JWTs
+/// The subject names the caller and the role claim gives their role
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtConfig {
+    pub secret: String,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+}
+
+/// Requests are only authenticated if tokens or a JWT secret are configured
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub tokens: Vec<ApiToken>,
+    pub jwt: Option<JwtConfig>,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    sub: String,
+    role: Role,
+}
+
+/// Who made a request, stored in the request extensions once authenticated
+#[derive(Clone, Debug, Serialize)]
+pub struct Principal {
+    pub name: String,
+    pub role: Role,
+}
+
+pub struct Authenticator {
+    tokens: Vec<ApiToken>,
+    jwt: Option<(DecodingKey, Validation)>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Result<Authenticator, String> {
+        if let Some(token) = config.tokens.iter().find(|token| token.token.is_empty()) {
+            return Err(format!("Token for {} must not be empty", token.name));
+        }
+
+        let jwt: Option<(DecodingKey, Validation)> = match &config.jwt {
+            Some(jwt) if jwt.secret.is_empty() => {
+                return Err("JWT secret must not be empty".to_string());
+            }
+            Some(jwt) => {
+                let mut validation: Validation = Validation::new(Algorithm::HS256);
+                validation.set_required_spec_claims(&["exp", "sub"]);
+
+                if let Some(issuer) = &jwt.issuer {
+                    validation.set_issuer(&[issuer]);
+                }
+
+                // Audiences are only checked when one is expected
+                match &jwt.audience {
+                    Some(audience) => validation.set_audience(&[audience]),
+                    None => validation.validate_aud = false,
+                }
+
+                Some((DecodingKey::from_secret(jwt.secret.as_bytes()), validation))
+            }
+            None => None,
+        };
+
+        Ok(Authenticator {
+            tokens: config.tokens.clone(),
+            jwt,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || self.jwt.is_some()
+    }
+
+    /// Identify the caller from the bearer token of a request
+    /// Every request is treated as an admin if authentication is disabled
+    /// This will fail if either:
+    ///     The request has no bearer token
+    ///     The token matches no static token and isn't a valid JWT
+    pub fn authenticate(&self, request: &Request<Body>) -> Result<Principal, EndpointError> {
+        if !self.is_enabled() {
+            return Ok(Principal {
+                name: "anonymous".to_string(),
+                role: Role::Admin,
+            });
+        }
+
+        let token: &str = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix(BEARER_PREFIX))
+            .ok_or(EndpointError::Unauthorized(
+                "A bearer token is required".to_string(),
+            ))?;
+
+        // Check every token so the time taken doesn't reveal which matched
+        let matched: Option<&ApiToken> = self.tokens.iter().fold(None, |matched, api_token| {
+            if constant_time_eq(api_token.token.as_bytes(), token.as_bytes()) {
+                Some(api_token)
+            } else {
+                matched
+            }
+        });
+
+        if let Some(api_token) = matched {
+            return Ok(Principal {
+                name: api_token.name.clone(),
+                role: api_token.role,
+            });
+        }
+
+        let Some((key, validation)) = &self.jwt else {
+            return Err(EndpointError::Unauthorized(
+                "Token is not valid".to_string(),
+            ));
+        };
+
+        let claims: TokenData<Claims> = decode(token, key, validation)
+            .map_err(|err| EndpointError::Unauthorized(format!("Token is not valid: {}", err)))?;
+
+        Ok(Principal {
+            name: claims.claims.sub,
+            role: claims.claims.role,
+        })
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,374 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use clap::Parser;
+use serde::Deserialize;
+use serde_json::Value;
+
+use cascade_api::component::component::Schedule;
+use cascade_core::graph::flow::FlowDefinition;
+use cascade_core::parameter::ParameterContext;
+use cascade_core::sensitive::SensitiveKey;
+use cascade_http_server::auth::{AuthConfig, JwtConfig};
+use cascade_http_server::DEFAULT_STATS_PERIOD;
+use cascade_http_server::tls::TlsConfig;
+
+use crate::logger::{
+    LogFileConfig, LogFormat, LoggerConfig, LOG_ENV, LOG_FILE_ENV, LOG_FORMAT_ENV,
+};
+
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:3001";
+// Changes are always audited to disk, so this is used if no directory is given
+const DEFAULT_AUDIT_DIRECTORY: &str = "./data/audit";
+
+/// Command line flags, each of which can also be set through the environment
+/// Anything given here takes precedence over the config file
+#[derive(Debug, Parser)]
+#[command(
+    name = "cascade_http_server",
+    version,
+    about = "Run a cascade instance over HTTP"
+)]
+pub struct Args {
+    /// TOML file to read the server configuration from
+    #[arg(short, long, env = "CASCADE_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address to serve the API on
+    #[arg(long, env = "CASCADE_BIND")]
+    pub bind: Option<SocketAddr>,
+
+    /// Directory to persist component state in
+    #[arg(long, env = "CASCADE_STATE_DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Directory to journal provenance events in
+    #[arg(long, env = "CASCADE_PROVENANCE_DIR")]
+    pub provenance_dir: Option<PathBuf>,
+
+    /// Directory to append the audit log to, ./data/audit by default
+    #[arg(long, env = "CASCADE_AUDIT_DIR")]
+    pub audit_dir: Option<PathBuf>,
+
+    /// Log filter such as info,cascade_core=debug
+    #[arg(long, env = LOG_ENV)]
+    pub log: Option<String>,
+
+    /// Log output format, either text or json
+    #[arg(long, env = LOG_FORMAT_ENV)]
+    pub log_format: Option<LogFormat>,
+
+    /// File to log to instead of stdout
+    #[arg(long, env = LOG_FILE_ENV)]
+    pub log_file: Option<PathBuf>,
+
+    /// Component types to register, all available types if not given
+    #[arg(long = "component", env = "CASCADE_COMPONENTS", value_delimiter = ',')]
+    pub components: Vec<String>,
+
+    /// Period of the interval schedule used by components without one
+    #[arg(long, env = "CASCADE_DEFAULT_PERIOD_MILLIS")]
+    pub default_period_millis: Option<u64>,
+
+    /// JSON or TOML flow to load into the graph on startup
+    #[arg(long, env = "CASCADE_FLOW_FILE")]
+    pub flow_file: Option<PathBuf>,
+
+    /// File holding the base64 encoded 256 bit key sensitive properties are encrypted with
+    #[arg(long, env = "CASCADE_SENSITIVE_KEY_FILE")]
+    pub sensitive_key_file: Option<PathBuf>,
+
+    /// How often queue stats are pushed to event subscribers
+    #[arg(long, env = "CASCADE_STATS_PERIOD_MILLIS")]
+    pub stats_period_millis: Option<u64>,
+
+    /// File to export spans to as OTLP JSON lines
+    #[arg(long, env = "CASCADE_TRACE_FILE")]
+    pub trace_file: Option<PathBuf>,
+
+    /// Collector to post spans to, takes precedence over the trace file
+    #[arg(long, env = "CASCADE_TRACE_ENDPOINT")]
+    pub trace_endpoint: Option<String>,
+
+    /// Secret to validate HS256 signed JWTs with
+    #[arg(long, env = "CASCADE_JWT_SECRET", hide_env_values = true)]
+    pub jwt_secret: Option<String>,
+
+    /// PEM certificate chain to serve HTTPS with
+    #[arg(long, env = "CASCADE_TLS_CERT")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the certificate
+    #[arg(long, env = "CASCADE_TLS_KEY")]
+    pub tls_key: Option<PathBuf>,
+
+    /// PEM certificates of authorities to require client certificates from
+    #[arg(long, env = "CASCADE_TLS_CLIENT_CA")]
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TraceConfig {
+    pub file: Option<PathBuf>,
+    pub endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub bind_address: SocketAddr,
+
+    pub state_directory: Option<PathBuf>,
+    pub provenance_directory: Option<PathBuf>,
+    pub audit_directory: PathBuf,
+
+    pub log: LoggerConfig,
+
+    // Component types to register, all available types if empty
+    pub components: Vec<String>,
+    // Schedule for components which don't specify one
+    pub default_schedule: Option<Schedule>,
+
+    pub flow_file: Option<PathBuf>,
+    // Contexts the flow's parameters are resolved from, so it can be deployed anywhere
+    pub parameter_contexts: BTreeMap<String, ParameterContext>,
+    // Sensitive properties are kept in plain text if this isn't set
+    pub sensitive_key_file: Option<PathBuf>,
+
+    pub stats_period_millis: u64,
+
+    pub trace: TraceConfig,
+
+    pub auth: AuthConfig,
+
+    // Plain HTTP is served if this isn't set
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            bind_address: DEFAULT_BIND_ADDRESS.parse().unwrap(),
+            state_directory: None,
+            provenance_directory: None,
+            audit_directory: PathBuf::from(DEFAULT_AUDIT_DIRECTORY),
+            log: Default::default(),
+            components: vec![],
+            default_schedule: None,
+            flow_file: None,
+            parameter_contexts: Default::default(),
+            sensitive_key_file: None,
+            stats_period_millis: DEFAULT_STATS_PERIOD.as_millis() as u64,
+            trace: Default::default(),
+            auth: Default::default(),
+            tls: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, String),
+    InvalidArgument(String),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => {
+                f.write_fmt(format_args!("Could not read {}: {}", path.display(), err))
+            }
+            ConfigError::Parse(path, err) => {
+                f.write_fmt(format_args!("Could not parse {}: {}", path.display(), err))
+            }
+            ConfigError::InvalidArgument(err) => f.write_str(err),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Read the config file if one was given then apply the flags over it
+    pub fn load(args: Args) -> Result<ServerConfig, ConfigError> {
+        let mut config: ServerConfig = match &args.config {
+            Some(path) => toml::from_str(&read_file(path)?)
+                .map_err(|err| ConfigError::Parse(path.clone(), err.to_string()))?,
+            None => Default::default(),
+        };
+
+        config.apply_args(args)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    // Settings which would otherwise only fail once the server is running
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.stats_period_millis == 0 {
+            return Err(ConfigError::InvalidArgument(
+                "stats_period_millis must be greater than 0".to_string(),
+            ));
+        }
+
+        if self.tls.as_ref().is_some_and(|tls| tls.reload_period_millis == 0) {
+            return Err(ConfigError::InvalidArgument(
+                "tls.reload_period_millis must be greater than 0".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn apply_args(&mut self, args: Args) -> Result<(), ConfigError> {
+        if let Some(bind) = args.bind {
+            self.bind_address = bind;
+        }
+
+        if args.state_dir.is_some() {
+            self.state_directory = args.state_dir;
+        }
+
+        if args.provenance_dir.is_some() {
+            self.provenance_directory = args.provenance_dir;
+        }
+
+        if let Some(audit_dir) = args.audit_dir {
+            self.audit_directory = audit_dir;
+        }
+
+        if let Some(spec) = args.log {
+            self.log
+                .apply_filter_spec(&spec)
+                .map_err(ConfigError::InvalidArgument)?;
+        }
+
+        if let Some(format) = args.log_format {
+            self.log.format = format;
+        }
+
+        if let Some(path) = args.log_file {
+            self.log.file = Some(LogFileConfig::new(path));
+        }
+
+        if !args.components.is_empty() {
+            self.components = args.components;
+        }
+
+        if let Some(period_millis) = args.default_period_millis {
+            self.default_schedule = Some(Schedule::Interval { period_millis });
+        }
+
+        if args.flow_file.is_some() {
+            self.flow_file = args.flow_file;
+        }
+
+        if args.sensitive_key_file.is_some() {
+            self.sensitive_key_file = args.sensitive_key_file;
+        }
+
+        if let Some(stats_period_millis) = args.stats_period_millis {
+            self.stats_period_millis = stats_period_millis;
+        }
+
+        if args.trace_file.is_some() {
+            self.trace.file = args.trace_file;
+        }
+
+        if args.trace_endpoint.is_some() {
+            self.trace.endpoint = args.trace_endpoint;
+        }
+
+        if let Some(secret) = args.jwt_secret {
+            match &mut self.auth.jwt {
+                Some(jwt) => jwt.secret = secret,
+                None => {
+                    self.auth.jwt = Some(JwtConfig {
+                        secret,
+                        issuer: None,
+                        audience: None,
+                    })
+                }
+            }
+        }
+
+        self.apply_tls_args(args.tls_cert, args.tls_key, args.tls_client_ca)
+    }
+
+    // Flags can complete or override the TLS section of the config file
+    fn apply_tls_args(
+        &mut self,
+        certificate: Option<PathBuf>,
+        private_key: Option<PathBuf>,
+        client_ca: Option<PathBuf>,
+    ) -> Result<(), ConfigError> {
+        if certificate.is_none() && private_key.is_none() && client_ca.is_none() {
+            return Ok(());
+        }
+
+        let existing: Option<TlsConfig> = self.tls.take();
+
+        let (Some(certificate), Some(private_key)) = (
+            certificate.or(existing.as_ref().map(|tls| tls.certificate.clone())),
+            private_key.or(existing.as_ref().map(|tls| tls.private_key.clone())),
+        ) else {
+            return Err(ConfigError::InvalidArgument(
+                "TLS requires both a certificate and a private key".to_string(),
+            ));
+        };
+
+        let mut tls: TlsConfig =
+            existing.unwrap_or_else(|| TlsConfig::new(certificate.clone(), private_key.clone()));
+        tls.certificate = certificate;
+        tls.private_key = private_key;
+
+        if client_ca.is_some() {
+            tls.client_ca = client_ca;
+        }
+
+        self.tls = Some(tls);
+
+        Ok(())
+    }
+
+    pub fn stats_period(&self) -> Duration {
+        Duration::from_millis(self.stats_period_millis)
+    }
+}
+
+/// Read a flow from a TOML file, or JSON for any other extension
+pub fn read_flow_file(path: &Path) -> Result<FlowDefinition, ConfigError> {
+    let document: Value = read_flow_document(path)?;
+
+    // Ids are kept so component state is found again after a restart
+    FlowDefinition::with_ids(document)
+        .map_err(|err| ConfigError::Parse(path.to_path_buf(), err.to_string()))
+}
+
+/// Read the key sensitive properties are encrypted with
+pub fn read_sensitive_key(path: &Path) -> Result<SensitiveKey, ConfigError> {
+    SensitiveKey::from_base64(&read_file(path)?)
+        .map_err(|err| ConfigError::Parse(path.to_path_buf(), err.to_string()))
+}
+
+/// Read a flow file without interpreting it as a flow
+pub fn read_flow_document(path: &Path) -> Result<Value, ConfigError> {
+    let contents: String = read_file(path)?;
+
+    let document: Result<Value, String> = match path.extension() {
+        Some(extension) if extension == "toml" => {
+            toml::from_str(&contents).map_err(|err| err.to_string())
+        }
+        _ => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+    };
+
+    document.map_err(|err| ConfigError::Parse(path.to_path_buf(), err))
+}
+
+fn read_file(path: &Path) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use hyper::{Body, Request};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use cascade_core::audit::{AuditQuery, AuditRecord};
+use cascade_core::controller::CascadeController;
+
+use crate::endpoint::{create_json_body, EndpointError, EndpointResult, parse_query_params};
+
+pub(crate) const PRINCIPAL_PARAM: &str = "principal";
+pub(crate) const OPERATION_PARAM: &str = "operation";
+pub(crate) const TARGET_PARAM: &str = "target";
+pub(crate) const SINCE_MILLIS_PARAM: &str = "since_millis";
+pub(crate) const LIMIT_PARAM: &str = "limit";
+
+/// Details of a change, attached to the response of a successful operation
+/// The router combines it with who made the request into an audit record
+#[derive(Clone, Default)]
+pub struct AuditChange {
+    // Ids affected beyond those in the path, such as a newly created component
+    pub targets: Vec<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+// Definitions always serialise, so null is never actually recorded
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or_default()
+}
+
+impl AuditChange {
+    pub fn created<T: Serialize>(targets: Vec<String>, after: &T) -> AuditChange {
+        AuditChange {
+            targets,
+            before: None,
+            after: Some(to_value(after)),
+        }
+    }
+
+    pub fn removed<T: Serialize>(targets: Vec<String>, before: &T) -> AuditChange {
+        AuditChange {
+            targets,
+            before: Some(to_value(before)),
+            after: None,
+        }
+    }
+
+    pub fn updated<T: Serialize>(before: &T, after: &T) -> AuditChange {
+        AuditChange {
+            targets: vec![],
+            before: Some(to_value(before)),
+            after: Some(to_value(after)),
+        }
+    }
+}
+
+// Attach a change to a response for the router to audit
+pub(crate) fn audited(result: EndpointResult, change: AuditChange) -> EndpointResult {
+    result.map(|mut response| {
+        response.extensions_mut().insert(change);
+        response
+    })
+}
+
+// Complete a record from the outcome of the operation
+pub(crate) fn complete_record(record: &mut AuditRecord, result: &EndpointResult) {
+    match result {
+        Ok(response) => {
+            let Some(change) = response.extensions().get::<AuditChange>() else {
+                return;
+            };
+
+            for target in &change.targets {
+                if !record.targets.contains(target) {
+                    record.targets.push(target.clone());
+                }
+            }
+
+            record.before = change.before.clone();
+            record.after = change.after.clone();
+        }
+        Err(err) => record.error = Some(err.body().message),
+    }
+}
+
+fn parse_param<T: FromStr>(
+    params: &HashMap<String, String>,
+    name: &str,
+) -> Result<Option<T>, EndpointError> {
+    params
+        .get(name)
+        .map(|value| {
+            value.parse::<T>().map_err(|_| {
+                EndpointError::BadRequest(format!("Query parameter {} was not a number", name))
+            })
+        })
+        .transpose()
+}
+
+/// List audit records, oldest first
+/// Optionally filtered by principal, operation, target id, since_millis and limit query parameters
+/// This will fail if:
+///     since_millis or limit are not numbers
+pub async fn list_audit(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let params: HashMap<String, String> = parse_query_params(&request);
+
+    let query: AuditQuery = AuditQuery {
+        principal: params.get(PRINCIPAL_PARAM).cloned(),
+        operation: params.get(OPERATION_PARAM).cloned(),
+        target: params.get(TARGET_PARAM).cloned(),
+        since_millis: parse_param(&params, SINCE_MILLIS_PARAM)?,
+        limit: parse_param(&params, LIMIT_PARAM)?,
+    };
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let records: Vec<AuditRecord> = controller_lock.audit.list(&query);
+
+    create_json_body(&records)
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use hyper::{Body, Request};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use cascade_core::bulletin::{Bulletin, Severity};
+use cascade_core::controller::CascadeController;
+
+use crate::endpoint::{create_json_body, EndpointError, EndpointResult, parse_query_params};
+
+pub(crate) const COMPONENT_ID_PARAM: &str = "component_id";
+pub(crate) const SEVERITY_PARAM: &str = "severity";
+
+/// List bulletins raised by components
+/// Optionally filtered with component_id and minimum severity query parameters
+/// This will fail if:
+///     The severity is not one of Warning or Error
+pub async fn list_bulletins(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let params: HashMap<String, String> = parse_query_params(&request);
+
+    let severity: Option<Severity> = params
+        .get(SEVERITY_PARAM)
+        .map(|severity| Severity::from_str(severity))
+        .transpose()
+        .map_err(EndpointError::BadRequest)?;
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let bulletins: Vec<Bulletin> = controller_lock
+        .bulletins
+        .list(params.get(COMPONENT_ID_PARAM).map(String::as_str), severity);
+
+    create_json_body(&bulletins)
+}
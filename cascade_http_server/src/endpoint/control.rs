@@ -1,29 +1,46 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
-use hyper::{Body, Request, Response, StatusCode};
-use log::info;
-use petgraph::graph::NodeIndex;
-use tokio::sync::{RwLock, RwLockWriteGuard};
+use hyper::{Body, Request};
+use log::{info, LevelFilter};
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use cascade_api::component::component::ComponentMetadata;
+use cascade_api::connection::filter::MessageFilter;
 use cascade_core::controller::CascadeController;
 use cascade_core::controller::error::{StartComponentError, StopComponentError};
+use cascade_core::event::ComponentStatus;
+
+use crate::endpoint::{
+    component_index, connection_index, create_json_body, deserialise_body,
+    deserialise_body_or_default, EndpointError, EndpointResult, get_path_parameter, ID_PARAM,
+};
+use crate::endpoint::audit::{AuditChange, audited};
+
+#[derive(Serialize)]
+struct StatusResult {
+    id: String,
+    idx: usize,
+    status: ComponentStatus,
+}
 
-use crate::endpoint::{EndpointError, EndpointResult, get_idx_query_parameter};
-
-/// Start a component in the graph from an index query parameter
+/// Start a component from the id in the path
 /// This will fail if either:
-///     The query parameter is not present or NaN
-///     The component to start does not exist in the graph
+///     The component does not exist in the graph
+///     The component is already running
+///     The connections from the component are inconsistent
 /// This will NOT fail if:
 ///     The component experiences a runtime error
 pub async fn start_component(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let node_idx: NodeIndex = NodeIndex::new(get_idx_query_parameter(request)?);
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
 
     let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let node_idx: NodeIndex = component_index(&controller_lock, &id).await?;
 
     // Try and start the component associated with the node
     let result: Result<ComponentMetadata, StartComponentError> =
@@ -31,37 +48,40 @@ pub async fn start_component(
 
     match result {
         Ok(metadata) => {
-            let message: String = format!(
+            info!(
                 "Successfully started {} at idx {}",
                 metadata,
                 node_idx.index()
             );
 
-            info!("{}", message);
-
-            Ok(Response::builder()
-                .status(StatusCode::ACCEPTED)
-                .body(Body::from(message))?)
+            create_json_body(&StatusResult {
+                id,
+                idx: node_idx.index(),
+                status: ComponentStatus::Started,
+            })
+        }
+        Err(err @ StartComponentError::InvalidNodeIndex(_)) => {
+            Err(EndpointError::NotFound(err.to_string()))
+        }
+        Err(err @ StartComponentError::MissingComponent(_)) => {
+            Err(EndpointError::BadRequest(err.to_string()))
         }
-        Err(err) => Err(EndpointError::BadRequest(format!(
-            "Encountered {:?} when starting idx {}",
-            err,
-            node_idx.index()
-        ))),
+        Err(err) => Err(EndpointError::Conflict(err.to_string())),
     }
 }
 
-/// Stop a component in the graph from an index query parameter
+/// Stop a component from the id in the path
 /// This will fail if either:
-///     The query parameter is not present or NaN
-///     The component to stop is not running already
+///     The component does not exist in the graph
+///     The component is not running
 pub async fn stop_component(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let node_idx: NodeIndex = NodeIndex::new(get_idx_query_parameter(request)?);
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
 
     let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let node_idx: NodeIndex = component_index(&controller_lock, &id).await?;
 
     let result: Result<ComponentMetadata, StopComponentError> =
         controller_lock.stop_component(node_idx).await;
@@ -73,46 +93,120 @@ pub async fn stop_component(
 
     match result {
         Ok(metadata) => {
-            let message: String = format!(
+            info!(
                 "Successfully stopped {} at idx {}",
                 metadata,
                 node_idx.index()
             );
 
-            info!("{}", message);
-
-            Ok(Response::builder()
-                .status(StatusCode::ACCEPTED)
-                .body(Body::from(message))?)
+            create_json_body(&StatusResult {
+                id,
+                idx: node_idx.index(),
+                status: ComponentStatus::Stopped,
+            })
+        }
+        Err(err @ StopComponentError::ComponentNotStarted(_)) => {
+            Err(EndpointError::Conflict(err.to_string()))
         }
-        Err(err) => Err(EndpointError::BadRequest(format!(
-            "Encountered {:?} when stopping idx {}",
-            err,
-            node_idx.index()
-        ))),
+        Err(err) => Err(EndpointError::InternalServerError(err.to_string())),
     }
 }
 
-/// Kill a component in the graph from an index query parameter
-/// This will fail if either:
-///     The query parameter is not present or NaN
-///     The component to stop is not running already
+/// Kill a component from the id in the path
+/// This will fail if:
+///     The component does not exist in the graph
 /// This will not return until the components are killed
 pub async fn kill_component(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let node_idx: NodeIndex = NodeIndex::new(get_idx_query_parameter(request)?);
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
 
     let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let node_idx: NodeIndex = component_index(&controller_lock, &id).await?;
 
     controller_lock.kill_component(node_idx).await;
 
-    let message: String = format!("Successfully sent kill signal to idx {}", node_idx.index());
+    info!("Successfully sent kill signal to idx {}", node_idx.index());
+
+    create_json_body(&StatusResult {
+        id,
+        idx: node_idx.index(),
+        status: ComponentStatus::Killed,
+    })
+}
+
+#[derive(Serialize)]
+struct PurgeResult {
+    dropped: usize,
+}
+
+/// Drop messages queued on a connection from the id in the path
+/// An optional JSON body of MessageFilter selects which messages to drop
+/// Every message is dropped if no body is sent
+/// This will fail if either:
+///     The JSON is malformed or doesn't match MessageFilter
+///     The connection does not exist in the graph
+pub async fn purge_connection(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+    let filter: MessageFilter = deserialise_body_or_default(request).await?;
 
-    info!("{}", message);
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let edge_idx: EdgeIndex = connection_index(&controller_lock, &id).await?;
 
-    Ok(Response::builder()
-        .status(StatusCode::ACCEPTED)
-        .body(Body::from(message))?)
+    match controller_lock.purge_connection(edge_idx, &filter).await {
+        Ok(dropped) => create_json_body(&PurgeResult { dropped }),
+        Err(err) => Err(EndpointError::NotFound(err.to_string())),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LogLevel {
+    level: String,
+}
+
+/// Set the log level of a component from the id in the path and a JSON request
+/// The level applies immediately to running components and persists across restarts
+/// This will fail if either:
+///     The JSON is malformed or the level is unknown
+///     The component does not exist in the graph
+pub async fn set_log_level(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+    let body: LogLevel = deserialise_body(request).await?;
+
+    let level: LevelFilter = LevelFilter::from_str(&body.level)
+        .map_err(|_| EndpointError::BadRequest(format!("Unknown log level {}", body.level)))?;
+
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let node_idx: NodeIndex = component_index(&controller_lock, &id).await?;
+
+    match controller_lock.set_log_level(node_idx, level).await {
+        Ok((def, previous)) => {
+            info!(
+                "Set log level of {} at idx {} to {}",
+                def.display_name,
+                node_idx.index(),
+                level
+            );
+
+            let previous: LogLevel = LogLevel {
+                level: previous.to_string(),
+            };
+            let current: LogLevel = LogLevel {
+                level: level.to_string(),
+            };
+
+            audited(
+                create_json_body(&current),
+                AuditChange::updated(&previous, &current),
+            )
+        }
+        Err(err) => Err(EndpointError::NotFound(err.to_string())),
+    }
 }
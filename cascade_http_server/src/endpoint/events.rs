@@ -0,0 +1,92 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream;
+use hyper::{header, Body, Request, Response, StatusCode};
+use log::warn;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::{RwLock, RwLockReadGuard};
+use tokio::time::{interval, Interval};
+
+use cascade_core::controller::CascadeController;
+use cascade_core::event::{CascadeEvent, EventBus};
+use cascade_core::registry::ComponentRegistry;
+
+use crate::endpoint::EndpointResult;
+
+const TEXT_EVENT_STREAM: &str = "text/event-stream";
+
+/// Stream events to the client as Server-Sent Events
+/// The response stays open until the client disconnects
+pub async fn stream_events(
+    controller: Arc<RwLock<CascadeController>>,
+    _: Request<Body>,
+) -> EndpointResult {
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let receiver: Receiver<CascadeEvent> = controller_lock.events.subscribe();
+    // Kept to mask sensitive properties in definitions
+    let registry: ComponentRegistry = controller_lock.component_registry.clone();
+
+    let events = stream::unfold((receiver, registry), |(mut receiver, registry)| async move {
+        let chunk: String = match receiver.recv().await {
+            Ok(event) => format_event(&mask_event(&registry, event)),
+            // Let the client know it missed some events
+            Err(RecvError::Lagged(skipped)) => format!(": skipped {} events\n\n", skipped),
+            Err(RecvError::Closed) => return None,
+        };
+
+        Some((Ok::<_, Infallible>(chunk), (receiver, registry)))
+    });
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, TEXT_EVENT_STREAM)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::wrap_stream(events))?)
+}
+
+fn mask_event(registry: &ComponentRegistry, event: CascadeEvent) -> CascadeEvent {
+    match event {
+        CascadeEvent::ComponentCreated { idx, definition } => CascadeEvent::ComponentCreated {
+            idx,
+            definition: registry.mask(&definition),
+        },
+        CascadeEvent::ComponentUpdated { idx, definition } => CascadeEvent::ComponentUpdated {
+            idx,
+            definition: registry.mask(&definition),
+        },
+        event => event,
+    }
+}
+
+fn format_event(event: &CascadeEvent) -> String {
+    match serde_json::to_string(event) {
+        Ok(serialised) => format!("data: {}\n\n", serialised),
+        Err(err) => {
+            warn!("Failed to serialise event {}", err);
+
+            // Comments are ignored by clients
+            String::from(": unserialisable event\n\n")
+        }
+    }
+}
+
+/// Periodically publish the state of every connection while anyone is subscribed
+pub async fn publish_queue_stats(controller: Arc<RwLock<CascadeController>>, period: Duration) {
+    let mut interval: Interval = interval(period);
+
+    loop {
+        interval.tick().await;
+
+        let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+        let events: &EventBus = &controller_lock.events;
+
+        if events.has_subscribers() {
+            events.publish(CascadeEvent::QueueStats {
+                connections: controller_lock.list_queue_stats().await,
+            });
+        }
+    }
+}
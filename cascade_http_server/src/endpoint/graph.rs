@@ -1,181 +1,480 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
-use hyper::{Body, Request, Response, StatusCode};
+use hyper::{Body, Request, StatusCode};
 use log::info;
 use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde::Serialize;
 use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use cascade_api::component::definition::ComponentDefinition;
-use cascade_api::connection::definition::ConnectionDefinition;
-use cascade_core::controller::CascadeController;
-use cascade_core::graph::{CascadeGraph, GraphInternal};
+use cascade_api::component::definition::{ComponentDefinition, ComponentUpdate, FieldChange};
+use cascade_api::connection::ConnectionStats;
+use cascade_api::connection::definition::{ConnectionDefinition, ConnectionUpdate};
+use cascade_core::bulletin::Bulletin;
+use cascade_core::controller::{
+    CascadeController, ComponentUpdateResult, ConnectionUpdateResult,
+};
+use cascade_core::controller::error::{
+    ReloadFlowError, RemoveComponentError, RemoveConnectionError, UpdateComponentError,
+    UpdateConnectionError,
+};
+use cascade_core::event::CascadeEvent;
+use cascade_core::graph::CascadeGraph;
+use cascade_core::graph::flow::{FlowDefinition, FlowDiff};
 
 use crate::endpoint::{
-    create_json_body, deserialise_body, EndpointError, EndpointResult, get_idx_query_parameter,
+    component_index, connection_index, create_empty_response, create_json_body, create_json_response,
+    deserialise_body, EndpointError, EndpointResult, find_component, find_connection,
+    get_path_parameter, ID_PARAM, parse_query_params,
 };
+use crate::endpoint::audit::{AuditChange, audited};
+use crate::endpoint::metrics::connection_stats;
+
+// Bulletins included alongside each node
+const NODE_BULLETIN_LIMIT: usize = 5;
+
+const RESTART_PARAM: &str = "restart";
+const DRY_RUN_PARAM: &str = "dry_run";
+
+#[derive(Serialize)]
+struct NodeSummary {
+    idx: usize,
+    #[serde(flatten)]
+    definition: ComponentDefinition,
+    running: bool,
+    // Most recent warnings and errors from the component
+    bulletins: Vec<Bulletin>,
+}
+
+fn summarise_node(
+    controller: &CascadeController,
+    graph: &CascadeGraph,
+    node_idx: NodeIndex,
+) -> NodeSummary {
+    // Sensitive properties are never returned
+    let definition: ComponentDefinition = controller
+        .component_registry
+        .mask(&graph.graph_internal[node_idx]);
+
+    NodeSummary {
+        idx: node_idx.index(),
+        running: controller.is_running(node_idx),
+        bulletins: controller
+            .bulletins
+            .list_recent(&definition.id, NODE_BULLETIN_LIMIT),
+        definition,
+    }
+}
+
+#[derive(Serialize)]
+struct ConnectionSummary {
+    idx: usize,
+    #[serde(flatten)]
+    definition: ConnectionDefinition,
+    stats: ConnectionStats,
+}
+
+async fn summarise_connection(
+    controller: &CascadeController,
+    graph: &CascadeGraph,
+    edge_idx: EdgeIndex,
+) -> ConnectionSummary {
+    let definition: ConnectionDefinition = graph.graph_internal[edge_idx].clone();
+
+    ConnectionSummary {
+        idx: edge_idx.index(),
+        stats: connection_stats(controller, edge_idx, &definition).await,
+        definition,
+    }
+}
 
-/// List the component definitions in the graph
-pub async fn list_graph_nodes(
+/// List the component definitions in the graph with their recent bulletins
+pub async fn list_components(
     controller: Arc<RwLock<CascadeController>>,
     _: Request<Body>,
 ) -> EndpointResult {
     let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
     let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
 
-    let definitions: Vec<ComponentDefinition> = graph_lock
+    let nodes: Vec<NodeSummary> = graph_lock
         .graph_internal
-        .raw_nodes()
-        .iter()
-        .map(|node| node.weight.clone())
+        .node_indices()
+        .map(|node_idx| summarise_node(&controller_lock, &graph_lock, node_idx))
         .collect();
 
-    Ok(create_json_body(&definitions)?)
+    create_json_body(&nodes)
 }
 
-/// List the connection definitions in the graph
-pub async fn list_graph_connections(
+/// Describe a single component from the id in the path
+/// This will fail if:
+///     The component does not exist
+pub async fn get_component(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
+
+    let node_idx: NodeIndex = find_component(&graph_lock, &id)?;
+
+    create_json_body(&summarise_node(&controller_lock, &graph_lock, node_idx))
+}
+
+/// List the connection definitions in the graph with the state of their queues
+pub async fn list_connections(
     controller: Arc<RwLock<CascadeController>>,
     _: Request<Body>,
 ) -> EndpointResult {
     let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
     let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
 
-    let definitions: Vec<ConnectionDefinition> = graph_lock
-        .graph_internal
-        .raw_edges()
-        .iter()
-        .map(|node| node.weight.clone())
-        .collect();
+    let mut connections: Vec<ConnectionSummary> = vec![];
+
+    for edge_idx in graph_lock.graph_internal.edge_indices() {
+        connections.push(summarise_connection(&controller_lock, &graph_lock, edge_idx).await);
+    }
+
+    create_json_body(&connections)
+}
+
+/// Describe a single connection from the id in the path
+/// This will fail if:
+///     The connection does not exist
+pub async fn get_connection(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
+
+    let edge_idx: EdgeIndex = find_connection(&graph_lock, &id)?;
 
-    Ok(create_json_body(&definitions)?)
+    create_json_body(&summarise_connection(&controller_lock, &graph_lock, edge_idx).await)
 }
 
 /// Create a component in the graph from a JSON request
 /// This will fail if either:
 ///     The JSON is malformed or doesn't match ComponentDefinition
 ///     The named component does not exist in the registry
+///     A sensitive property is given masked
+///     The config doesn't match what the component type expects
 pub async fn create_component(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let def: ComponentDefinition = deserialise_body(request).await?;
+    let mut def: ComponentDefinition = deserialise_body(request).await?;
     let type_name: String = def.type_name.clone();
 
     let controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
 
-    // Error early if the component type is not known
-    if !controller_lock
+    controller_lock
         .component_registry
-        .is_known_component(&type_name)
-    {
-        return Ok(Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from(format!(
-                "Type {} does not exist in registry",
-                type_name
-            )))?);
-    }
+        .protect(&type_name, &mut def.config, None)
+        .map_err(|err| EndpointError::BadRequest(err.to_string()))?;
+
+    // Error early if the component type is not known or rejects the config
+    controller_lock
+        .validate_component(&def)
+        .map_err(|err| EndpointError::BadRequest(err.to_string()))?;
+
+    let mut graph_lock: RwLockWriteGuard<CascadeGraph> =
+        controller_lock.graph_definition.write().await;
 
     // Create a node for the component definition in the graph
-    let node_idx: NodeIndex = controller_lock
-        .graph_definition
-        .write()
-        .await
-        .graph_internal
-        .add_node(def);
+    let node_idx: NodeIndex = graph_lock.graph_internal.add_node(def.clone());
+    let change: AuditChange = AuditChange::created(
+        vec![def.id.clone()],
+        &controller_lock.component_registry.mask(&def),
+    );
 
-    let message: String = format!(
+    controller_lock
+        .events
+        .publish(CascadeEvent::ComponentCreated {
+            idx: node_idx.index(),
+            definition: def,
+        });
+
+    info!(
         "Successfully created instance of {} at idx {}",
         type_name,
         node_idx.index()
     );
 
-    info!("{}", message);
-
-    Ok(Response::builder()
-        .status(StatusCode::CREATED)
-        .body(Body::from(message))?)
+    audited(
+        create_json_response(
+            StatusCode::CREATED,
+            &summarise_node(&controller_lock, &graph_lock, node_idx),
+        ),
+        change,
+    )
 }
 
 /// Create a connection in the graph from a JSON request
 /// This will fail if either:
 ///     The JSON is malformed or doesn't match ConnectionDefinition
+///     The connection can't hold any items
 ///     The nodes referenced by the connection don't exist
 pub async fn create_connection(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
     let def: ConnectionDefinition = deserialise_body(request).await?;
+    def.validate()
+        .map_err(|err| EndpointError::BadRequest(err.to_string()))?;
 
     let from: NodeIndex = NodeIndex::new(def.source);
     let to: NodeIndex = NodeIndex::new(def.target);
 
     let controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
-    let graph_internal: &mut GraphInternal = &mut controller_lock
-        .graph_definition
-        .write()
-        .await
-        .graph_internal;
+    let mut graph_lock: RwLockWriteGuard<CascadeGraph> =
+        controller_lock.graph_definition.write().await;
 
     // Check whether the nodes from the definition exist in the graph
+    if let Some(missing) = [from, to]
+        .into_iter()
+        .find(|idx| graph_lock.get_component_for_node(*idx).is_none())
+    {
+        return Err(EndpointError::BadRequest(format!(
+            "No node at idx {}",
+            missing.index()
+        )));
+    }
 
     // Add the edge between two defined nodes
-    let index: EdgeIndex = graph_internal.add_edge(from, to, def);
+    let index: EdgeIndex = graph_lock.graph_internal.add_edge(from, to, def.clone());
+
+    // The connected components are affected as well
+    let change: AuditChange = AuditChange::created(
+        vec![
+            def.id.clone(),
+            graph_lock.graph_internal[from].id.clone(),
+            graph_lock.graph_internal[to].id.clone(),
+        ],
+        &def,
+    );
+
+    controller_lock
+        .events
+        .publish(CascadeEvent::ConnectionCreated {
+            idx: index.index(),
+            definition: def,
+        });
 
-    let message: String = format!(
+    info!(
         "Created connection idx {} between {} and {}",
         index.index(),
         from.index(),
         to.index()
     );
 
-    info!("{}", message);
+    audited(
+        create_json_response(
+            StatusCode::CREATED,
+            &summarise_connection(&controller_lock, &graph_lock, index).await,
+        ),
+        change,
+    )
+}
+
+// Flag given in the query, false if not given
+fn bool_param(request: &Request<Body>, name: &str) -> Result<bool, EndpointError> {
+    parse_query_params(request)
+        .get(name)
+        .map(|value| bool::from_str(value))
+        .transpose()
+        .map_err(|_| {
+            EndpointError::BadRequest(format!("Query parameter {} was not true or false", name))
+        })
+        .map(|value| value.unwrap_or(false))
+}
 
-    Ok(Response::builder()
-        .status(StatusCode::CREATED)
-        .body(Body::from(message))?)
+#[derive(Serialize)]
+struct NodeUpdateSummary {
+    component: NodeSummary,
+    // Only the values which differ are listed
+    changes: Vec<FieldChange>,
+    restarted: bool,
 }
 
-/// Remove a component from the graph from a JSON request
+/// Update the name, schedule or config of a component from the id in the path and a JSON request
+/// Running components are only updated if the restart query parameter is true
 /// This will fail if either:
+///     The JSON is malformed or doesn't match ComponentUpdate
 ///     The component doesn't exist
-///     There are connections attached to the node
-pub async fn remove_component(
+///     The config doesn't match what the component type expects
+///     The component is running and isn't to be restarted
+pub async fn update_component(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let node_idx: NodeIndex = NodeIndex::new(get_idx_query_parameter(request)?);
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
 
-    let controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
-    let mut graph_lock: RwLockWriteGuard<CascadeGraph> =
-        controller_lock.graph_definition.write().await;
+    let restart: bool = bool_param(&request, RESTART_PARAM)?;
 
-    // Error if component is still running
-    if controller_lock.executions.contains_key(&node_idx) {
-        return Err(EndpointError::BadRequest(format!(
-            "Component at idx {} is still running",
-            node_idx.index()
-        )));
-    }
+    let update: ComponentUpdate = deserialise_body(request).await?;
 
-    match graph_lock.graph_internal.remove_node(node_idx) {
-        None => Err(EndpointError::BadRequest(format!(
-            "No node at idx {}",
-            node_idx.index()
-        ))),
-        Some(_) => {
-            let message: String = format!("Removed node at idx {}", node_idx.index());
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let node_idx: NodeIndex = component_index(&controller_lock, &id).await?;
+
+    let result: ComponentUpdateResult = controller_lock
+        .update_component(node_idx, update, restart)
+        .await
+        .map_err(|err| match err {
+            UpdateComponentError::InvalidNodeIndex(_) => EndpointError::NotFound(err.to_string()),
+            UpdateComponentError::ComponentRunning(_) => EndpointError::Conflict(format!(
+                "Component {} is running, set {}=true to restart it",
+                id, RESTART_PARAM
+            )),
+            UpdateComponentError::MissingComponent(_) | UpdateComponentError::InvalidConfig(_) => {
+                EndpointError::BadRequest(err.to_string())
+            }
+            UpdateComponentError::FailedToStop | UpdateComponentError::FailedToRestart(_) => {
+                EndpointError::InternalServerError(err.to_string())
+            }
+        })?;
+
+    info!(
+        "Updated {} at idx {} with {} changes",
+        id,
+        node_idx.index(),
+        result.changes.len()
+    );
+
+    let change: AuditChange = AuditChange::updated(
+        &controller_lock.component_registry.mask(&result.before),
+        &controller_lock.component_registry.mask(&result.after),
+    );
+    let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
+
+    audited(
+        create_json_body(&NodeUpdateSummary {
+            component: summarise_node(&controller_lock, &graph_lock, node_idx),
+            changes: result.changes,
+            restarted: result.restarted,
+        }),
+        change,
+    )
+}
+
+#[derive(Serialize)]
+struct ConnectionUpdateSummary {
+    connection: ConnectionSummary,
+    // Only the values which differ are listed
+    changes: Vec<FieldChange>,
+    // Indices of components restarted to pick up the update
+    restarted: Vec<usize>,
+}
+
+/// Update the settings or target of a connection from the id in the path and a JSON request
+/// Messages already queued on the connection are kept
+/// Running components are only rewired if the restart query parameter is true
+/// This will fail if either:
+///     The JSON is malformed or doesn't match ConnectionUpdate
+///     The connection or new target doesn't exist
+///     The updated connection can't hold any items
+///     Components needing to be rewired are running and aren't to be restarted
+pub async fn update_connection(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+    let restart: bool = bool_param(&request, RESTART_PARAM)?;
 
-            info!("{}", message);
+    let update: ConnectionUpdate = deserialise_body(request).await?;
 
-            Ok(Response::builder()
-                .status(StatusCode::ACCEPTED)
-                .body(Body::from(message))?)
-        }
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let edge_idx: EdgeIndex = connection_index(&controller_lock, &id).await?;
+
+    let result: ConnectionUpdateResult = controller_lock
+        .update_connection(edge_idx, update, restart)
+        .await
+        .map_err(|err| match err {
+            UpdateConnectionError::InvalidEdgeIndex(_) => EndpointError::NotFound(err.to_string()),
+            UpdateConnectionError::InvalidTarget(_)
+            | UpdateConnectionError::InvalidConnection(_)
+            | UpdateConnectionError::ConflictingDistribution(_) => {
+                EndpointError::BadRequest(err.to_string())
+            }
+            UpdateConnectionError::ComponentsRunning(_) => EndpointError::Conflict(format!(
+                "{}, set {}=true to restart them",
+                err, RESTART_PARAM
+            )),
+            UpdateConnectionError::FailedToStop | UpdateConnectionError::FailedToRestart(_) => {
+                EndpointError::InternalServerError(err.to_string())
+            }
+        })?;
+
+    info!(
+        "Updated connection {} at idx {} with {} changes",
+        id,
+        result.edge_idx.index(),
+        result.changes.len()
+    );
+
+    let mut change: AuditChange = AuditChange::updated(&result.before, &result.after);
+    let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
+
+    // Components the connection was redirected from or to are affected as well
+    if result.before.target != result.after.target {
+        change.targets = [result.before.target, result.after.target]
+            .into_iter()
+            .filter_map(|idx| graph_lock.get_component_for_node(NodeIndex::new(idx)))
+            .map(|def| def.id.clone())
+            .collect();
     }
+
+    audited(
+        create_json_body(&ConnectionUpdateSummary {
+            connection: summarise_connection(&controller_lock, &graph_lock, result.edge_idx).await,
+            changes: result.changes,
+            restarted: result.restarted,
+        }),
+        change,
+    )
 }
 
-/// Remove a connection in the graph from a JSON request
+/// Remove a component from the graph from the id in the path
+/// Any connections to the component are removed with it
+/// This will fail if either:
+///     The component doesn't exist
+///     The component is still running
+pub async fn remove_component(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let node_idx: NodeIndex = component_index(&controller_lock, &id).await?;
+
+    let definition: ComponentDefinition = controller_lock
+        .component_registry
+        .mask(&controller_lock.graph_definition.read().await.graph_internal[node_idx]);
+
+    let connection_ids: Vec<String> = controller_lock
+        .remove_component(node_idx)
+        .await
+        .map_err(|err| match err {
+            RemoveComponentError::InvalidNodeIndex(_) => EndpointError::NotFound(err.to_string()),
+            RemoveComponentError::ComponentRunning(_) => {
+                EndpointError::Conflict(format!("Component {} is still running", id))
+            }
+        })?;
+
+    info!("Removed node {} at idx {}", id, node_idx.index());
+
+    audited(
+        create_empty_response(),
+        AuditChange::removed(connection_ids, &definition),
+    )
+}
+
+/// Remove a connection from the graph from the id in the path
+/// Any messages still queued on it are dropped
 /// This will fail if either:
 ///     The connection does not exist
 ///     There are components attached to it still running
@@ -183,36 +482,150 @@ pub async fn remove_connection(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let edge_idx: EdgeIndex = EdgeIndex::new(get_idx_query_parameter(request)?);
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
 
-    let controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
-    let mut graph_lock: RwLockWriteGuard<CascadeGraph> =
-        controller_lock.graph_definition.write().await;
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let edge_idx: EdgeIndex = connection_index(&controller_lock, &id).await?;
 
-    let (node_source, node_dest): (NodeIndex, NodeIndex) = graph_lock
-        .graph_internal
-        .edge_endpoints(edge_idx)
-        .ok_or(EndpointError::BadRequest(format!(
-            "No connection at idx {}",
-            edge_idx.index()
-        )))?;
-
-    if controller_lock.executions.contains_key(&node_source)
-        || controller_lock.executions.contains_key(&node_dest)
-    {
-        return Err(EndpointError::BadRequest(
-            "Connected node is still running".to_string(),
-        ));
+    let definition: ConnectionDefinition = controller_lock
+        .remove_connection(edge_idx)
+        .await
+        .map_err(|err| match err {
+            RemoveConnectionError::InvalidEdgeIndex(_) => {
+                EndpointError::NotFound(err.to_string())
+            }
+            RemoveConnectionError::ConnectionRunning(_) => {
+                EndpointError::Conflict("Connected node is still running".to_string())
+            }
+        })?;
+
+    info!("Removed connection {} at idx {}", id, edge_idx.index());
+
+    audited(
+        create_empty_response(),
+        AuditChange::removed(vec![], &definition),
+    )
+}
+
+/// Export the graph as a flow which can be imported into another instance
+pub async fn export_flow(
+    controller: Arc<RwLock<CascadeController>>,
+    _: Request<Body>,
+) -> EndpointResult {
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let flow: FlowDefinition = controller_lock
+        .graph_definition
+        .read()
+        .await
+        .export_flow();
+
+    create_json_body(&controller_lock.component_registry.mask_flow(&flow))
+}
+
+#[derive(Serialize)]
+struct ImportResult {
+    components: usize,
+    connections: usize,
+}
+
+/// Add every component and connection of a flow from a JSON request to the graph
+/// Components and connections are given new ids
+/// This will fail if either:
+///     The JSON is malformed or doesn't match FlowDefinition
+///     Any component type does not exist in the registry
+///     A connection refers to a component outside of the flow
+pub async fn import_flow(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let flow: FlowDefinition = deserialise_body(request).await?;
+    let result: ImportResult = ImportResult {
+        components: flow.components.len(),
+        connections: flow.connections.len(),
+    };
+
+    let ids: Vec<String> = flow
+        .components
+        .iter()
+        .map(|def| def.id.clone())
+        .chain(flow.connections.iter().map(|def| def.id.clone()))
+        .collect();
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+
+    let change: AuditChange =
+        AuditChange::created(ids, &controller_lock.component_registry.mask_flow(&flow));
+
+    controller_lock
+        .load_flow(flow)
+        .await
+        .map_err(|err| EndpointError::BadRequest(err.to_string()))?;
+
+    info!(
+        "Imported flow of {} components and {} connections",
+        result.components, result.connections
+    );
+
+    audited(create_json_response(StatusCode::CREATED, &result), change)
+}
+
+/// Replace the graph with a flow from a JSON request, changing only what differs
+/// Components and connections are matched to the graph by id, so an exported flow can be edited
+/// Running components affected by the difference are restarted, nothing is changed for a dry run
+/// This will fail if either:
+///     The JSON is malformed or doesn't match FlowDefinition
+///     Any component type does not exist in the registry or rejects its config
+///     A connection refers to a component outside of the flow
+///     An id is used more than once in the flow
+pub async fn reload_flow(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let dry_run: bool = bool_param(&request, DRY_RUN_PARAM)?;
+
+    let flow: FlowDefinition = FlowDefinition::with_ids(deserialise_body(request).await?)?;
+
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let before: FlowDefinition = controller_lock
+        .graph_definition
+        .read()
+        .await
+        .export_flow();
+
+    let diff: FlowDiff = controller_lock
+        .reload_flow(flow, dry_run)
+        .await
+        .map_err(|err| match err {
+            ReloadFlowError::InvalidFlow(_) => EndpointError::BadRequest(err.to_string()),
+            ReloadFlowError::FailedToStop | ReloadFlowError::FailedToRestart(_) => {
+                EndpointError::InternalServerError(err.to_string())
+            }
+        })?;
+
+    if dry_run || diff.is_empty() {
+        return create_json_body(&diff);
     }
 
-    // We just asserted that the edge exists
-    graph_lock.graph_internal.remove_edge(edge_idx).unwrap();
+    info!(
+        "Reloaded flow restarting {} running components",
+        diff.restarted.len()
+    );
 
-    let message: String = format!("Removed connection at idx {}", edge_idx.index());
+    let after: FlowDefinition = controller_lock
+        .graph_definition
+        .read()
+        .await
+        .export_flow();
 
-    info!("{}", message);
+    // Everything added or removed is affected
+    let mut change: AuditChange = AuditChange::updated(
+        &controller_lock.component_registry.mask_flow(&before),
+        &controller_lock.component_registry.mask_flow(&after),
+    );
+    change.targets = [&diff.components, &diff.connections]
+        .into_iter()
+        .flat_map(|changes| changes.added.iter().chain(&changes.removed).chain(&changes.changed))
+        .cloned()
+        .collect();
 
-    Ok(Response::builder()
-        .status(StatusCode::ACCEPTED)
-        .body(Body::from(message))?)
+    audited(create_json_body(&diff), change)
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use hyper::{Body, Request};
@@ -5,38 +7,133 @@ use petgraph::graph::EdgeIndex;
 use serde::Serialize;
 use tokio::sync::{RwLock, RwLockReadGuard};
 
+use cascade_api::connection::ConnectionStats;
+use cascade_api::connection::definition::ConnectionDefinition;
+use cascade_api::message::content::Content;
+use cascade_api::message::Message;
 use cascade_core::controller::{CascadeController, ConnectionsMap};
+use cascade_core::graph::CascadeGraph;
 
-use crate::endpoint::{create_json_body, EndpointError, EndpointResult, get_idx_query_parameter};
+use crate::endpoint::{
+    connection_index, create_json_body, EndpointError, EndpointResult, find_connection,
+    get_path_parameter, ID_PARAM, parse_query_params,
+};
 
-#[derive(Serialize)]
-struct ConnectionMetric {
-    name: String,
-    count: usize,
-    max_items: usize,
+pub(crate) const LIMIT_PARAM: &str = "limit";
+
+// Messages returned by a peek if no limit is given
+const DEFAULT_PEEK_LIMIT: usize = 10;
+
+// Stats of a connection, which are empty until the connection is initialised
+pub(crate) async fn connection_stats(
+    controller: &CascadeController,
+    edge_idx: EdgeIndex,
+    def: &ConnectionDefinition,
+) -> ConnectionStats {
+    match controller.connections.read().await.get(&edge_idx) {
+        Some(connection) => connection.stats(),
+        None => ConnectionStats {
+            name: def.name.clone(),
+            count: 0,
+            max_items: def.max_items,
+            expired: 0,
+        },
+    }
 }
 
-/// Describe the connection state
+/// Describe the queue of a connection from the id in the path
+/// This will fail if:
+///     The connection does not exist in the graph
 pub async fn stat_connection(
     controller: Arc<RwLock<CascadeController>>,
     request: Request<Body>,
 ) -> EndpointResult {
-    let edge_idx: EdgeIndex = EdgeIndex::new(get_idx_query_parameter(request)?);
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
 
     let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let graph_lock: RwLockReadGuard<CascadeGraph> = controller_lock.graph_definition.read().await;
+
+    let edge_idx: EdgeIndex = find_connection(&graph_lock, &id)?;
+
+    create_json_body(
+        &connection_stats(
+            &controller_lock,
+            edge_idx,
+            &graph_lock.graph_internal[edge_idx],
+        )
+        .await,
+    )
+}
+
+#[derive(Serialize)]
+struct MessageSummary {
+    id: String,
+    created_nanos: u128,
+    properties: HashMap<String, String>,
+    // Description of each content reference rather than the content itself
+    content: HashMap<String, String>,
+    trace_id: String,
+}
+
+impl From<Message> for MessageSummary {
+    fn from(message: Message) -> Self {
+        MessageSummary {
+            id: message.id,
+            created_nanos: message.created_nanos,
+            properties: message.properties,
+            content: message
+                .content
+                .iter()
+                .map(|(reference, content)| (reference.clone(), describe_content(content)))
+                .collect(),
+            trace_id: message.trace.trace_id,
+        }
+    }
+}
+
+fn describe_content(content: &Content) -> String {
+    match content {
+        Content::Memory { buffer } => format!("Memory ({} bytes)", buffer.len()),
+        Content::Disk => String::from("Disk"),
+        Content::Http { url } => format!("Http ({})", url),
+        Content::Local => String::from("Local"),
+    }
+}
+
+/// List the next messages to be received from a connection without removing them
+/// The amount of messages is set with an optional limit query parameter
+/// This will fail if either:
+///     The limit query parameter is NaN
+///     The connection does not exist in the graph
+pub async fn peek_connection(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let id: String = get_path_parameter(&request, ID_PARAM)?;
+
+    let limit: usize = parse_query_params(&request)
+        .get(LIMIT_PARAM)
+        .map(|limit| usize::from_str(limit))
+        .transpose()
+        .map_err(|_| {
+            EndpointError::BadRequest(format!("Query parameter {} was not a number", LIMIT_PARAM))
+        })?
+        .unwrap_or(DEFAULT_PEEK_LIMIT);
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let edge_idx: EdgeIndex = connection_index(&controller_lock, &id).await?;
 
     let connections_lock: RwLockReadGuard<ConnectionsMap> =
         controller_lock.connections.read().await;
 
-    match connections_lock.get(&edge_idx) {
-        None => Err(EndpointError::BadRequest(format!(
-            "No edge found at idx {}",
-            edge_idx.index()
-        ))),
-        Some(connection) => Ok(create_json_body(&ConnectionMetric {
-            name: connection.name.clone(),
-            count: connection.tx.len(),
-            max_items: connection.max_items,
-        })?),
-    }
+    // Connections are created lazily so there may be nothing queued yet
+    let messages: Vec<MessageSummary> = connections_lock
+        .get(&edge_idx)
+        .map(|connection| connection.peek(limit))
+        .unwrap_or_default()
+        .into_iter()
+        .map(MessageSummary::from)
+        .collect();
+
+    create_json_body(&messages)
 }
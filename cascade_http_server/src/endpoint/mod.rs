@@ -1,20 +1,39 @@
 use std::collections::HashMap;
-use std::str::FromStr;
 
 use hyper::{Body, header, http, Request, Response, StatusCode};
 use hyper::body::{Buf, Bytes};
+use petgraph::graph::{EdgeIndex, NodeIndex};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::Error;
 
+use cascade_core::controller::CascadeController;
+use cascade_core::graph::CascadeGraph;
+
+use crate::route::PathParams;
+
+pub(crate) mod audit;
+pub(crate) mod bulletin;
 pub(crate) mod control;
+pub(crate) mod events;
 pub(crate) mod graph;
 pub(crate) mod registry;
 pub(crate) mod metrics;
+pub(crate) mod openapi;
+pub(crate) mod parameter;
+pub(crate) mod provenance;
 
 pub enum EndpointError {
     HyperError(hyper::Error),
     BadRequest(String),
+    // No credentials were given or they weren't valid
+    Unauthorized(String),
+    // The caller's role doesn't permit the operation
+    Forbidden(String),
+    NotFound(String),
+    MethodNotAllowed(String),
+    // The request conflicts with the current state, such as a running component
+    Conflict(String),
     InternalServerError(String),
 }
 
@@ -36,13 +55,71 @@ impl From<Error> for EndpointError {
     }
 }
 
+/// Body returned alongside every error status
+#[derive(Serialize)]
+pub struct ErrorBody {
+    // Stable identifier for the kind of error
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl EndpointError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            EndpointError::HyperError(_) | EndpointError::InternalServerError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            EndpointError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            EndpointError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            EndpointError::Forbidden(_) => StatusCode::FORBIDDEN,
+            EndpointError::NotFound(_) => StatusCode::NOT_FOUND,
+            EndpointError::MethodNotAllowed(_) => StatusCode::METHOD_NOT_ALLOWED,
+            EndpointError::Conflict(_) => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        let (code, message): (&'static str, String) = match self {
+            EndpointError::HyperError(err) => ("internal_error", err.to_string()),
+            EndpointError::BadRequest(message) => ("bad_request", message.clone()),
+            EndpointError::Unauthorized(message) => ("unauthorized", message.clone()),
+            EndpointError::Forbidden(message) => ("forbidden", message.clone()),
+            EndpointError::NotFound(message) => ("not_found", message.clone()),
+            EndpointError::MethodNotAllowed(message) => ("method_not_allowed", message.clone()),
+            EndpointError::Conflict(message) => ("conflict", message.clone()),
+            EndpointError::InternalServerError(message) => ("internal_error", message.clone()),
+        };
+
+        ErrorBody { code, message }
+    }
+}
+
 pub type EndpointResult = Result<Response<Body>, EndpointError>;
 
+// Fails if the client disconnects or the body is malformed part way through
+async fn read_body(request: Request<Body>) -> Result<Bytes, EndpointError> {
+    hyper::body::to_bytes(request)
+        .await
+        .map_err(|err| EndpointError::BadRequest(format!("Could not read request body: {}", err)))
+}
+
 pub async fn deserialise_body<T: DeserializeOwned>(
     request: Request<Body>,
 ) -> Result<T, EndpointError> {
-    // This is actually Infallible
-    let whole_body: Bytes = hyper::body::to_bytes(request).await.unwrap();
+    let whole_body: Bytes = read_body(request).await?;
+
+    Ok(serde_json::from_reader(whole_body.reader())?)
+}
+
+// Deserialise a body which is allowed to be empty
+pub async fn deserialise_body_or_default<T: DeserializeOwned + Default>(
+    request: Request<Body>,
+) -> Result<T, EndpointError> {
+    let whole_body: Bytes = read_body(request).await?;
+
+    if whole_body.is_empty() {
+        return Ok(T::default());
+    }
 
     Ok(serde_json::from_reader(whole_body.reader())?)
 }
@@ -51,11 +128,19 @@ const APPLICATION_JSON: &str = "application/json";
 
 // Serialise a value and return it in a JSON response body
 fn create_json_body<T: Serialize>(to_serialise: &T) -> Result<Response<Body>, EndpointError> {
+    create_json_response(StatusCode::OK, to_serialise)
+}
+
+// Serialise a value and return it in a JSON response body with the given status
+pub(crate) fn create_json_response<T: Serialize>(
+    status: StatusCode,
+    to_serialise: &T,
+) -> Result<Response<Body>, EndpointError> {
     match serde_json::to_string_pretty(to_serialise) {
         Ok(serialised) => {
             // Return serialised response in JSON body
             Ok(Response::builder()
-                .status(StatusCode::OK)
+                .status(status)
                 .header(header::CONTENT_TYPE, APPLICATION_JSON)
                 .body(Body::from(serialised))?)
         }
@@ -63,7 +148,13 @@ fn create_json_body<T: Serialize>(to_serialise: &T) -> Result<Response<Body>, En
     }
 }
 
-fn parse_query_params(request: Request<Body>) -> HashMap<String, String> {
+fn create_empty_response() -> EndpointResult {
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Body::empty())?)
+}
+
+fn parse_query_params(request: &Request<Body>) -> HashMap<String, String> {
     request
         .uri()
         .query()
@@ -74,24 +165,50 @@ fn parse_query_params(request: Request<Body>) -> HashMap<String, String> {
                 .collect()
         })
         // Default to empty map instead of failure
-        .unwrap_or_else(HashMap::new)
+        .unwrap_or_default()
 }
 
-pub(crate) const INDEX_PARAM: &str = "idx";
+pub(crate) const ID_PARAM: &str = "id";
 
-fn get_idx_query_parameter(request: Request<Body>) -> Result<usize, EndpointError> {
-    let params: HashMap<String, String> = parse_query_params(request);
-
-    // Error if the param is missing or not an integer
-    let idx_str: &String = params
-        .get(INDEX_PARAM)
+// Parameter captured from the path by the router
+fn get_path_parameter(request: &Request<Body>, name: &str) -> Result<String, EndpointError> {
+    request
+        .extensions()
+        .get::<PathParams>()
+        .and_then(|params| params.0.get(name))
+        .cloned()
         .ok_or(EndpointError::BadRequest(format!(
-            "Query parameter {} was missing",
-            INDEX_PARAM
-        )))?;
-    let idx: usize = usize::from_str(idx_str).map_err(|_| {
-        EndpointError::BadRequest(format!("Query parameter {} was not a number", INDEX_PARAM))
-    })?;
-
-    Ok(idx)
+            "Path parameter {} was missing",
+            name
+        )))
+}
+
+// Index of the component named by the id in the path
+fn find_component(graph: &CascadeGraph, id: &str) -> Result<NodeIndex, EndpointError> {
+    graph
+        .find_node(id)
+        .ok_or(EndpointError::NotFound(format!("No component with id {}", id)))
+}
+
+// Index of the connection named by the id in the path
+fn find_connection(graph: &CascadeGraph, id: &str) -> Result<EdgeIndex, EndpointError> {
+    graph
+        .find_edge(id)
+        .ok_or(EndpointError::NotFound(format!("No connection with id {}", id)))
+}
+
+// Resolve a component id without keeping the graph locked
+async fn component_index(
+    controller: &CascadeController,
+    id: &str,
+) -> Result<NodeIndex, EndpointError> {
+    find_component(&*controller.graph_definition.read().await, id)
+}
+
+// Resolve a connection id without keeping the graph locked
+async fn connection_index(
+    controller: &CascadeController,
+    id: &str,
+) -> Result<EdgeIndex, EndpointError> {
+    find_connection(&*controller.graph_definition.read().await, id)
 }
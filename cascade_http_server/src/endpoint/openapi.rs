@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use hyper::{Body, Method, Request};
+use serde_json::{json, Map, Value};
+use tokio::sync::RwLock;
+
+use cascade_core::controller::CascadeController;
+
+use crate::endpoint::{create_json_body, EndpointResult};
+use crate::route::{Route, routes};
+
+const OPENAPI_VERSION: &str = "3.0.3";
+const ERROR_SCHEMA: &str = "Error";
+const SECURITY_SCHEME: &str = "bearerAuth";
+
+/// Describe every route as an OpenAPI document
+pub async fn openapi_document(_: Arc<RwLock<CascadeController>>, _: Request<Body>) -> EndpointResult {
+    create_json_body(&document(&routes()))
+}
+
+pub fn document(routes: &[Route]) -> Value {
+    let mut paths: Map<String, Value> = Map::new();
+
+    for route in routes {
+        let operations: &mut Value = paths
+            .entry(route.full_path())
+            .or_insert_with(|| json!({}));
+
+        operations[route.method.as_str().to_lowercase()] = operation(route);
+    }
+
+    // Bodies are described by name, with the error body given in full
+    let mut schemas: Map<String, Value> = routes
+        .iter()
+        .filter_map(|route| route.request_body)
+        .collect::<BTreeSet<&str>>()
+        .into_iter()
+        .map(|name| (name.to_string(), json!({ "type": "object", "title": name })))
+        .collect();
+
+    schemas.insert(
+        ERROR_SCHEMA.to_string(),
+        json!({
+            "type": "object",
+            "required": ["code", "message"],
+            "properties": {
+                "code": { "type": "string" },
+                "message": { "type": "string" },
+            },
+        }),
+    );
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": {
+            "title": "Cascade",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                SECURITY_SCHEME: {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Static API token or HS256 signed JWT",
+                },
+            },
+        },
+        // Only enforced when the instance has authentication configured
+        "security": [{ SECURITY_SCHEME: [] }],
+    })
+}
+
+fn operation(route: &Route) -> Value {
+    let path_params = route.path_params().map(|name| {
+        json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string" },
+        })
+    });
+    let query_params = route.query.iter().map(|name| {
+        json!({
+            "name": name,
+            "in": "query",
+            "required": false,
+            "schema": { "type": "string" },
+        })
+    });
+
+    let mut operation: Value = json!({
+        "operationId": route.operation_id,
+        "tags": [route.tag],
+        "summary": route.summary,
+        "x-cascade-role": route.role,
+        "parameters": path_params.chain(query_params).collect::<Vec<Value>>(),
+        "responses": {
+            route.status.as_str(): {
+                "description": route.status.canonical_reason().unwrap_or_default(),
+            },
+            "default": {
+                "description": "Error",
+                "content": {
+                    "application/json": {
+                        "schema": { "$ref": schema_ref(ERROR_SCHEMA) },
+                    },
+                },
+            },
+        },
+    });
+
+    if let Some(name) = route.request_body {
+        operation["requestBody"] = json!({
+            // Bodies of deletes only filter what is removed so can be left out
+            "required": route.method != Method::DELETE,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": schema_ref(name) },
+                },
+            },
+        });
+    }
+
+    operation
+}
+
+fn schema_ref(name: &str) -> String {
+    format!("#/components/schemas/{}", name)
+}
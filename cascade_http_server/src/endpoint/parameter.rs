@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use hyper::{Body, Request};
+use log::info;
+use serde::Serialize;
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use cascade_core::controller::CascadeController;
+use cascade_core::controller::error::ParameterContextError;
+use cascade_core::parameter::ParameterContext;
+
+use crate::endpoint::{
+    create_empty_response, create_json_body, deserialise_body, EndpointError, EndpointResult,
+    get_path_parameter, ID_PARAM,
+};
+use crate::endpoint::audit::{AuditChange, audited};
+
+// Contexts are always returned with sensitive values masked
+#[derive(Serialize)]
+struct ContextSummary {
+    name: String,
+    #[serde(flatten)]
+    context: ParameterContext,
+}
+
+#[derive(Serialize)]
+struct ContextUpdateSummary {
+    #[serde(flatten)]
+    context: ContextSummary,
+    // Ids of components restarted to pick up changed parameters
+    restarted: Vec<String>,
+}
+
+fn summarise_context(name: &str, context: &ParameterContext) -> ContextSummary {
+    ContextSummary {
+        name: name.to_string(),
+        context: context.masked(),
+    }
+}
+
+fn context_error(err: ParameterContextError) -> EndpointError {
+    match err {
+        ParameterContextError::MissingContext(_) => EndpointError::NotFound(err.to_string()),
+        ParameterContextError::ContextInUse(_) => EndpointError::Conflict(err.to_string()),
+        ParameterContextError::InvalidComponent(..) | ParameterContextError::MaskedValue(_) => {
+            EndpointError::BadRequest(err.to_string())
+        }
+        ParameterContextError::FailedToStop | ParameterContextError::FailedToRestart(_) => {
+            EndpointError::InternalServerError(err.to_string())
+        }
+    }
+}
+
+/// List every parameter context with sensitive values masked
+pub async fn list_parameter_contexts(
+    controller: Arc<RwLock<CascadeController>>,
+    _: Request<Body>,
+) -> EndpointResult {
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+
+    let contexts: Vec<ContextSummary> = controller_lock
+        .parameter_contexts
+        .iter()
+        .map(|(name, context)| summarise_context(name, context))
+        .collect();
+
+    create_json_body(&contexts)
+}
+
+/// Describe a single parameter context from the name in the path
+/// This will fail if:
+///     The context does not exist
+pub async fn get_parameter_context(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let name: String = get_path_parameter(&request, ID_PARAM)?;
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let context: &ParameterContext = controller_lock
+        .parameter_contexts
+        .get(&name)
+        .ok_or(EndpointError::NotFound(format!(
+            "No parameter context named {}",
+            name
+        )))?;
+
+    create_json_body(&summarise_context(&name, context))
+}
+
+/// Create or replace the parameter context named in the path from a JSON request
+/// Sensitive values given back masked keep their existing value
+/// Running components referencing a changed parameter are restarted
+/// This will fail if either:
+///     The JSON is malformed or doesn't match ParameterContext
+///     A sensitive value is masked but had no value to keep
+///     A restarted component fails to start with the new values
+pub async fn set_parameter_context(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let name: String = get_path_parameter(&request, ID_PARAM)?;
+    let context: ParameterContext = deserialise_body(request).await?;
+
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+
+    let masked: ParameterContext = context.masked();
+    let (previous, restarted): (Option<ParameterContext>, Vec<String>) = controller_lock
+        .set_parameter_context(&name, context)
+        .await
+        .map_err(context_error)?;
+
+    info!(
+        "Set parameter context {}, restarting {} components",
+        name,
+        restarted.len()
+    );
+
+    // Sensitive values are never recorded
+    let change: AuditChange = match previous {
+        Some(previous) => AuditChange::updated(&previous.masked(), &masked),
+        None => AuditChange::created(vec![], &masked),
+    };
+
+    audited(
+        create_json_body(&ContextUpdateSummary {
+            context: ContextSummary {
+                name,
+                context: masked,
+            },
+            restarted,
+        }),
+        change,
+    )
+}
+
+/// Remove the parameter context named in the path
+/// This will fail if either:
+///     The context does not exist
+///     Any component still refers to the context
+pub async fn remove_parameter_context(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let name: String = get_path_parameter(&request, ID_PARAM)?;
+
+    let mut controller_lock: RwLockWriteGuard<CascadeController> = controller.write().await;
+    let removed: ParameterContext = controller_lock
+        .remove_parameter_context(&name)
+        .await
+        .map_err(context_error)?;
+
+    audited(
+        create_empty_response(),
+        AuditChange::removed(vec![], &removed.masked()),
+    )
+}
@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use hyper::{Body, Request};
+use tokio::sync::{RwLock, RwLockReadGuard};
+
+use cascade_core::controller::CascadeController;
+use cascade_core::provenance::ProvenanceEvent;
+
+use crate::endpoint::{create_json_body, EndpointResult, parse_query_params};
+
+pub(crate) const MESSAGE_ID_PARAM: &str = "message_id";
+
+/// List recorded provenance events
+/// Optionally restricted to a single message with a message_id query parameter
+pub async fn list_provenance(
+    controller: Arc<RwLock<CascadeController>>,
+    request: Request<Body>,
+) -> EndpointResult {
+    let params: HashMap<String, String> = parse_query_params(&request);
+
+    let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
+    let events: Vec<ProvenanceEvent> = controller_lock
+        .provenance
+        .list_events(params.get(MESSAGE_ID_PARAM).map(String::as_str));
+
+    create_json_body(&events)
+}
@@ -15,5 +15,5 @@ pub async fn list_available_components(
     let controller_lock: RwLockReadGuard<CascadeController> = controller.read().await;
     let component_types: Vec<&str> = controller_lock.component_registry.list_component_types();
 
-    Ok(create_json_body(&component_types)?)
+    create_json_body(&component_types)
 }
@@ -0,0 +1,307 @@
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use toml_edit::{DocumentMut, Item};
+
+use crate::config::ConfigError;
+
+/// String value in the config of a component in a flow file to replace
+pub struct FlowEdit {
+    // Index of the component in the flow
+    pub component: usize,
+    pub property: String,
+    pub value: String,
+}
+
+/// Replace values in a flow file, leaving the rest of it as it was written
+/// The file is replaced in one step, so a failure part way leaves the original in place
+/// This will fail if either:
+///     The file can't be read or parsed
+///     A value to replace isn't in the file
+///     The file can't be written
+pub fn edit_flow_file(path: &Path, edits: &[FlowEdit]) -> Result<(), ConfigError> {
+    let contents: String =
+        fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+
+    let edited: Result<String, String> = match path.extension() {
+        Some(extension) if extension == "toml" => edit_toml(&contents, edits),
+        _ => edit_json(&contents, edits),
+    };
+    let edited: String = edited.map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+
+    replace_file(path, &edited).map_err(|err| ConfigError::Io(path.to_path_buf(), err))
+}
+
+// Comments and layout are kept by editing the parsed document
+fn edit_toml(contents: &str, edits: &[FlowEdit]) -> Result<String, String> {
+    let mut document: DocumentMut = contents.parse().map_err(|err| format!("{}", err))?;
+
+    for edit in edits {
+        let value: &mut toml_edit::Value =
+            toml_value(&mut document, edit).ok_or(missing(edit))?;
+        let decor: toml_edit::Decor = value.decor().clone();
+
+        *value = toml_edit::Value::from(edit.value.as_str());
+        *value.decor_mut() = decor;
+    }
+
+    Ok(document.to_string())
+}
+
+// Only the text of each replaced value changes, so everything else stays byte for byte
+fn edit_json(contents: &str, edits: &[FlowEdit]) -> Result<String, String> {
+    let mut spans: Vec<(Range<usize>, String)> = edits
+        .iter()
+        .map(|edit| {
+            let path: [JsonKey; 4] = [
+                JsonKey::Field("components"),
+                JsonKey::Index(edit.component),
+                JsonKey::Field("config"),
+                JsonKey::Field(&edit.property),
+            ];
+
+            let span: Range<usize> = JsonScanner::new(contents)
+                .find(&path)?
+                .ok_or(missing(edit))?;
+            let value: String = serde_json::to_string(&edit.value).map_err(|err| err.to_string())?;
+
+            Ok((span, value))
+        })
+        .collect::<Result<Vec<(Range<usize>, String)>, String>>()?;
+
+    // Replaced from the end so earlier spans stay where they are
+    spans.sort_by_key(|(span, _)| std::cmp::Reverse(span.start));
+
+    let mut edited: String = contents.to_string();
+    for (span, value) in spans {
+        edited.replace_range(span, &value);
+    }
+
+    Ok(edited)
+}
+
+// Components may be an array of tables or an array of inline tables
+fn toml_value<'a>(
+    document: &'a mut DocumentMut,
+    edit: &FlowEdit,
+) -> Option<&'a mut toml_edit::Value> {
+    let config: &mut Item = match document.get_mut("components")? {
+        Item::ArrayOfTables(components) => components.get_mut(edit.component)?.get_mut("config")?,
+        Item::Value(toml_edit::Value::Array(components)) => {
+            return components
+                .get_mut(edit.component)?
+                .as_inline_table_mut()?
+                .get_mut("config")?
+                .as_inline_table_mut()?
+                .get_mut(&edit.property);
+        }
+        _ => return None,
+    };
+
+    config
+        .as_table_like_mut()?
+        .get_mut(&edit.property)?
+        .as_value_mut()
+}
+
+fn missing(edit: &FlowEdit) -> String {
+    format!(
+        "No {} in the config of component {}",
+        edit.property, edit.component
+    )
+}
+
+// Write next to the file then rename over it, keeping its permissions
+fn replace_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let file_name: String = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temporary: PathBuf = path.with_file_name(format!(".{}.tmp", file_name));
+
+    let result: std::io::Result<()> = (|| {
+        let mut file: File = File::create(&temporary)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+
+        fs::set_permissions(&temporary, fs::metadata(path)?.permissions())?;
+        fs::rename(&temporary, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+
+    result
+}
+
+enum JsonKey<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+// Finds where a value is in JSON text, which serde_json doesn't keep track of
+struct JsonScanner<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(text: &'a str) -> JsonScanner<'a> {
+        JsonScanner { text, position: 0 }
+    }
+
+    /// Span of the value at the path, None if there's nothing there
+    /// This will fail if the text isn't valid JSON
+    fn find(&mut self, path: &[JsonKey]) -> Result<Option<Range<usize>>, String> {
+        self.skip_whitespace();
+
+        let Some((key, rest)) = path.split_first() else {
+            let start: usize = self.position;
+            self.skip_value()?;
+
+            return Ok(Some(start..self.position));
+        };
+
+        match (key, self.peek()) {
+            (JsonKey::Field(field), Some(b'{')) => {
+                self.position += 1;
+
+                while self.next_entry(b'}')? {
+                    let name: String = self.string()?;
+                    self.expect(b':')?;
+
+                    if name == *field {
+                        return self.find(rest);
+                    }
+
+                    self.skip_whitespace();
+                    self.skip_value()?;
+                }
+
+                Ok(None)
+            }
+            (JsonKey::Index(index), Some(b'[')) => {
+                self.position += 1;
+                let mut current: usize = 0;
+
+                while self.next_entry(b']')? {
+                    if current == *index {
+                        return self.find(rest);
+                    }
+
+                    self.skip_value()?;
+                    current += 1;
+                }
+
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // Move to the next entry of an object or array, false once it has ended
+    fn next_entry(&mut self, end: u8) -> Result<bool, String> {
+        self.skip_whitespace();
+
+        if self.peek() == Some(b',') {
+            self.position += 1;
+            self.skip_whitespace();
+        }
+
+        match self.peek() {
+            Some(next) if next == end => {
+                self.position += 1;
+                Ok(false)
+            }
+            Some(_) => Ok(true),
+            None => Err(self.error("Unexpected end of JSON")),
+        }
+    }
+
+    fn skip_value(&mut self) -> Result<(), String> {
+        match self.peek() {
+            Some(b'"') => self.string().map(|_| ()),
+            Some(open @ (b'{' | b'[')) => {
+                let close: u8 = if open == b'{' { b'}' } else { b']' };
+                self.position += 1;
+
+                while self.next_entry(close)? {
+                    if open == b'{' {
+                        self.string()?;
+                        self.expect(b':')?;
+                        self.skip_whitespace();
+                    }
+
+                    self.skip_value()?;
+                }
+
+                Ok(())
+            }
+            // Numbers, booleans and null run until the next delimiter
+            Some(_) => {
+                let length: usize = self.text[self.position..]
+                    .find(|c: char| c.is_whitespace() || matches!(c, ',' | '}' | ']'))
+                    .unwrap_or(self.text.len() - self.position);
+                self.position += length;
+
+                Ok(())
+            }
+            None => Err(self.error("Unexpected end of JSON")),
+        }
+    }
+
+    // Read a string, decoding any escapes in it
+    fn string(&mut self) -> Result<String, String> {
+        self.skip_whitespace();
+        let start: usize = self.position;
+
+        if self.peek() != Some(b'"') {
+            return Err(self.error("Expected a string"));
+        }
+        self.position += 1;
+
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.position += 2,
+                Some(b'"') => {
+                    self.position += 1;
+                    break;
+                }
+                Some(_) => self.position += 1,
+                None => return Err(self.error("Unterminated string")),
+            }
+        }
+
+        serde_json::from_str(&self.text[start..self.position]).map_err(|err| err.to_string())
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), String> {
+        self.skip_whitespace();
+
+        match self.peek() == Some(expected) {
+            true => {
+                self.position += 1;
+                Ok(())
+            }
+            false => Err(self.error(&format!("Expected {}", expected as char))),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|next| next.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.position).copied()
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.position)
+    }
+}
@@ -1,99 +1,232 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::{Body, header, Method, Request, Response, Server};
+use hyper::server::conn::Http;
 use hyper::service::{make_service_fn, service_fn};
-use log::info;
+use log::{debug, info, warn};
+use tokio::net::TcpListener;
 use tokio::sync::RwLock;
 
+use cascade_core::audit::AuditRecord;
 use cascade_core::controller::CascadeController;
 
-use crate::endpoint::{EndpointError, EndpointResult};
-use crate::endpoint::control::{kill_component, start_component, stop_component};
-use crate::endpoint::graph::{
-    create_component, create_connection, list_graph_connections, list_graph_nodes,
-    remove_component, remove_connection,
-};
-use crate::endpoint::metrics::stat_connection;
-use crate::endpoint::registry::list_available_components;
+use crate::auth::{Authenticator, Principal};
+use crate::endpoint::{create_json_response, EndpointError, EndpointResult};
+use crate::endpoint::audit::complete_record;
+use crate::endpoint::events::publish_queue_stats;
+use crate::route::{PathParams, Route, routes};
+use crate::tls::ReloadingTlsAcceptor;
 
+pub mod auth;
 mod endpoint;
+mod route;
+pub mod tls;
+pub mod trace;
 
-static NOT_FOUND: &[u8] = b"Resource not found";
+pub const DEFAULT_STATS_PERIOD: Duration = Duration::from_secs(5);
 
 pub struct CascadeServer {
     pub addr: SocketAddr,
 
     pub controller: Arc<RwLock<CascadeController>>,
+
+    // How often queue stats are pushed to event subscribers
+    pub stats_period: Duration,
+
+    pub authenticator: Authenticator,
+
+    // Serve HTTPS instead of plain HTTP if set
+    pub tls: Option<Arc<ReloadingTlsAcceptor>>,
 }
 
 async fn router(
     controller: Arc<RwLock<CascadeController>>,
-    req: Request<Body>,
+    routes: Arc<Vec<Route>>,
+    authenticator: Arc<Authenticator>,
+    mut req: Request<Body>,
 ) -> Result<Response<Body>, hyper::Error> {
     let controller: Arc<RwLock<CascadeController>> = Arc::clone(&controller);
 
-    let result: EndpointResult = match (req.method(), req.uri().path()) {
-        // Retrieve information from the component registry
-        (&Method::GET, "/list_available_components") => {
-            list_available_components(controller, req).await
+    // Nothing is revealed about the API to unauthenticated callers
+    let principal: Principal = match authenticator.authenticate(&req) {
+        Ok(principal) => principal,
+        Err(err) => return Ok(error_response(err)),
+    };
+
+    // Methods of the routes matching the path, in case none match the method
+    let mut allowed: Vec<&str> = vec![];
+
+    for route in routes.iter() {
+        let Some(params) = route.matches(req.uri().path()) else {
+            continue;
+        };
+
+        if route.method != req.method() {
+            allowed.push(route.method.as_str());
+            continue;
         }
 
-        // Modify items in the graph
-        (&Method::PUT, "/create_component") => create_component(controller, req).await,
-        (&Method::PUT, "/create_connection") => create_connection(controller, req).await,
-        (&Method::DELETE, "/remove_component") => remove_component(controller, req).await,
-        (&Method::DELETE, "/remove_connection") => remove_connection(controller, req).await,
-
-        // Control of individual components
-        (&Method::GET, "/start_component") => start_component(controller, req).await,
-        (&Method::GET, "/stop_component") => stop_component(controller, req).await,
-        (&Method::GET, "/kill_component") => kill_component(controller, req).await,
-
-        // List the current graph state
-        (&Method::GET, "/list_nodes") => list_graph_nodes(controller, req).await,
-        (&Method::GET, "/list_connections") => list_graph_connections(controller, req).await,
-        (&Method::GET, "/stat_connection") => stat_connection(controller, req).await,
-        // Return 404 not found response.
-        _ => Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(NOT_FOUND.into())
-            .unwrap()),
-    };
+        // Anything which isn't a read is audited, whether or not it succeeds
+        let audit_record: Option<AuditRecord> = (route.method != Method::GET).then(|| {
+            AuditRecord::new(
+                &principal.name,
+                route.operation_id,
+                params.0.values().cloned().collect(),
+            )
+        });
+
+        let result: EndpointResult = if principal.role < route.role {
+            Err(EndpointError::Forbidden(format!(
+                "{} requires the {} role",
+                route.operation_id, route.role
+            )))
+        } else {
+            req.extensions_mut().insert::<PathParams>(params);
+            req.extensions_mut().insert::<Principal>(principal);
+
+            (route.handler)(Arc::clone(&controller), req).await
+        };
+
+        if let Some(mut record) = audit_record {
+            complete_record(&mut record, &result);
+            controller.read().await.audit.record(record);
+        }
+
+        return Ok(match result {
+            Ok(response) => response,
+            Err(err) => error_response(err),
+        });
+    }
+
+    if allowed.is_empty() {
+        return Ok(error_response(EndpointError::NotFound(format!(
+            "No resource at {}",
+            req.uri().path()
+        ))));
+    }
+
+    let mut response: Response<Body> = error_response(EndpointError::MethodNotAllowed(format!(
+        "{} is not supported for {}",
+        req.method(),
+        req.uri().path()
+    )));
+    response
+        .headers_mut()
+        .insert(header::ALLOW, allowed.join(", ").parse().unwrap());
 
-    // Handle any generic errors which fell through
-    match result {
-        Ok(response) => Ok(response),
-        Err(err) => match err {
-            EndpointError::HyperError(err) => Err(err),
-            EndpointError::BadRequest(err) => Ok(Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .body(Body::from(err.to_string()))
-                .unwrap()),
-            EndpointError::InternalServerError(err) => Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from(err.to_string()))
-                .unwrap()),
-        },
+    Ok(response)
+}
+
+// Render any error as a JSON body with a matching status
+fn error_response(err: EndpointError) -> Response<Body> {
+    let result: EndpointResult = create_json_response(err.status(), &err.body());
+
+    // Serialising the error body can't fail
+    let mut response: Response<Body> = result.unwrap_or_else(|_| Response::new(Body::empty()));
+
+    if let EndpointError::Unauthorized(_) = err {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, "Bearer".parse().unwrap());
     }
+
+    response
 }
 
-type ServerError = Box<dyn std::error::Error + Send + Sync>;
+pub type ServerError = Box<dyn std::error::Error + Send + Sync>;
 
 impl CascadeServer {
-    pub async fn start(self) -> Result<(), hyper::Error> {
+    pub async fn start(self) -> Result<(), ServerError> {
         info!("Starting server on {}", &self.addr);
 
+        tokio::spawn(publish_queue_stats(
+            Arc::clone(&self.controller),
+            self.stats_period,
+        ));
+
+        if !self.authenticator.is_enabled() {
+            warn!("Authentication is disabled, every request is allowed");
+        }
+
+        let routes: Arc<Vec<Route>> = Arc::new(routes());
+        let authenticator: Arc<Authenticator> = Arc::new(self.authenticator);
+
+        if let Some(tls) = self.tls {
+            return serve_tls(self.addr, tls, self.controller, routes, authenticator).await;
+        }
+
         let service = make_service_fn(move |_| {
             let controller: Arc<RwLock<CascadeController>> = Arc::clone(&self.controller);
 
-            async { Ok::<_, ServerError>(service_fn(move |req| router(controller.clone(), req))) }
+            let routes: Arc<Vec<Route>> = Arc::clone(&routes);
+            let authenticator: Arc<Authenticator> = Arc::clone(&authenticator);
+
+            async {
+                Ok::<_, ServerError>(service_fn(move |req| {
+                    router(controller.clone(), routes.clone(), authenticator.clone(), req)
+                }))
+            }
         });
 
         let server = Server::bind(&self.addr).serve(service);
 
         info!("Server started on {}", &self.addr);
 
-        server.await
+        Ok(server.await?)
+    }
+}
+
+// Accept connections and complete the handshake of each in its own task,
+// so a slow client can't hold up the others
+async fn serve_tls(
+    addr: SocketAddr,
+    tls: Arc<ReloadingTlsAcceptor>,
+    controller: Arc<RwLock<CascadeController>>,
+    routes: Arc<Vec<Route>>,
+    authenticator: Arc<Authenticator>,
+) -> Result<(), ServerError> {
+    let listener: TcpListener = TcpListener::bind(addr).await?;
+
+    tokio::spawn(Arc::clone(&tls).watch());
+
+    info!(
+        "Server started on {} with {}",
+        addr,
+        if tls.is_mutual() { "mutual TLS" } else { "TLS" }
+    );
+
+    loop {
+        let (stream, remote) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                warn!("Failed to accept connection: {}", err);
+                continue;
+            }
+        };
+
+        let acceptor = tls.acceptor();
+        let controller: Arc<RwLock<CascadeController>> = Arc::clone(&controller);
+        let routes: Arc<Vec<Route>> = Arc::clone(&routes);
+        let authenticator: Arc<Authenticator> = Arc::clone(&authenticator);
+
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    debug!("TLS handshake with {} failed: {}", remote, err);
+                    return;
+                }
+            };
+
+            let service = service_fn(move |req| {
+                router(controller.clone(), routes.clone(), authenticator.clone(), req)
+            });
+
+            if let Err(err) = Http::new().serve_connection(stream, service).await {
+                debug!("Connection with {} failed: {}", remote, err);
+            }
+        });
     }
 }
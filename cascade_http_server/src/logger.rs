@@ -1,21 +1,334 @@
-use log::{Level, Metadata, Record};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
 
-pub struct SimpleLogger;
+use chrono::{SecondsFormat, Utc};
+use log::kv::{Key, Value, VisitSource};
+use log::{LevelFilter, Metadata, Record, SetLoggerError};
+use serde::Deserialize;
+use serde_json::json;
+
+use cascade_api::component::logger::COMPONENT_TARGET;
 
 const MISSING_MODULE: &str = "unknown_module";
 
-impl log::Log for SimpleLogger {
+// Filter spec such as info,cascade_core=debug
+pub const LOG_ENV: &str = "CASCADE_LOG";
+pub const LOG_FORMAT_ENV: &str = "CASCADE_LOG_FORMAT";
+pub const LOG_FILE_ENV: &str = "CASCADE_LOG_FILE";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format {}", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogFileConfig {
+    pub path: PathBuf,
+    // Rotate once the file grows past this size
+    pub max_bytes: Option<u64>,
+    // Amount of rotated files to keep alongside the active one
+    #[serde(default = "max_files_default")]
+    pub max_files: usize,
+}
+
+fn max_files_default() -> usize {
+    5
+}
+
+impl LogFileConfig {
+    pub fn new(path: PathBuf) -> LogFileConfig {
+        LogFileConfig {
+            path,
+            max_bytes: None,
+            max_files: max_files_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LoggerConfig {
+    #[serde(default = "level_default")]
+    pub level: LevelFilter,
+    // Levels for module or component targets, the longest matching prefix wins
+    #[serde(default)]
+    pub modules: HashMap<String, LevelFilter>,
+    #[serde(default)]
+    pub format: LogFormat,
+    // Log to a file instead of stdout
+    pub file: Option<LogFileConfig>,
+}
+
+fn level_default() -> LevelFilter {
+    LevelFilter::Info
+}
+
+impl LoggerConfig {
+    /// Level records logged under the target are filtered to
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        target_level(self.level, &self.modules, target)
+    }
+}
+
+// Level of the longest module matching the target, or the level for everything else
+fn target_level(
+    level: LevelFilter,
+    modules: &HashMap<String, LevelFilter>,
+    target: &str,
+) -> LevelFilter {
+    modules
+        .iter()
+        .filter(|(module, _)| {
+            target == module.as_str() || target.starts_with(&format!("{}::", module))
+        })
+        .max_by_key(|(module, _)| module.len())
+        .map(|(_, level)| *level)
+        .unwrap_or(level)
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        LoggerConfig {
+            level: level_default(),
+            modules: Default::default(),
+            format: Default::default(),
+            file: None,
+        }
+    }
+}
+
+impl LoggerConfig {
+    /// Parse a comma separated list of levels
+    /// A bare level sets the default and module=level sets a module level
+    pub fn apply_filter_spec(&mut self, spec: &str) -> Result<(), String> {
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => {
+                    self.modules.insert(module.to_string(), parse_level(level)?);
+                }
+                None => self.level = parse_level(directive)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    LevelFilter::from_str(level).map_err(|_| format!("Unknown log level {}", level))
+}
+
+/// File which is rotated to path.1, path.2 etc once it reaches max_bytes
+struct RotatingFile {
+    config: LogFileConfig,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn open(config: LogFileConfig) -> std::io::Result<RotatingFile> {
+        let file: File = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        let written: u64 = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            config,
+            file,
+            written,
+        })
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let path: &Path = &self.config.path;
+
+        // Shift each rotated file along, dropping the oldest
+        for idx in (1..self.config.max_files).rev() {
+            let from: PathBuf = rotated_path(path, idx);
+
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(path, idx + 1))?;
+            }
+        }
+
+        if self.config.max_files > 0 {
+            std::fs::rename(path, rotated_path(path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)?;
+        self.written = 0;
+
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if let Some(max_bytes) = self.config.max_bytes {
+            if self.written > 0 && self.written + line.len() as u64 > max_bytes {
+                self.rotate()?;
+            }
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, idx: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", idx));
+
+    PathBuf::from(rotated)
+}
+
+enum LogOutput {
+    Stdout,
+    File(RotatingFile),
+}
+
+pub struct CascadeLogger {
+    level: LevelFilter,
+    modules: HashMap<String, LevelFilter>,
+    format: LogFormat,
+
+    output: Mutex<LogOutput>,
+}
+
+impl CascadeLogger {
+    pub fn new(config: LoggerConfig) -> std::io::Result<CascadeLogger> {
+        let output: LogOutput = match config.file {
+            None => LogOutput::Stdout,
+            Some(file) => LogOutput::File(RotatingFile::open(file)?),
+        };
+
+        Ok(CascadeLogger {
+            level: config.level,
+            modules: config.modules,
+            format: config.format,
+            output: Mutex::new(output),
+        })
+    }
+
+    // Install as the global logger
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max_level: LevelFilter = self
+            .modules
+            .values()
+            .copied()
+            .chain([self.level])
+            .max()
+            .unwrap_or(self.level);
+
+        log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(max_level))
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        target_level(self.level, &self.modules, target)
+    }
+
+    fn format_record(&self, record: &Record) -> String {
+        let timestamp: String = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        let module_path: &str = record.module_path().unwrap_or(MISSING_MODULE);
+
+        match self.format {
+            LogFormat::Text => format!(
+                "{} [{}] {} - {}\n",
+                timestamp,
+                module_path,
+                record.level(),
+                record.args()
+            ),
+            LogFormat::Json => {
+                let mut fields: FieldCollector = FieldCollector::default();
+                // Fields are best effort and never stop the record being logged
+                let _ = record.key_values().visit(&mut fields);
+
+                let line = json!({
+                    "timestamp": timestamp,
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "module": module_path,
+                    "message": record.args().to_string(),
+                    "fields": fields.0,
+                });
+
+                format!("{}\n", line)
+            }
+        }
+    }
+}
+
+// Collects structured key values from a record
+#[derive(Default)]
+struct FieldCollector(BTreeMap<String, String>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        self.0.insert(key.to_string(), value.to_string());
+
+        Ok(())
+    }
+}
+
+impl log::Log for CascadeLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
+        // Components filter their own records by their level, which starts from level_for
+        if metadata.target().starts_with(&format!("{}::", COMPONENT_TARGET)) {
+            return true;
+        }
+
+        metadata.level() <= self.level_for(metadata.target())
     }
 
     fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let module_path: &str = record.module_path().unwrap_or(MISSING_MODULE);
+        if !self.enabled(record.metadata()) {
+            return;
+        }
 
-            println!("[{}] {} - {}", module_path, record.level(), record.args());
+        let line: String = self.format_record(record);
+        let mut output = self.output.lock().unwrap();
+
+        let result: std::io::Result<()> = match &mut *output {
+            LogOutput::Stdout => stdout().write_all(line.as_bytes()),
+            LogOutput::File(file) => file.write_line(&line),
+        };
+
+        // Nowhere left to log a logging failure
+        if let Err(err) = result {
+            eprintln!("Failed to write log record {}", err);
         }
     }
 
-    fn flush(&self) {}
-}
\ No newline at end of file
+    fn flush(&self) {
+        let mut output = self.output.lock().unwrap();
+
+        let _ = match &mut *output {
+            LogOutput::Stdout => stdout().flush(),
+            LogOutput::File(file) => file.file.flush(),
+        };
+    }
+}
@@ -1,49 +1,279 @@
-// Required to call trait fns dynamically
-#![feature(fn_traits)]
-#![feature(async_fn_in_trait)]
 extern crate core;
 
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::Arc;
 
-use log::LevelFilter;
+use clap::Parser;
+use hyper::Uri;
+use log::{info, LevelFilter};
+use serde_json::Value;
 use tokio::sync::RwLock;
 
-use cascade_api::component::{NamedComponent, Process};
+use cascade_api::component::NamedComponent;
+use cascade_api::component::logger::component_target;
 use cascade_component_std::generate_item::GenerateItem;
+use cascade_component_std::get_file::GetFile;
 use cascade_component_std::log_message::LogMessage;
+use cascade_component_std::put_file::PutFile;
+use cascade_component_std::route_on_content::RouteOnContent;
+use cascade_component_std::route_on_property::RouteOnProperty;
 use cascade_component_std::update_properties::UpdateProperties;
+use cascade_core::audit::{AuditLog, DEFAULT_MAX_RECORDS};
 use cascade_core::controller::CascadeController;
-use cascade_core::registry::{ComponentMap, ComponentRegistry};
-use cascade_http_server::CascadeServer;
+use cascade_core::graph::flow::FlowDefinition;
+use cascade_core::provenance::{DEFAULT_MAX_EVENTS, ProvenanceRepository};
+use cascade_core::registry::{ComponentDescriptor, ComponentMap, ComponentRegistry};
+use cascade_core::trace::{FileSpanExporter, SpanExporter};
+use cascade_http_server::auth::Authenticator;
+use cascade_http_server::{CascadeServer, ServerError};
+use cascade_http_server::tls::ReloadingTlsAcceptor;
+use cascade_http_server::trace::HttpSpanExporter;
 
-use crate::logger::SimpleLogger;
+use crate::config::{
+    Args, ConfigError, read_flow_document, read_flow_file, read_sensitive_key, ServerConfig,
+    TraceConfig,
+};
+use crate::flow_file::{edit_flow_file, FlowEdit};
+use crate::logger::CascadeLogger;
 
+mod config;
+mod flow_file;
 mod logger;
 
-static LOGGER: SimpleLogger = SimpleLogger;
-
-#[tokio::main(flavor = "multi_thread")]
-async fn main() -> Result<(), hyper::Error> {
-    log::set_logger(&LOGGER)
-        .map(|()| log::set_max_level(LevelFilter::Info))
-        .expect("Logger failed to initialise");
-
+// Every component type which can be registered
+fn available_components() -> ComponentMap {
     let mut components: ComponentMap = Default::default();
 
-    components.insert(GenerateItem::type_name(), GenerateItem::create_from_json);
-    components.insert(LogMessage::type_name(), LogMessage::create_from_json);
+    components.insert(GenerateItem::type_name(), ComponentDescriptor::of::<GenerateItem>());
+    components.insert(GetFile::type_name(), ComponentDescriptor::of::<GetFile>());
+    components.insert(LogMessage::type_name(), ComponentDescriptor::of::<LogMessage>());
+    components.insert(PutFile::type_name(), ComponentDescriptor::of::<PutFile>());
+    components.insert(
+        RouteOnContent::type_name(),
+        ComponentDescriptor::of::<RouteOnContent>(),
+    );
+    components.insert(
+        RouteOnProperty::type_name(),
+        ComponentDescriptor::of::<RouteOnProperty>(),
+    );
     components.insert(
         UpdateProperties::type_name(),
-        UpdateProperties::create_from_json,
+        ComponentDescriptor::of::<UpdateProperties>(),
     );
 
-    let controller: Arc<RwLock<CascadeController>> = Arc::new(RwLock::new(CascadeController::new(
-        ComponentRegistry::new(components),
-    )));
+    components
+}
+
+// Restrict the available components to those named, or all if none are
+fn select_components(names: &[String]) -> Result<ComponentMap, ConfigError> {
+    let mut components: ComponentMap = available_components();
+
+    if names.is_empty() {
+        return Ok(components);
+    }
+
+    if let Some(name) = names
+        .iter()
+        .find(|name| !components.contains_key(name.as_str()))
+    {
+        return Err(ConfigError::InvalidArgument(format!(
+            "Unknown component type {}",
+            name
+        )));
+    }
+
+    components.retain(|type_name, _| names.iter().any(|name| name == type_name));
+
+    Ok(components)
+}
+
+// Export spans to a collector endpoint, or otherwise a file
+fn span_exporter(config: &TraceConfig) -> Result<Option<Arc<dyn SpanExporter>>, ConfigError> {
+    if let Some(endpoint) = &config.endpoint {
+        let endpoint: Uri = endpoint.parse().map_err(|_| {
+            ConfigError::InvalidArgument(format!("Trace endpoint {} was not a valid uri", endpoint))
+        })?;
+
+        return Ok(Some(Arc::new(HttpSpanExporter::new(endpoint))));
+    }
+
+    config
+        .file
+        .as_ref()
+        .map(|path| {
+            let exporter: FileSpanExporter =
+                FileSpanExporter::new(path).map_err(|err| ConfigError::Io(path.clone(), err))?;
+
+            Ok(Arc::new(exporter) as Arc<dyn SpanExporter>)
+        })
+        .transpose()
+}
+
+// Encrypt any sensitive properties the flow file holds in plain text, so they aren't left on disk
+fn encrypt_flow_file(path: &Path, registry: &ComponentRegistry) -> Result<(), ConfigError> {
+    let document: Value = read_flow_document(path)?;
+    let mut edits: Vec<FlowEdit> = vec![];
+
+    let components = document
+        .get("components")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .enumerate();
+
+    for (index, component) in components {
+        let (Some(type_name), Some(config)) = (
+            component.get("type_name").and_then(Value::as_str),
+            component.get("config"),
+        ) else {
+            continue;
+        };
+
+        let mut protected: Value = config.clone();
+        registry
+            .protect(type_name, &mut protected, None)
+            .map_err(|err| ConfigError::Parse(path.to_path_buf(), err.to_string()))?;
+
+        // Only the encrypted values are written back, the rest of the file is left as it is
+        let Some(protected) = protected.as_object() else {
+            continue;
+        };
+
+        for (property, value) in protected {
+            if config.get(property) != Some(value) {
+                if let Some(value) = value.as_str() {
+                    edits.push(FlowEdit {
+                        component: index,
+                        property: property.clone(),
+                        value: value.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    if edits.is_empty() {
+        return Ok(());
+    }
+
+    info!("Encrypting sensitive properties in {}", path.display());
+
+    edit_flow_file(path, &edits)
+}
+
+fn create_directory(directory: &Option<PathBuf>) -> Result<(), ConfigError> {
+    match directory {
+        Some(path) => fs::create_dir_all(path).map_err(|err| ConfigError::Io(path.clone(), err)),
+        None => Ok(()),
+    }
+}
+
+async fn create_controller(config: &ServerConfig) -> Result<CascadeController, ConfigError> {
+    for directory in [&config.state_directory, &config.provenance_directory] {
+        create_directory(directory)?;
+    }
+
+    let mut registry: ComponentRegistry =
+        ComponentRegistry::new(select_components(&config.components)?);
+
+    if let Some(schedule) = &config.default_schedule {
+        registry.default_schedule = schedule.clone();
+    }
+
+    if let Some(path) = &config.sensitive_key_file {
+        registry.sensitive_key = Some(Arc::new(read_sensitive_key(path)?));
+    }
+
+    // Component levels start from whatever the logger is configured to for their type
+    let default_log_levels: HashMap<String, LevelFilter> = registry
+        .list_component_types()
+        .into_iter()
+        .map(|type_name| {
+            (
+                type_name.to_string(),
+                config.log.level_for(&component_target(type_name)),
+            )
+        })
+        .collect();
+
+    let mut controller: CascadeController = CascadeController::new(registry);
+    controller.default_log_levels = default_log_levels;
+    controller.span_exporter = span_exporter(&config.trace)?;
+    controller.state_directory = config.state_directory.clone();
+
+    if let Some(directory) = &config.provenance_directory {
+        controller.provenance = Arc::new(
+            ProvenanceRepository::open(DEFAULT_MAX_EVENTS, directory)
+                .map_err(|err| ConfigError::Io(directory.clone(), err))?,
+        );
+    }
+
+    // Refuse to start rather than lose track of changes
+    let directory: &PathBuf = &config.audit_directory;
+    fs::create_dir_all(directory).map_err(|err| ConfigError::Io(directory.clone(), err))?;
+    controller.audit = Arc::new(
+        AuditLog::open(DEFAULT_MAX_RECORDS, directory)
+            .map_err(|err| ConfigError::Io(directory.clone(), err))?,
+    );
+
+    // Parameters must be set before a flow referencing them is loaded
+    controller.parameter_contexts = config.parameter_contexts.clone();
+
+    if let Some(path) = &config.flow_file {
+        if controller.component_registry.sensitive_key.is_some() {
+            encrypt_flow_file(path, &controller.component_registry)?;
+        }
+
+        let flow: FlowDefinition = read_flow_file(path)?;
+
+        controller.load_flow(flow).await.map_err(|err| {
+            ConfigError::InvalidArgument(format!("Could not load {}: {}", path.display(), err))
+        })?;
+    }
+
+    Ok(controller)
+}
+
+fn exit_with_error(err: ConfigError) -> ! {
+    eprintln!("{}", err);
+    process::exit(1)
+}
+
+#[tokio::main(flavor = "multi_thread")]
+async fn main() -> Result<(), ServerError> {
+    let config: ServerConfig =
+        ServerConfig::load(Args::parse()).unwrap_or_else(|err| exit_with_error(err));
+
+    CascadeLogger::new(config.log.clone())
+        .expect("Log file could not be opened")
+        .init()
+        .expect("Logger failed to initialise");
+
+    let controller: CascadeController = create_controller(&config)
+        .await
+        .unwrap_or_else(|err| exit_with_error(err));
+
+    let authenticator: Authenticator = Authenticator::new(&config.auth)
+        .unwrap_or_else(|err| exit_with_error(ConfigError::InvalidArgument(err)));
+
+    let tls: Option<Arc<ReloadingTlsAcceptor>> = config
+        .tls
+        .clone()
+        .map(|tls| ReloadingTlsAcceptor::new(tls).map(Arc::new))
+        .transpose()
+        .unwrap_or_else(|err| exit_with_error(ConfigError::InvalidArgument(err.to_string())));
+
+    info!("Serving on {}", config.bind_address);
 
     let service = CascadeServer {
-        addr: "127.0.0.1:3001".parse().unwrap(),
-        controller,
+        addr: config.bind_address,
+        controller: Arc::new(RwLock::new(controller)),
+        stats_period: config.stats_period(),
+        authenticator,
+        tls,
     };
 
     service.start().await
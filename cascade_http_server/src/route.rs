@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use hyper::{Body, Method, Request, StatusCode};
+use tokio::sync::RwLock;
+
+use cascade_core::controller::CascadeController;
+
+use crate::auth::Role;
+use crate::endpoint::audit::list_audit;
+use crate::endpoint::bulletin::list_bulletins;
+use crate::endpoint::control::{
+    kill_component, purge_connection, set_log_level, start_component, stop_component,
+};
+use crate::endpoint::events::stream_events;
+use crate::endpoint::EndpointResult;
+use crate::endpoint::graph::{
+    create_component, create_connection, export_flow, get_component, get_connection, import_flow,
+    list_components, list_connections, reload_flow, remove_component, remove_connection,
+    update_component, update_connection,
+};
+use crate::endpoint::metrics::{peek_connection, stat_connection};
+use crate::endpoint::openapi::openapi_document;
+use crate::endpoint::parameter::{
+    get_parameter_context, list_parameter_contexts, remove_parameter_context,
+    set_parameter_context,
+};
+use crate::endpoint::provenance::list_provenance;
+use crate::endpoint::registry::list_available_components;
+
+pub const API_PREFIX: &str = "/api/v1";
+
+pub type Handler =
+    fn(Arc<RwLock<CascadeController>>, Request<Body>) -> BoxFuture<'static, EndpointResult>;
+
+/// Parameters captured from the path, such as {id}
+#[derive(Clone)]
+pub struct PathParams(pub HashMap<String, String>);
+
+/// An endpoint along with the details needed to document it
+pub struct Route {
+    pub method: Method,
+    // Path below the API prefix with parameters in braces
+    pub path: &'static str,
+    pub operation_id: &'static str,
+    pub tag: &'static str,
+    pub summary: &'static str,
+    // Optional query parameters
+    pub query: &'static [&'static str],
+    // Name of the JSON body the endpoint accepts
+    pub request_body: Option<&'static str>,
+    // Status returned on success
+    pub status: StatusCode,
+    // Least privileged role allowed to call the endpoint
+    pub role: Role,
+    pub handler: Handler,
+}
+
+impl Route {
+    pub fn full_path(&self) -> String {
+        format!("{}{}", API_PREFIX, self.path)
+    }
+
+    // Names of the parameters in the path
+    pub fn path_params(&self) -> impl Iterator<Item = &'static str> {
+        self.path
+            .split('/')
+            .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+    }
+
+    // Parameters captured from the path if it matches this route
+    pub fn matches(&self, path: &str) -> Option<PathParams> {
+        let path: &str = path.strip_prefix(API_PREFIX)?;
+
+        let expected: Vec<&str> = self.path.split('/').collect();
+        let actual: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+
+        if expected.len() != actual.len() {
+            return None;
+        }
+
+        let mut params: HashMap<String, String> = HashMap::new();
+
+        for (expected, actual) in expected.into_iter().zip(actual) {
+            match expected.strip_prefix('{').and_then(|name| name.strip_suffix('}')) {
+                Some(name) if !actual.is_empty() => {
+                    params.insert(name.to_string(), actual.to_string());
+                }
+                None if expected == actual => {}
+                _ => return None,
+            }
+        }
+
+        Some(PathParams(params))
+    }
+}
+
+/// Every endpoint served under the API prefix
+pub fn routes() -> Vec<Route> {
+    vec![
+        // Retrieve information from the component registry
+        Route {
+            method: Method::GET,
+            path: "/component-types",
+            operation_id: "listComponentTypes",
+            tag: "registry",
+            summary: "List the component types which can be created",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(list_available_components(c, r)),
+        },
+        // Modify components in the graph
+        Route {
+            method: Method::GET,
+            path: "/components",
+            operation_id: "listComponents",
+            tag: "components",
+            summary: "List components with their status and recent bulletins",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(list_components(c, r)),
+        },
+        Route {
+            method: Method::POST,
+            path: "/components",
+            operation_id: "createComponent",
+            tag: "components",
+            summary: "Create a component",
+            query: &[],
+            request_body: Some("ComponentDefinition"),
+            status: StatusCode::CREATED,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(create_component(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/components/{id}",
+            operation_id: "getComponent",
+            tag: "components",
+            summary: "Describe a component",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(get_component(c, r)),
+        },
+        Route {
+            method: Method::PATCH,
+            path: "/components/{id}",
+            operation_id: "updateComponent",
+            tag: "components",
+            summary: "Update the name, schedule or config of a component and list what changed",
+            query: &["restart"],
+            request_body: Some("ComponentUpdate"),
+            status: StatusCode::OK,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(update_component(c, r)),
+        },
+        Route {
+            method: Method::DELETE,
+            path: "/components/{id}",
+            operation_id: "removeComponent",
+            tag: "components",
+            summary: "Remove a component which isn't running along with its connections",
+            query: &[],
+            request_body: None,
+            status: StatusCode::NO_CONTENT,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(remove_component(c, r)),
+        },
+        // Control of individual components
+        Route {
+            method: Method::POST,
+            path: "/components/{id}/start",
+            operation_id: "startComponent",
+            tag: "components",
+            summary: "Start a component",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(start_component(c, r)),
+        },
+        Route {
+            method: Method::POST,
+            path: "/components/{id}/stop",
+            operation_id: "stopComponent",
+            tag: "components",
+            summary: "Stop a component once its current invocation finishes",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(stop_component(c, r)),
+        },
+        Route {
+            method: Method::POST,
+            path: "/components/{id}/kill",
+            operation_id: "killComponent",
+            tag: "components",
+            summary: "Stop a component immediately",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(kill_component(c, r)),
+        },
+        Route {
+            method: Method::PUT,
+            path: "/components/{id}/log-level",
+            operation_id: "setLogLevel",
+            tag: "components",
+            summary: "Set the log level of a component",
+            query: &[],
+            request_body: Some("LogLevel"),
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(set_log_level(c, r)),
+        },
+        // Modify connections in the graph
+        Route {
+            method: Method::GET,
+            path: "/connections",
+            operation_id: "listConnections",
+            tag: "connections",
+            summary: "List connections with the state of their queues",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(list_connections(c, r)),
+        },
+        Route {
+            method: Method::POST,
+            path: "/connections",
+            operation_id: "createConnection",
+            tag: "connections",
+            summary: "Connect two components",
+            query: &[],
+            request_body: Some("ConnectionDefinition"),
+            status: StatusCode::CREATED,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(create_connection(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/connections/{id}",
+            operation_id: "getConnection",
+            tag: "connections",
+            summary: "Describe a connection",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(get_connection(c, r)),
+        },
+        Route {
+            method: Method::PATCH,
+            path: "/connections/{id}",
+            operation_id: "updateConnection",
+            tag: "connections",
+            summary: "Update the settings or target of a connection keeping its queued messages",
+            query: &["restart"],
+            request_body: Some("ConnectionUpdate"),
+            status: StatusCode::OK,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(update_connection(c, r)),
+        },
+        Route {
+            method: Method::DELETE,
+            path: "/connections/{id}",
+            operation_id: "removeConnection",
+            tag: "connections",
+            summary: "Remove a connection between components which aren't running",
+            query: &[],
+            request_body: None,
+            status: StatusCode::NO_CONTENT,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(remove_connection(c, r)),
+        },
+        // Messages queued on connections
+        Route {
+            method: Method::GET,
+            path: "/connections/{id}/stats",
+            operation_id: "statConnection",
+            tag: "connections",
+            summary: "Describe the queue of a connection",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(stat_connection(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/connections/{id}/messages",
+            operation_id: "peekConnection",
+            tag: "connections",
+            summary: "List the next queued messages without removing them",
+            query: &["limit"],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(peek_connection(c, r)),
+        },
+        Route {
+            method: Method::DELETE,
+            path: "/connections/{id}/messages",
+            operation_id: "purgeConnection",
+            tag: "connections",
+            summary: "Drop queued messages matching an optional filter",
+            query: &[],
+            request_body: Some("MessageFilter"),
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(purge_connection(c, r)),
+        },
+        // Whole flows
+        Route {
+            method: Method::GET,
+            path: "/flow",
+            operation_id: "exportFlow",
+            tag: "flow",
+            summary: "Export the graph as a flow",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Operator,
+            handler: |c, r| Box::pin(export_flow(c, r)),
+        },
+        Route {
+            method: Method::POST,
+            path: "/flow",
+            operation_id: "importFlow",
+            tag: "flow",
+            summary: "Add the components and connections of a flow to the graph",
+            query: &[],
+            request_body: Some("FlowDefinition"),
+            status: StatusCode::CREATED,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(import_flow(c, r)),
+        },
+        Route {
+            method: Method::PUT,
+            path: "/flow",
+            operation_id: "reloadFlow",
+            tag: "flow",
+            summary: "Replace the graph with a flow, restarting only the components it affects",
+            query: &["dry_run"],
+            request_body: Some("FlowDefinition"),
+            status: StatusCode::OK,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(reload_flow(c, r)),
+        },
+        // Values referenced from component configs
+        Route {
+            method: Method::GET,
+            path: "/parameter-contexts",
+            operation_id: "listParameterContexts",
+            tag: "parameters",
+            summary: "List parameter contexts with sensitive values masked",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(list_parameter_contexts(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/parameter-contexts/{id}",
+            operation_id: "getParameterContext",
+            tag: "parameters",
+            summary: "Describe a parameter context with sensitive values masked",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(get_parameter_context(c, r)),
+        },
+        Route {
+            method: Method::PUT,
+            path: "/parameter-contexts/{id}",
+            operation_id: "setParameterContext",
+            tag: "parameters",
+            summary: "Create or replace a parameter context, restarting components using changed values",
+            query: &[],
+            request_body: Some("ParameterContext"),
+            status: StatusCode::OK,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(set_parameter_context(c, r)),
+        },
+        Route {
+            method: Method::DELETE,
+            path: "/parameter-contexts/{id}",
+            operation_id: "removeParameterContext",
+            tag: "parameters",
+            summary: "Remove a parameter context no component refers to",
+            query: &[],
+            request_body: None,
+            status: StatusCode::NO_CONTENT,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(remove_parameter_context(c, r)),
+        },
+        // Monitoring
+        Route {
+            method: Method::GET,
+            path: "/bulletins",
+            operation_id: "listBulletins",
+            tag: "monitoring",
+            summary: "List warnings and errors raised by components",
+            query: &["component_id", "severity"],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(list_bulletins(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/provenance",
+            operation_id: "listProvenance",
+            tag: "monitoring",
+            summary: "List provenance events",
+            query: &["message_id"],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(list_provenance(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/audit",
+            operation_id: "listAudit",
+            tag: "monitoring",
+            summary: "List changes made through the API and who made them",
+            query: &["principal", "operation", "target", "since_millis", "limit"],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Admin,
+            handler: |c, r| Box::pin(list_audit(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/events",
+            operation_id: "streamEvents",
+            tag: "monitoring",
+            summary: "Stream changes as Server-Sent Events",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(stream_events(c, r)),
+        },
+        Route {
+            method: Method::GET,
+            path: "/openapi.json",
+            operation_id: "getOpenApi",
+            tag: "meta",
+            summary: "Describe this API as an OpenAPI document",
+            query: &[],
+            request_body: None,
+            status: StatusCode::OK,
+            role: Role::Viewer,
+            handler: |c, r| Box::pin(openapi_document(c, r)),
+        },
+    ]
+}
@@ -0,0 +1,213 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use log::{info, warn};
+use serde::Deserialize;
+use tokio::time::Interval;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::rustls::crypto::{CryptoProvider, ring};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::TlsAcceptor;
+
+// Only HTTP/1.1 is served
+const ALPN_HTTP1: &[u8] = b"http/1.1";
+
+fn default_reload_period_millis() -> u64 {
+    10_000
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TlsConfig {
+    // PEM certificate chain, starting with the server certificate
+    pub certificate: PathBuf,
+    // PEM private key for the server certificate
+    pub private_key: PathBuf,
+    // PEM certificates of the authorities clients must present a certificate from
+    // Client certificates are only verified when this is set
+    pub client_ca: Option<PathBuf>,
+    // How often the files are checked for changes
+    #[serde(default = "default_reload_period_millis")]
+    pub reload_period_millis: u64,
+}
+
+impl TlsConfig {
+    pub fn new(certificate: PathBuf, private_key: PathBuf) -> TlsConfig {
+        TlsConfig {
+            certificate,
+            private_key,
+            client_ca: None,
+            reload_period_millis: default_reload_period_millis(),
+        }
+    }
+
+    fn files(&self) -> Vec<&PathBuf> {
+        [&self.certificate, &self.private_key]
+            .into_iter()
+            .chain(&self.client_ca)
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum TlsError {
+    Io(PathBuf, std::io::Error),
+    NoCertificates(PathBuf),
+    NoPrivateKey(PathBuf),
+    InvalidConfig(String),
+}
+
+impl Display for TlsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TlsError::Io(path, err) => {
+                f.write_fmt(format_args!("Could not read {}: {}", path.display(), err))
+            }
+            TlsError::NoCertificates(path) => f.write_fmt(format_args!(
+                "No PEM certificates found in {}",
+                path.display()
+            )),
+            TlsError::NoPrivateKey(path) => f.write_fmt(format_args!(
+                "No PEM private key found in {}",
+                path.display()
+            )),
+            TlsError::InvalidConfig(err) => f.write_fmt(format_args!("Invalid TLS setup: {}", err)),
+        }
+    }
+}
+
+fn open(path: &Path) -> Result<BufReader<File>, TlsError> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| TlsError::Io(path.to_path_buf(), err))
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let certificates: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut open(path)?)
+        .collect::<Result<_, _>>()
+        .map_err(|err| TlsError::Io(path.to_path_buf(), err))?;
+
+    if certificates.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+
+    Ok(certificates)
+}
+
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    rustls_pemfile::private_key(&mut open(path)?)
+        .map_err(|err| TlsError::Io(path.to_path_buf(), err))?
+        .ok_or(TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+/// Build a server config from the PEM files, verifying clients if a CA is given
+pub fn load_server_config(config: &TlsConfig) -> Result<ServerConfig, TlsError> {
+    let provider: Arc<CryptoProvider> = Arc::new(ring::default_provider());
+    let invalid = |err: tokio_rustls::rustls::Error| TlsError::InvalidConfig(err.to_string());
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(invalid)?;
+
+    let builder = match &config.client_ca {
+        Some(path) => {
+            let mut roots: RootCertStore = RootCertStore::empty();
+
+            for certificate in load_certificates(path)? {
+                roots.add(certificate).map_err(invalid)?;
+            }
+
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|err| TlsError::InvalidConfig(err.to_string()))?;
+
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut server_config: ServerConfig = builder
+        .with_single_cert(
+            load_certificates(&config.certificate)?,
+            load_private_key(&config.private_key)?,
+        )
+        .map_err(invalid)?;
+    server_config.alpn_protocols = vec![ALPN_HTTP1.to_vec()];
+
+    Ok(server_config)
+}
+
+fn modified_times(config: &TlsConfig) -> Vec<Option<SystemTime>> {
+    config
+        .files()
+        .into_iter()
+        .map(|path| path.metadata().and_then(|meta| meta.modified()).ok())
+        .collect()
+}
+
+/// Accepts TLS connections with certificates which are reloaded when their files change
+pub struct ReloadingTlsAcceptor {
+    config: TlsConfig,
+    acceptor: RwLock<TlsAcceptor>,
+    modified: RwLock<Vec<Option<SystemTime>>>,
+}
+
+impl ReloadingTlsAcceptor {
+    /// This will fail if the files can't be read or don't form a valid config
+    pub fn new(config: TlsConfig) -> Result<ReloadingTlsAcceptor, TlsError> {
+        let modified: Vec<Option<SystemTime>> = modified_times(&config);
+        let acceptor: TlsAcceptor = TlsAcceptor::from(Arc::new(load_server_config(&config)?));
+
+        Ok(ReloadingTlsAcceptor {
+            config,
+            acceptor: RwLock::new(acceptor),
+            modified: RwLock::new(modified),
+        })
+    }
+
+    pub fn is_mutual(&self) -> bool {
+        self.config.client_ca.is_some()
+    }
+
+    /// Acceptor with the most recently loaded certificates
+    pub fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap().clone()
+    }
+
+    // Reload if any file has changed since it was last checked
+    // Existing certificates are kept if the new ones can't be loaded
+    fn reload_if_changed(&self) {
+        let modified: Vec<Option<SystemTime>> = modified_times(&self.config);
+
+        if *self.modified.read().unwrap() == modified {
+            return;
+        }
+
+        *self.modified.write().unwrap() = modified;
+
+        match load_server_config(&self.config) {
+            Ok(server_config) => {
+                *self.acceptor.write().unwrap() = TlsAcceptor::from(Arc::new(server_config));
+
+                info!("Reloaded TLS certificates");
+            }
+            Err(err) => warn!("Keeping previous TLS certificates: {}", err),
+        }
+    }
+
+    /// Check for changed files until the server stops
+    pub async fn watch(self: Arc<Self>) {
+        let mut interval: Interval =
+            tokio::time::interval(Duration::from_millis(self.config.reload_period_millis));
+
+        loop {
+            interval.tick().await;
+
+            self.reload_if_changed();
+        }
+    }
+}
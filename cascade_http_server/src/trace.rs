@@ -0,0 +1,58 @@
+use hyper::client::HttpConnector;
+use hyper::{header, Body, Client, Method, Request, Uri};
+use log::error;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use cascade_api::message::trace::Span;
+use cascade_core::trace::{to_otlp_json, SpanExporter};
+
+/// Posts spans as OTLP JSON to a collector, such as http://localhost:4318/v1/traces
+/// Requests are sent from a background task which batches pending spans
+pub struct HttpSpanExporter {
+    tx: UnboundedSender<Vec<Span>>,
+}
+
+impl HttpSpanExporter {
+    // Must be called from within the tokio runtime
+    pub fn new(endpoint: Uri) -> HttpSpanExporter {
+        let (tx, mut rx): (UnboundedSender<Vec<Span>>, UnboundedReceiver<Vec<Span>>) =
+            unbounded_channel();
+
+        tokio::spawn(async move {
+            let client: Client<HttpConnector> = Client::new();
+
+            while let Some(mut spans) = rx.recv().await {
+                // Combine anything else waiting into the same request
+                while let Ok(pending) = rx.try_recv() {
+                    spans.extend(pending);
+                }
+
+                let request: Request<Body> = Request::builder()
+                    .method(Method::POST)
+                    .uri(endpoint.clone())
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(to_otlp_json(&spans).to_string()))
+                    .unwrap();
+
+                match client.request(request).await {
+                    Ok(response) if !response.status().is_success() => error!(
+                        "Collector rejected {} spans with {}",
+                        spans.len(),
+                        response.status()
+                    ),
+                    Err(err) => error!("Failed to export {} spans with {}", spans.len(), err),
+                    Ok(_) => {}
+                }
+            }
+        });
+
+        HttpSpanExporter { tx }
+    }
+}
+
+impl SpanExporter for HttpSpanExporter {
+    fn export(&self, spans: Vec<Span>) {
+        // Only fails once the runtime is shutting down
+        let _ = self.tx.send(spans);
+    }
+}
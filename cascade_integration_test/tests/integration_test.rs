@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use serde_json::{json, Value};
+use tokio::sync::RwLockReadGuard;
+use tokio::time::sleep;
+
+use cascade_api::component::{NamedComponent, Process};
+use cascade_api::component::component::{Component, Schedule};
+use cascade_api::component::definition::ComponentUpdate;
+use cascade_api::component::environment::ExecutionEnvironment;
+use cascade_api::component::error::ComponentError;
+use cascade_api::component::state::ComponentState;
+use cascade_api::connection::definition::ConnectionUpdate;
+use cascade_api::connection::error::ConnectionError;
+use cascade_api::connection::queue::MessageQueue;
+use cascade_api::message::Message;
+use cascade_component_std::generate_item::GenerateItem;
+use cascade_component_std::log_message::LogMessage;
+use cascade_component_std::route_on_property::RouteOnProperty;
+use cascade_core::controller::{CascadeController, ConnectionUpdateResult};
+use cascade_core::controller::error::{
+    LoadFlowError, ParameterContextError, UpdateConnectionError,
+};
+use cascade_core::graph::CascadeGraph;
+use cascade_core::graph::flow::FlowDefinition;
+use cascade_core::parameter::ParameterContext;
+use cascade_core::registry::{ComponentDescriptor, ComponentMap, ComponentRegistry};
+
+// Takes a while to pass each message on, so can be caught mid-invocation
+struct SlowForward {
+    delay_millis: u64,
+}
+
+impl NamedComponent for SlowForward {
+    fn type_name() -> &'static str
+    where
+        Self: Sized,
+    {
+        "SlowForward"
+    }
+}
+
+#[async_trait]
+impl Process for SlowForward {
+    fn create_from_json(config: Value) -> Result<Arc<dyn Process>, ComponentError> {
+        Ok(Arc::new(SlowForward {
+            delay_millis: config["delay_millis"].as_u64().unwrap_or_default(),
+        }))
+    }
+
+    async fn process(&self, execution: &mut ExecutionEnvironment) -> Result<(), ComponentError> {
+        let item: Message = execution.recv().await?.clone();
+
+        sleep(Duration::from_millis(self.delay_millis)).await;
+
+        execution.send_default(item).await
+    }
+}
+
+fn controller() -> CascadeController {
+    let mut components: ComponentMap = Default::default();
+    components.insert(GenerateItem::type_name(), ComponentDescriptor::of::<GenerateItem>());
+    components.insert(LogMessage::type_name(), ComponentDescriptor::of::<LogMessage>());
+    components.insert(
+        RouteOnProperty::type_name(),
+        ComponentDescriptor::of::<RouteOnProperty>(),
+    );
+    components.insert(SlowForward::type_name(), ComponentDescriptor::of::<SlowForward>());
+
+    CascadeController::new(ComponentRegistry::new(components))
+}
+
+// Messages are put on input by hand and left on output, so only slow is ever started
+fn flow(slow_config: Value) -> FlowDefinition {
+    FlowDefinition::with_ids(json!({
+        "components": [
+            {
+                "id": "source",
+                "display_name": "Source",
+                "type_name": "GenerateItem",
+                "component_type": "Producer",
+                "config": { "batch_size": 1, "content": null }
+            },
+            {
+                "id": "slow",
+                "display_name": "Slow",
+                "type_name": "SlowForward",
+                "component_type": "Processor",
+                "schedule": { "type": "Unbounded" },
+                "config": slow_config
+            },
+            {
+                "id": "sink",
+                "display_name": "Sink",
+                "type_name": "LogMessage",
+                "component_type": "Processor",
+                "config": { "log_every_x": 1 }
+            }
+        ],
+        "connections": [
+            { "id": "input", "name": "default", "source": 0, "target": 1, "max_items": 10 },
+            { "id": "output", "name": "default", "source": 1, "target": 2, "max_items": 10 }
+        ]
+    }))
+    .unwrap()
+}
+
+fn context(parameters: Value) -> ParameterContext {
+    serde_json::from_value(json!({ "parameters": parameters })).unwrap()
+}
+
+async fn queue(controller: &CascadeController, id: &str) -> Arc<MessageQueue> {
+    let edge_idx: EdgeIndex = controller.graph_definition.read().await.find_edge(id).unwrap();
+
+    controller.connections.read().await[&edge_idx].queue.clone()
+}
+
+// Start slow and leave it part way through passing on a message
+async fn start_mid_invocation(controller: &mut CascadeController) -> NodeIndex {
+    let slow: NodeIndex = controller.graph_definition.read().await.find_node("slow").unwrap();
+    controller.start_component(slow).await.unwrap();
+
+    queue(controller, "input").await.push(Message::new(HashMap::new())).await;
+
+    // Give the component time to take the message and start on it
+    sleep(Duration::from_millis(50)).await;
+    assert!(queue(controller, "input").await.is_empty());
+
+    slow
+}
+
+#[tokio::test]
+async fn reload_waits_for_current_invocation() {
+    let mut controller: CascadeController = controller();
+    controller.load_flow(flow(json!({ "delay_millis": 300 }))).await.unwrap();
+    start_mid_invocation(&mut controller).await;
+
+    let restarted: Vec<String> = controller
+        .reload_flow(flow(json!({ "delay_millis": 0 })), false)
+        .await
+        .unwrap()
+        .restarted;
+
+    assert_eq!(restarted, vec!["slow".to_string()]);
+    assert_eq!(queue(&controller, "output").await.len(), 1);
+}
+
+#[tokio::test]
+async fn update_component_waits_for_current_invocation() {
+    let mut controller: CascadeController = controller();
+    controller.load_flow(flow(json!({ "delay_millis": 300 }))).await.unwrap();
+    let slow: NodeIndex = start_mid_invocation(&mut controller).await;
+
+    let update: ComponentUpdate = ComponentUpdate {
+        config: Some(json!({ "delay_millis": 0 })),
+        ..Default::default()
+    };
+    let restarted: bool = controller
+        .update_component(slow, update, true)
+        .await
+        .unwrap()
+        .restarted;
+
+    assert!(restarted);
+    assert_eq!(queue(&controller, "output").await.len(), 1);
+}
+
+#[tokio::test]
+async fn update_connection_waits_for_current_invocation() {
+    let mut controller: CascadeController = controller();
+    controller.load_flow(flow(json!({ "delay_millis": 300 }))).await.unwrap();
+    let slow: NodeIndex = start_mid_invocation(&mut controller).await;
+
+    let (input, sink): (EdgeIndex, NodeIndex) = {
+        let graph: RwLockReadGuard<CascadeGraph> = controller.graph_definition.read().await;
+
+        (graph.find_edge("input").unwrap(), graph.find_node("sink").unwrap())
+    };
+
+    // Redirecting the input away from slow rewires it
+    let update: ConnectionUpdate = ConnectionUpdate {
+        target: Some(sink.index()),
+        ..Default::default()
+    };
+    let restarted: Vec<usize> = controller
+        .update_connection(input, update, true)
+        .await
+        .unwrap()
+        .restarted;
+
+    assert_eq!(restarted, vec![slow.index()]);
+    assert_eq!(queue(&controller, "output").await.len(), 1);
+}
+
+#[tokio::test]
+async fn set_parameter_context_waits_for_current_invocation() {
+    let mut flow: FlowDefinition = flow(json!({ "delay_millis": "#{delay}" }));
+    flow.components[1].parameter_context = Some("timing".to_string());
+
+    let mut controller: CascadeController = controller();
+    controller.load_flow(flow).await.unwrap();
+    controller
+        .set_parameter_context("timing", context(json!({ "delay": { "value": 300 } })))
+        .await
+        .unwrap();
+    start_mid_invocation(&mut controller).await;
+
+    let (_, restarted): (Option<ParameterContext>, Vec<String>) = controller
+        .set_parameter_context("timing", context(json!({ "delay": { "value": 0 } })))
+        .await
+        .unwrap();
+
+    assert_eq!(restarted, vec!["slow".to_string()]);
+    assert_eq!(queue(&controller, "output").await.len(), 1);
+}
+
+#[tokio::test]
+async fn masked_parameter_needs_a_value_to_keep() {
+    let mut controller: CascadeController = controller();
+    let masked: Value = json!({ "secret": { "value": "********", "sensitive": true } });
+
+    let result: Result<(Option<ParameterContext>, Vec<String>), ParameterContextError> =
+        controller.set_parameter_context("secrets", context(masked.clone())).await;
+    assert!(matches!(result, Err(ParameterContextError::MaskedValue(name)) if name == "secret"));
+
+    let secret: Value = json!({ "secret": { "value": "hunter2", "sensitive": true } });
+    controller.set_parameter_context("secrets", context(secret)).await.unwrap();
+    controller.set_parameter_context("secrets", context(masked)).await.unwrap();
+
+    assert_eq!(controller.parameter_contexts["secrets"].parameters["secret"].value, "hunter2");
+}
+
+#[tokio::test]
+async fn route_condition_which_fails_is_unmatched() {
+    let flow: FlowDefinition = FlowDefinition::with_ids(json!({
+        "components": [
+            {
+                "id": "source",
+                "display_name": "Source",
+                "type_name": "GenerateItem",
+                "component_type": "Producer",
+                "config": { "batch_size": 1, "content": null }
+            },
+            {
+                "id": "router",
+                "display_name": "Router",
+                "type_name": "RouteOnProperty",
+                "component_type": "Processor",
+                "schedule": { "type": "Unbounded" },
+                "config": { "routes": [{ "output": "large", "condition": "${size * 2 > 10}" }] }
+            },
+            {
+                "id": "sink",
+                "display_name": "Sink",
+                "type_name": "LogMessage",
+                "component_type": "Processor",
+                "config": { "log_every_x": 1 }
+            }
+        ],
+        "connections": [
+            { "id": "input", "name": "default", "source": 0, "target": 1, "max_items": 10 },
+            { "id": "large", "name": "large", "source": 1, "target": 2, "max_items": 10 },
+            { "id": "unmatched", "name": "unmatched", "source": 1, "target": 2, "max_items": 10 }
+        ]
+    }))
+    .unwrap();
+
+    let mut controller: CascadeController = controller();
+    controller.load_flow(flow).await.unwrap();
+
+    let router: NodeIndex = controller.graph_definition.read().await.find_node("router").unwrap();
+    controller.start_component(router).await.unwrap();
+
+    for size in ["100", "abc"] {
+        let message: Message = Message::new(HashMap::from([("size".to_string(), size.to_string())]));
+        queue(&controller, "input").await.push(message).await;
+    }
+
+    sleep(Duration::from_millis(100)).await;
+
+    let large: Vec<Message> = queue(&controller, "large").await.peek(10);
+    let unmatched: Vec<Message> = queue(&controller, "unmatched").await.peek(10);
+
+    assert_eq!(large.len(), 1);
+    assert_eq!(large[0].properties["size"], "100");
+    assert_eq!(unmatched.len(), 1);
+    assert_eq!(unmatched[0].properties["size"], "abc");
+}
+
+#[tokio::test]
+async fn connection_without_capacity_is_rejected() {
+    let mut flow: FlowDefinition = flow(json!({ "delay_millis": 0 }));
+    flow.connections[0].max_items = 0;
+
+    let mut controller: CascadeController = controller();
+    let result: Result<(), LoadFlowError> = controller.load_flow(flow).await;
+
+    assert!(matches!(
+        result,
+        Err(LoadFlowError::InvalidConnection(ConnectionError::ZeroCapacity(id))) if id == "input"
+    ));
+    assert_eq!(controller.graph_definition.read().await.graph_internal.node_count(), 0);
+}
+
+#[tokio::test]
+async fn update_leaving_connection_without_capacity_is_rejected() {
+    let mut controller: CascadeController = controller();
+    controller.load_flow(flow(json!({ "delay_millis": 0 }))).await.unwrap();
+    let input: EdgeIndex = controller.graph_definition.read().await.find_edge("input").unwrap();
+
+    let update: ConnectionUpdate = ConnectionUpdate {
+        max_items: Some(0),
+        ..Default::default()
+    };
+    let result: Result<ConnectionUpdateResult, UpdateConnectionError> =
+        controller.update_connection(input, update, true).await;
+
+    assert!(matches!(
+        result,
+        Err(UpdateConnectionError::InvalidConnection(ConnectionError::ZeroCapacity(_)))
+    ));
+    assert_eq!(controller.graph_definition.read().await.graph_internal[input].max_items, 10);
+}
+
+#[test]
+fn definitions_without_a_schedule_use_the_registry_default() {
+    let mut registry: ComponentRegistry = controller().component_registry;
+    registry.default_schedule = Schedule::Unbounded { concurrency: 2 };
+
+    let flow: FlowDefinition = flow(json!({ "delay_millis": 0 }));
+    let source: Component = registry.get_component(&flow.components[0]).unwrap();
+    let slow: Component = registry.get_component(&flow.components[1]).unwrap();
+
+    assert!(matches!(source.schedule, Schedule::Unbounded { concurrency: 2 }));
+    assert!(matches!(slow.schedule, Schedule::Unbounded { concurrency: 1 }));
+}
+
+#[test]
+fn saved_state_is_loaded_back() {
+    let directory: PathBuf = std::env::temp_dir().join(format!("cascade-state-{}", process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    let state: ComponentState = ComponentState::new(Some(&directory), "component");
+    state.save(&json!({ "offset": 42 })).unwrap();
+    let loaded: Option<Value> = state.load().unwrap();
+
+    assert_eq!(loaded, Some(json!({ "offset": 42 })));
+    assert!(!directory.join("component.json.tmp").exists());
+
+    fs::remove_dir_all(&directory).unwrap();
+}